@@ -0,0 +1,89 @@
+//! Lexing throughput on generated sources of growing size, run with `cargo bench --bench scanner`.
+//! The time per byte should stay flat as the input grows if the scanner is linear, while
+//! re-lexing a one character edit should barely depend on the size at all.
+
+use std::{hint::black_box, time::Instant};
+
+use calcify::scanner::{Scanner, TextEdit};
+
+const CHUNK: &str = r#"
+struct Point<T:Add>{x: T, y: T}
+
+enum Shape{
+    Circle{center: Point<Float>, radius: Float},
+    Rect{min: Point<Float>, max: Point<Float>},
+}
+
+// the area of any shape, non-ascii in comments should not matter: ünïcødé
+fn area(shape: Shape) -> Float {
+    let mut total = 0.0;
+    if let Shape.Circle{radius: r} shape {
+        total = 3.14159 * r * r;
+    }
+    while total >= 100 {
+        total = total - 1;
+    }
+    return total;
+}
+
+impl Add for Point<Int> {
+    fn add(self: Self, other: Self) -> Self {
+        Point{x: self.x + other.x, y: self.y + other.y}
+    }
+}
+"#;
+
+fn main() {
+    println!("{:>10} {:>12} {:>12} {:>12} {:>10}", "lines", "bytes", "tokens", "MB/s", "ns/byte");
+    for repeats in [100, 1_000, 10_000] {
+        let source = CHUNK.repeat(repeats);
+        let lines = source.lines().count();
+        let bytes = source.len();
+        let runs = 5;
+        let mut best = f64::MAX;
+        let mut token_count = 0;
+        for _ in 0..runs {
+            let now = Instant::now();
+            let tokens = black_box(Scanner::scan(&source));
+            best = best.min(now.elapsed().as_secs_f64());
+            token_count = tokens.len();
+        }
+        println!(
+            "{:>10} {:>12} {:>12} {:>12.2} {:>10.2}",
+            lines,
+            bytes,
+            token_count,
+            bytes as f64 / best / 1_000_000.0,
+            best * 1_000_000_000.0 / bytes as f64,
+        );
+    }
+    println!();
+    println!("{:>10} {:>12} {:>12} {:>12}", "lines", "bytes", "full µs", "edit µs");
+    for repeats in [100, 1_000, 10_000] {
+        let source = CHUNK.repeat(repeats);
+        let old = Scanner::scan(&source);
+        let middle = source.len() / 2;
+        let middle = source[middle..].find("total").map_or(middle, |offset| middle + offset);
+        let edit = TextEdit::new(middle..middle, "sub");
+        let edited = edit.apply(&source);
+        let (mut full, mut incremental) = (f64::MAX, f64::MAX);
+        for _ in 0..5 {
+            let now = Instant::now();
+            let tokens = black_box(Scanner::scan(&edited));
+            full = full.min(now.elapsed().as_secs_f64());
+            drop(tokens);
+            let tokens = old.clone();
+            let now = Instant::now();
+            let tokens = black_box(Scanner::new(&edited, 0).relex(tokens, &edit));
+            incremental = incremental.min(now.elapsed().as_secs_f64());
+            drop(tokens);
+        }
+        println!(
+            "{:>10} {:>12} {:>12.1} {:>12.1}",
+            source.lines().count(),
+            source.len(),
+            full * 1_000_000.0,
+            incremental * 1_000_000.0,
+        );
+    }
+}
@@ -0,0 +1,359 @@
+//! A lossless concrete syntax tree that sits under the `Parsable` AST.
+//!
+//! The parser records the kind and span of every node it finishes, and the scanner in lossless
+//! mode keeps whitespace and comments, so putting the two together gives a tree that holds every
+//! byte of the file. The green tree is immutable and position independent so it can be shared,
+//! the red `SyntaxNode` layer on top of it adds parents and absolute offsets.
+
+use std::{fmt::Display, rc::Rc};
+
+use crate::scanner::{Scanner, Span, Token, TokenType};
+
+use super::{
+    decl::Program,
+    parser::{ParseError, TokenStream},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    SourceFile,
+    //decl
+    StructDecl,
+    EnumDecl,
+    FunctionDecl,
+    TraitDecl,
+    ImplDecl,
+    ModDecl,
+    UseDecl,
+    ConstDecl,
+    StaticDecl,
+    TypeAliasDecl,
+    FuncSig,
+    Parameter,
+    VarientDecl,
+    FieldDecl,
+    GenericDecl,
+    TraitType,
+    SolidType,
+    UnitType,
+    ArrayType,
+    FunctionPointer,
+    DynamicType,
+    TupleType,
+    Visibility,
+    //statments
+    Block,
+    VarCreation,
+    If,
+    IfLet,
+    While,
+    For,
+    Return,
+    ImReturn,
+    Continue,
+    Break,
+    Panic,
+    Pattern,
+    WildcardPattern,
+    BindingPattern,
+    AtPattern,
+    LiteralPattern,
+    OrPattern,
+    TuplePattern,
+    FieldPattern,
+    Match,
+    MatchArm,
+    Closure,
+    //expresions
+    IntLitteral,
+    FloatLitteral,
+    StringLitteral,
+    CharLitteral,
+    BoolLitteral,
+    VarAccess,
+    TypeAssocatedFunction,
+    FunctionCall,
+    FieldAccess,
+    Index,
+    Constructor,
+    StructConstructor,
+    EnumConstructor,
+    ArrayConstructor,
+    FieldConstructor,
+    Cast,
+    Parens,
+    Tuple,
+    Unary,
+    Binary,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenToken {
+    kind: TokenType,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    text_len: usize,
+    children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenToken {
+    pub fn kind(&self) -> TokenType {
+        self.kind
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        Self {
+            kind,
+            text_len: children.iter().map(GreenElement::text_len).sum(),
+            children,
+        }
+    }
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+    pub fn text_len(&self) -> usize {
+        self.text_len
+    }
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+impl GreenElement {
+    pub fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+impl Display for GreenNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.children.iter().try_for_each(|child| match child {
+            GreenElement::Node(node) => node.fmt(f),
+            GreenElement::Token(token) => f.write_str(&token.text),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct SyntaxData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+/// A cursor into the green tree that knows where it is in the file and who its parent is.
+#[derive(Debug, Clone)]
+pub struct SyntaxNode(Rc<SyntaxData>);
+
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(SyntaxData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+    /// The byte range this node covers, including any trivia inside it.
+    pub fn text_range(&self) -> (usize, usize) {
+        (self.0.offset, self.0.offset + self.0.green.text_len)
+    }
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children
+            .iter()
+            .map(|child| {
+                let element = match child {
+                    GreenElement::Node(node) => SyntaxElement::Node(SyntaxNode(Rc::new(SyntaxData {
+                        green: node.clone(),
+                        offset,
+                        parent: Some(self.clone()),
+                    }))),
+                    GreenElement::Token(token) => SyntaxElement::Token(SyntaxToken {
+                        green: token.clone(),
+                        offset,
+                        parent: self.clone(),
+                    }),
+                };
+                offset += child.text_len();
+                element
+            })
+            .collect()
+    }
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+    /// This node and every node under it, in source order.
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut found = vec![self.clone()];
+        for child in self.children() {
+            found.append(&mut child.descendants());
+        }
+        found
+    }
+    /// The deepest node covering the byte at `offset`.
+    pub fn covering_node(&self, offset: usize) -> SyntaxNode {
+        self.children()
+            .into_iter()
+            .find(|child| {
+                let (start, end) = child.text_range();
+                start <= offset && offset < end
+            })
+            .map(|child| child.covering_node(offset))
+            .unwrap_or_else(|| self.clone())
+    }
+}
+
+impl Display for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.green.fmt(f)
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> TokenType {
+        self.green.kind
+    }
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+    pub fn text_range(&self) -> (usize, usize) {
+        (self.offset, self.offset + self.green.text.len())
+    }
+}
+
+/// The output of a lossless parse, the tree always holds the whole file even where the
+/// AST has gaps from syntax errors.
+#[derive(Debug)]
+pub struct LosslessParse {
+    pub cst: SyntaxNode,
+    pub program: Program,
+    pub errors: Vec<ParseError>,
+}
+
+/// Scans with trivia kept, parses the AST and builds the CST from what the parser recorded.
+pub fn parse_lossless(source: &str, file_id: usize) -> LosslessParse {
+    let all_tokens = Scanner::scan_lossless(source, file_id);
+    let significant = all_tokens
+        .iter()
+        .filter(|token| !token.is_trivia())
+        .cloned()
+        .collect::<Vec<Token>>();
+    let mut stream = TokenStream::recording(&significant);
+    let (program, errors) = Program::parse(&mut stream);
+    let green = build_tree(&all_tokens, stream.take_nodes());
+    LosslessParse {
+        cst: SyntaxNode::new_root(Rc::new(green)),
+        program,
+        errors,
+    }
+}
+
+/// Nests `tokens` under the recorded `nodes`, a node owns every token inside its span and any
+/// trivia between them, trivia on either side of it goes to the parent.
+pub fn build_tree(tokens: &[Token], mut nodes: Vec<(SyntaxKind, Span)>) -> GreenNode {
+    // children are recorded before their parents, so when two nodes cover the same span the
+    // later one is the outer one
+    let mut nodes = nodes
+        .drain(..)
+        .enumerate()
+        .filter(|(_, (_, span))| !span.is_empty())
+        .collect::<Vec<_>>();
+    nodes.sort_by_key(|(order, (_, span))| {
+        (
+            span.byte_start,
+            std::cmp::Reverse(span.byte_end),
+            std::cmp::Reverse(*order),
+        )
+    });
+    let mut nodes = nodes.into_iter().map(|(_, node)| node).peekable();
+    let mut stack: Vec<(SyntaxKind, usize, Vec<GreenElement>)> =
+        vec![(SyntaxKind::SourceFile, usize::MAX, vec![])];
+    fn close(stack: &mut Vec<(SyntaxKind, usize, Vec<GreenElement>)>) {
+        let (kind, _, children) = stack.pop().expect("the root is never closed here");
+        stack
+            .last_mut()
+            .expect("the root is never closed here")
+            .2
+            .push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+    for token in tokens {
+        let position = token.span.byte_start;
+        if !token.is_trivia() {
+            while stack.len() > 1 && stack.last().is_some_and(|(_, end, _)| *end <= position) {
+                close(&mut stack);
+            }
+            while let Some((kind, span)) = nodes.next_if(|(_, span)| span.byte_start <= position) {
+                stack.push((kind, span.byte_end, vec![]));
+            }
+        }
+        stack
+            .last_mut()
+            .expect("the root is never closed here")
+            .2
+            .push(GreenElement::Token(Rc::new(GreenToken {
+                kind: token.token_type,
+                text: token.lexeme.clone(),
+            })));
+        if !token.is_trivia() {
+            while stack.len() > 1 && stack.last().is_some_and(|(_, end, _)| *end <= token.span.byte_end) {
+                close(&mut stack);
+            }
+        }
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    let (kind, _, children) = stack.pop().expect("the root is never closed here");
+    GreenNode::new(kind, children)
+}
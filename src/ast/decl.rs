@@ -1,15 +1,16 @@
-use std::{iter::Peekable, slice::Iter};
-
 use pub_fields::pub_fields;
 
-use crate::scanner::{Token, TokenType};
+use crate::scanner::{ContextualKeyword, Span, Token, TokenType};
 
 use super::{
-    parser::{Parsable, ParseError, ParseResult, TokenExt, Wrapper}, statments::{Statment,Block},
+    cst::SyntaxKind,
+    expresions::Expresion,
+    parser::{Parsable, ParseError, ParseResult, Spanned, TokenExt, TokenStream, ITEM_SYNC}, statments::Block,
 };
 #[derive(Debug, Clone)]
 #[pub_fields]
 pub struct Program {
+    docs: Vec<Token>, //InnerDocComment
     code: Vec<Declaration>,
 }
 #[derive(Debug, Clone)]
@@ -20,6 +21,84 @@ pub enum Declaration {
     FunctionDecl(FunctionDecl),
     TraitDecl(TraitDecl),
     ImplDecl(ImplDecl),
+    ModDecl(ModDecl),
+    UseDecl(UseDecl),
+    ConstDecl(ConstDecl),
+    StaticDecl(StaticDecl),
+    TypeAliasDecl(TypeAliasDecl),
+}
+
+/// `const NAME: Type = value;`, the value is worked out while compiling and put in
+/// wherever the name is used.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct ConstDecl {
+    docs: Vec<Token>, //DocComment
+    visibility: Visibility,
+    name: Token, //Ident
+    type_of: Type,
+    value: Expresion,
+    span: Span,
+}
+
+/// `static NAME: Type = value;`, one value kept for the whole run and read wherever the
+/// name is used. Its starting value is worked out while compiling like a constant's, and
+/// only a `static mut` can be assigned to.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct StaticDecl {
+    docs: Vec<Token>, //DocComment
+    visibility: Visibility,
+    mutable: bool,
+    name: Token, //Ident
+    type_of: Type,
+    value: Expresion,
+    span: Span,
+}
+
+/// `type Name<T> = Type;`, another name for a type. Uses of it are swapped for the type it
+/// names, with the generics filled in, before the types are made into IR.
+///
+/// `type Name<T> = new Type;` is a newtype instead, a struct of its own with the type in its
+/// one field, `value`, that can't be used in place of the type or the other way around.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct TypeAliasDecl {
+    docs: Vec<Token>, //DocComment
+    visibility: Visibility,
+    name: Token, //Ident
+    generics: Vec<GenericDecl>,
+    new: bool, //written with `new` before the type
+    type_of: Type,
+    span: Span,
+}
+
+/// `mod name;`, the module's items are in `name.cal` or `name/mod.cal` beside the file.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct ModDecl {
+    docs: Vec<Token>, //DocComment
+    name: Token, //Ident
+    span: Span,
+}
+
+/// `use a::b::Name;`, lets `Name` be used on its own.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct UseDecl {
+    path: Vec<Token>, //Ident, the last is the name brought in
+    span: Span,
+}
+
+/// Who can see an item, a field or a method. With nothing in front of it only the module it
+/// is declared in can, `pub(module)` lets the modules inside that one see it too, and `pub`
+/// lets every module. The fields of a varient are as visible as their enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visibility {
+    #[default]
+    Private,
+    Module,
+    Public,
 }
 #[derive(Debug, Clone)]
 
@@ -29,14 +108,19 @@ pub struct ImplDecl {
     trait_to_impl: Option<Type>,
     type_to_impl_on: Type,
     funcs: Vec<FunctionDecl>,
+    consts: Vec<ConstDecl>,
+    span: Span,
 }
 #[derive(Debug, Clone)]
 
 #[pub_fields]
 pub struct TraitDecl {
+    docs: Vec<Token>, //DocComment
+    visibility: Visibility,
     name: Token, //Ident
     generics: Vec<GenericDecl>,
     funcs: Vec<FuncSig>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -47,20 +131,27 @@ pub struct FuncSig {
     generics: Vec<GenericDecl>,
     parameters: Vec<(Token, Type)>, //Ident
     out: Type,
+    span: Span,
 }
 #[derive(Debug, Clone)]
 #[pub_fields]
 pub struct FunctionDecl {
+    docs: Vec<Token>, //DocComment
+    visibility: Visibility,
     sig: FuncSig,
     body: Block,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
 #[pub_fields]
 pub struct EnumDecl {
+    docs: Vec<Token>, //DocComment
+    visibility: Visibility,
     name: Token, //Ident
     generics: Vec<GenericDecl>,
     varients: Vec<VarientDecl>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -68,14 +159,18 @@ pub struct EnumDecl {
 pub struct VarientDecl {
     name: Token, //Ident
     fields: Vec<FieldDecl>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
 #[pub_fields]
 pub struct StructDecl {
+    docs: Vec<Token>, //DocComment
+    visibility: Visibility,
     name: Token, //Ident
     generics: Vec<GenericDecl>,
     fields: Vec<FieldDecl>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -83,29 +178,35 @@ pub struct StructDecl {
 pub struct GenericDecl {
     name: Token, //Ident
     constraints: Vec<TraitType>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
 #[pub_fields]
 pub struct TraitType{
+    path: Vec<Token>, //Ident, the modules the trait is in
     name: Token, //Ident or Self
     generics: Vec<Type>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
 #[pub_fields]
 pub struct FieldDecl {
+    visibility: Visibility,
     name: Token, //Ident
     type_of: Type,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum Type{
     PhysicalType(SolidType),
-    Unit,
-    Array(SolidType),
+    Unit(Span),
+    Array(SolidType, Span),
     FP(FunctionPointer),
-    DynamicType(Vec<TraitType>)
+    DynamicType(Vec<TraitType>, Span),
+    Tuple(Vec<Type>, Span), // `(A, B)`, or `(A,)` with one
 } 
 
 
@@ -114,44 +215,102 @@ pub enum Type{
 pub struct FunctionPointer{
     args:Vec<Type>,
     out:Box<Type>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
 #[pub_fields]
 pub struct SolidType{
+    path: Vec<Token>, //Ident, the modules the type is in
     name: Token, //Ident or Self
     generics: Vec<Type>,
+    span: Span,
 }
 
-impl Parsable for Program {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
-        Ok(Self {
-            code: {
-                let mut code = vec![];
-                while let Some(Token { token_type, .. }) = tokens.peek() {
-                    if TokenType::EOF == *token_type {
-                        break;
-                    };
-                    code.push(Declaration::parse(tokens)?);
+impl Program {
+    /// Parses the whole file, carrying on past syntax errors so all of them are found in
+    /// one go. The program holds every item that parsed.
+    pub fn parse(tokens: &mut TokenStream) -> (Self, Vec<ParseError>) {
+        let docs = tokens.inner_docs();
+        let mut code = vec![];
+        while let Some(Token { token_type, .. }) = tokens.peek() {
+            if TokenType::EOF == *token_type {
+                break;
+            };
+            let from = tokens.mark();
+            match Declaration::parse(tokens) {
+                Ok(declaration) => code.push(declaration),
+                Err(err) => {
+                    tokens.report(err);
+                    tokens.recover(from, &ITEM_SYNC);
                 }
-                code
-            },
-        })
+            }
+        }
+        (Self { docs, code }, tokens.take_errors())
+    }
+}
+
+impl Spanned for Declaration {
+    fn span(&self) -> Span {
+        match self {
+            Declaration::StructDecl(struct_) => struct_.span,
+            Declaration::EnumDecl(enum_) => enum_.span,
+            Declaration::FunctionDecl(function) => function.span,
+            Declaration::TraitDecl(trait_) => trait_.span,
+            Declaration::ImplDecl(impl_) => impl_.span,
+            Declaration::ModDecl(mod_) => mod_.span,
+            Declaration::UseDecl(use_) => use_.span,
+            Declaration::ConstDecl(const_) => const_.span,
+            Declaration::StaticDecl(static_) => static_.span,
+            Declaration::TypeAliasDecl(alias) => alias.span,
+        }
+    }
+}
+
+impl Spanned for Type {
+    fn span(&self) -> Span {
+        match self {
+            Type::PhysicalType(solid_type) => solid_type.span,
+            Type::Unit(span) => *span,
+            Type::Array(_, span) => *span,
+            Type::FP(function_pointer) => function_pointer.span,
+            Type::DynamicType(_, span) => *span,
+            Type::Tuple(_, span) => *span,
+        }
     }
 }
 
 impl Parsable for Declaration {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
         Ok({
-            let token = tokens.peek().expect(
-                "The only place this can be called we explicitly check that there is another token",
-            );
+            let token = tokens.peek_token()?;
+            // the item is told apart by what comes after its visibility
+            let token = if token.token_type == TokenType::Pub {
+                let mut ahead = tokens.fork();
+                Visibility::parse(&mut ahead)?;
+                let token = ahead.peek_token()?;
+                if matches!(token.token_type, TokenType::Impl | TokenType::Mod | TokenType::Use) {
+                    return Err(ParseError {
+                        expected: vec![TokenType::Struct, TokenType::Enum, TokenType::Fn, TokenType::Trait, TokenType::Const, TokenType::Static, TokenType::Type],
+                        got: Box::new(token.clone()),
+                        note: Some(format!("`{}` can't be `pub`", token.lexeme)),
+                    });
+                }
+                token
+            } else {
+                token
+            };
             match token.token_type {
                 TokenType::Struct => Self::StructDecl(StructDecl::parse(tokens)?),
                 TokenType::Enum => Self::EnumDecl(EnumDecl::parse(tokens)?),
                 TokenType::Fn => Self::FunctionDecl(FunctionDecl::parse(tokens)?),
                 TokenType::Trait => Self::TraitDecl(TraitDecl::parse(tokens)?),
                 TokenType::Impl => Self::ImplDecl(ImplDecl::parse(tokens)?),
+                TokenType::Mod => Self::ModDecl(ModDecl::parse(tokens)?),
+                TokenType::Use => Self::UseDecl(UseDecl::parse(tokens)?),
+                TokenType::Const => Self::ConstDecl(ConstDecl::parse(tokens)?),
+                TokenType::Static => Self::StaticDecl(StaticDecl::parse(tokens)?),
+                TokenType::Type => Self::TypeAliasDecl(TypeAliasDecl::parse(tokens)?),
                 _ => Err(ParseError {
                     expected: vec![
                         TokenType::Struct,
@@ -159,8 +318,15 @@ impl Parsable for Declaration {
                         TokenType::Fn,
                         TokenType::Trait,
                         TokenType::Impl,
+                        TokenType::Mod,
+                        TokenType::Use,
+                        TokenType::Const,
+                        TokenType::Static,
+                        TokenType::Type,
+                        TokenType::Pub,
                     ],
-                    got: (*token).clone(),
+                    got: Box::new((*token).clone()),
+                    note: None,
                 })?,
             }
         })
@@ -168,8 +334,8 @@ impl Parsable for Declaration {
 }
 
 impl Parsable for ImplDecl {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> Result<Self, ParseError> {
-        tokens.consume(TokenType::Impl)?;
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let start = tokens.consume(TokenType::Impl)?.span;
         let generics = tokens.optional_list_parse::<GenericDecl>(
             TokenType::LArrow,
             TokenType::Comma,
@@ -182,28 +348,48 @@ impl Parsable for ImplDecl {
             type_to_impl_on = Type::parse(tokens)?;
         }
         let mut funcs = vec![];
+        let mut consts = vec![];
         tokens.consume(TokenType::LBrace)?;
         while tokens.peek_consume(TokenType::RBrace).is_err() {
-            funcs.push(FunctionDecl::parse(tokens)?);
+            let from = tokens.mark();
+            let mut ahead = tokens.fork();
+            let is_const = Visibility::parse(&mut ahead).is_ok() && ahead.peek_consume(TokenType::Const).is_ok();
+            let parsed = if is_const {
+                ConstDecl::parse(tokens).map(|const_| consts.push(const_))
+            } else {
+                FunctionDecl::parse(tokens).map(|func| funcs.push(func))
+            };
+            match parsed {
+                Ok(()) => {}
+                Err(err) if tokens.peek().is_some_and(|token| token.token_type != TokenType::EOF) => {
+                    tokens.report(err);
+                    tokens.recover(from, &[TokenType::Fn, TokenType::Const, TokenType::Pub, TokenType::RBrace]);
+                }
+                Err(err) => return Err(err),
+            }
         }
         Ok(Self {
             generics,
             trait_to_impl,
             type_to_impl_on,
             funcs,
+            consts,
+            span: tokens.node(SyntaxKind::ImplDecl, tokens.span_from(start)),
         })
     }
 }
 
 impl Parsable for FunctionDecl{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,ParseError> {
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let docs = tokens.docs();
+        let start = tokens.peek_span();
+        let visibility = Visibility::parse(tokens)?;
         let sig = FuncSig::parse(tokens)?;
-        let mut body = vec![];
-        tokens.consume(TokenType::LBrace)?;
-        while tokens.peek_consume(TokenType::RBrace).is_err(){
-            body.push(Statment::parse(tokens)?);
-        }
+        let body = Block::parse(tokens)?;
         Ok(Self{
+            docs,
+            visibility,
+            span: tokens.node(SyntaxKind::FunctionDecl, start.to(body.span)),
             sig,
             body,
         })
@@ -211,7 +397,10 @@ impl Parsable for FunctionDecl{
 }
 
 impl Parsable for TraitDecl {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let docs = tokens.docs();
+        let start = tokens.peek_span();
+        let visibility = Visibility::parse(tokens)?;
         tokens.consume(TokenType::Trait)?;
         let name = tokens.consume(TokenType::Ident)?;
         let generics = tokens.optional_list_parse::<GenericDecl>(
@@ -222,16 +411,19 @@ impl Parsable for TraitDecl {
         let funcs =
             tokens.list_parse(TokenType::LBrace, TokenType::SemiColon, TokenType::RBrace)?;
         Ok(Self {
+            docs,
+            visibility,
             name,
             generics,
             funcs,
+            span: tokens.node(SyntaxKind::TraitDecl, tokens.span_from(start)),
         })
     }
 }
 
 impl Parsable for FuncSig {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
-        tokens.consume(TokenType::Fn)?;
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let start = tokens.consume(TokenType::Fn)?.span;
         let name = tokens.consume(TokenType::Ident)?;
         let generics = tokens.optional_list_parse::<GenericDecl>(
             TokenType::LArrow,
@@ -246,28 +438,62 @@ impl Parsable for FuncSig {
         let out = if tokens.peek_consume(TokenType::SmallArrow).is_ok(){
             Type::parse(tokens)?
         }else{
-            Type::Unit
+            let got = tokens.peek_token()?;
+            let note = match got.token_type{
+                TokenType::Colon => Some("the return type goes after a `->`, not a `:`".to_string()),
+                TokenType::Ident | TokenType::Self_ | TokenType::LBrack | TokenType::Pipe => Some(format!(
+                    "a return type needs a `->` in front of it, as in `fn {}(..) -> {}`",
+                    name.lexeme, got.lexeme
+                )),
+                _ => None,
+            };
+            if note.is_some(){
+                return Err(ParseError{
+                    expected: vec![TokenType::SmallArrow, TokenType::LBrace],
+                    got: Box::new(got.clone()),
+                    note,
+                });
+            }
+            Type::Unit(tokens.prev_span().end_point())
         };
         Ok(Self {
             name,
             generics,
             parameters,
             out,
+            span: tokens.node(SyntaxKind::FuncSig, tokens.span_from(start)),
         })
     }
 }
 
+/// Adds a note to a missing `:` between a name and its type when a type seems to be there.
+fn missing_colon<'a>(what: &'a str, name: &'a Token) -> impl FnOnce(ParseError) -> ParseError + 'a {
+    move |mut err| {
+        if matches!(err.got.token_type, TokenType::Ident | TokenType::Self_ | TokenType::LBrack | TokenType::Pipe | TokenType::LParen) {
+            err.note = Some(format!(
+                "{what} are written `name: Type`, add a `:` after `{}`",
+                name.lexeme
+            ));
+        }
+        err
+    }
+}
+
 impl Parsable for (Token, Type) {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> Result<Self, ParseError> {
+    fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
         let name = tokens.consume(TokenType::Ident)?;
-        tokens.consume(TokenType::Colon)?;
+        tokens.consume(TokenType::Colon).map_err(missing_colon("parameters", &name))?;
         let type_ = Type::parse(tokens)?;
+        tokens.node(SyntaxKind::Parameter, name.span.to(type_.span()));
         Ok((name, type_))
     }
 }
 
 impl Parsable for EnumDecl {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let docs = tokens.docs();
+        let start = tokens.peek_span();
+        let visibility = Visibility::parse(tokens)?;
         tokens.consume(TokenType::Enum)?;
         let name = tokens.consume(TokenType::Ident)?;
         let generics = tokens.optional_list_parse::<GenericDecl>(
@@ -288,28 +514,38 @@ impl Parsable for EnumDecl {
             }
         }
         Ok(Self {
+            docs,
+            visibility,
             name,
             generics,
             varients,
+            span: tokens.node(SyntaxKind::EnumDecl, tokens.span_from(start)),
         })
     }
 }
 
 impl Parsable for VarientDecl {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
         let name = tokens.consume(TokenType::Ident)?;
         let fields = tokens.optional_list_parse::<FieldDecl>(
             TokenType::LBrace,
             TokenType::Comma,
             TokenType::RBrace,
         )?;
-        Ok(Self { name, fields })
+        Ok(Self {
+            span: tokens.node(SyntaxKind::VarientDecl, tokens.span_from(name.span)),
+            name,
+            fields,
+        })
     }
 }
 
 impl Parsable for StructDecl {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
-        let _ = tokens.consume(TokenType::Struct)?;
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let docs = tokens.docs();
+        let start = tokens.peek_span();
+        let visibility = Visibility::parse(tokens)?;
+        tokens.consume(TokenType::Struct)?;
         let name = tokens.consume(TokenType::Ident)?;
         let generics = tokens.optional_list_parse::<GenericDecl>(
             TokenType::LArrow,
@@ -318,27 +554,178 @@ impl Parsable for StructDecl {
         )?;
         let fields = tokens.list_parse(TokenType::LBrace, TokenType::Comma, TokenType::RBrace)?;
         Ok(Self {
-            name: name,
+            docs,
+            visibility,
+            name,
             generics,
             fields,
+            span: tokens.node(SyntaxKind::StructDecl, tokens.span_from(start)),
+        })
+    }
+}
+
+impl Parsable for ModDecl {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let docs = tokens.docs();
+        let start = tokens.consume(TokenType::Mod)?.span;
+        let name = tokens.consume(TokenType::Ident)?;
+        tokens.consume(TokenType::SemiColon)?;
+        Ok(Self {
+            docs,
+            name,
+            span: tokens.node(SyntaxKind::ModDecl, tokens.span_from(start)),
+        })
+    }
+}
+
+impl Parsable for ConstDecl {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let docs = tokens.docs();
+        let start = tokens.peek_span();
+        let visibility = Visibility::parse(tokens)?;
+        tokens.consume(TokenType::Const)?;
+        let name = tokens.consume(TokenType::Ident)?;
+        tokens.consume(TokenType::Colon).map_err(missing_colon("constants", &name))?;
+        let type_of = Type::parse(tokens)?;
+        tokens.consume(TokenType::Equal)?;
+        let value = Expresion::parse(tokens)?;
+        tokens.consume(TokenType::SemiColon)?;
+        Ok(Self {
+            docs,
+            visibility,
+            name,
+            type_of,
+            value,
+            span: tokens.node(SyntaxKind::ConstDecl, tokens.span_from(start)),
+        })
+    }
+}
+
+impl Parsable for StaticDecl {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let docs = tokens.docs();
+        let start = tokens.peek_span();
+        let visibility = Visibility::parse(tokens)?;
+        tokens.consume(TokenType::Static)?;
+        let mutable = tokens.peek_consume(TokenType::Mut).is_ok();
+        let name = tokens.consume(TokenType::Ident)?;
+        tokens.consume(TokenType::Colon).map_err(missing_colon("statics", &name))?;
+        let type_of = Type::parse(tokens)?;
+        tokens.consume(TokenType::Equal)?;
+        let value = Expresion::parse(tokens)?;
+        tokens.consume(TokenType::SemiColon)?;
+        Ok(Self {
+            docs,
+            visibility,
+            mutable,
+            name,
+            type_of,
+            value,
+            span: tokens.node(SyntaxKind::StaticDecl, tokens.span_from(start)),
+        })
+    }
+}
+
+impl Parsable for TypeAliasDecl {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let docs = tokens.docs();
+        let start = tokens.peek_span();
+        let visibility = Visibility::parse(tokens)?;
+        tokens.consume(TokenType::Type)?;
+        let name = tokens.consume(TokenType::Ident)?;
+        let generics = tokens.optional_list_parse::<GenericDecl>(
+            TokenType::LArrow,
+            TokenType::Comma,
+            TokenType::RArrow,
+        )?;
+        tokens.consume(TokenType::Equal)?;
+        // `new` with a type after it, on its own it's an alias of a type called `new`
+        let mut ahead = tokens.fork();
+        let new = ahead.next().is_some_and(|token| token.is_contextual(ContextualKeyword::New))
+            && ahead.peek().is_some_and(|token| {
+                matches!(token.token_type, TokenType::Ident | TokenType::LParen | TokenType::Pipe | TokenType::LBrack)
+            });
+        if new {
+            tokens.next();
+        }
+        let type_of = Type::parse(tokens)?;
+        tokens.consume(TokenType::SemiColon)?;
+        Ok(Self {
+            docs,
+            visibility,
+            name,
+            generics,
+            new,
+            type_of,
+            span: tokens.node(SyntaxKind::TypeAliasDecl, tokens.span_from(start)),
+        })
+    }
+}
+
+impl Parsable for UseDecl {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let start = tokens.consume(TokenType::Use)?.span;
+        let mut path = vec![tokens.consume(TokenType::Ident)?];
+        while tokens.peek_consume(TokenType::DoubleColon).is_ok() {
+            path.push(tokens.consume(TokenType::Ident)?);
+        }
+        tokens.consume(TokenType::SemiColon).map_err(|mut err| {
+            err.expected.insert(0, TokenType::DoubleColon);
+            err
+        })?;
+        Ok(Self {
+            path,
+            span: tokens.node(SyntaxKind::UseDecl, tokens.span_from(start)),
         })
     }
 }
 
 impl Parsable for FieldDecl {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let start = tokens.peek_span();
+        let visibility = Visibility::parse(tokens)?;
         let name = tokens.consume(TokenType::Ident)?;
-        tokens.consume(TokenType::Colon)?;
+        tokens.consume(TokenType::Colon).map_err(missing_colon("fields", &name))?;
         let type_ = Type::parse(tokens)?;
         Ok(Self {
+            span: tokens.node(SyntaxKind::FieldDecl, start.to(type_.span())),
+            visibility,
             name,
             type_of: type_,
         })
     }
 }
 
+impl Parsable for Visibility {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let Ok(pub_) = tokens.peek_consume(TokenType::Pub) else {
+            return Ok(Self::Private);
+        };
+        if tokens.peek_consume(TokenType::LParen).is_err() {
+            tokens.node(SyntaxKind::Visibility, pub_.span);
+            return Ok(Self::Public);
+        }
+        tokens.peek_consume_contextual(ContextualKeyword::Module)?;
+        tokens.consume(TokenType::RParen)?;
+        tokens.node(SyntaxKind::Visibility, tokens.span_from(pub_.span));
+        Ok(Self::Module)
+    }
+}
+
+impl Visibility {
+    /// Whether code in the module at `from` can see something with this visibility
+    /// declared in the module at `home`.
+    pub fn allows(&self, home: &[String], from: &[String]) -> bool {
+        match self {
+            Visibility::Private => home == from,
+            Visibility::Module => from.starts_with(home),
+            Visibility::Public => true,
+        }
+    }
+}
+
 impl Parsable for GenericDecl {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
         let name = tokens.peek_consume(TokenType::Ident)?;
         let constraints = if tokens.peek_consume(TokenType::Colon).is_ok() {
             let mut temp = vec![];
@@ -353,58 +740,97 @@ impl Parsable for GenericDecl {
             vec![]
         };
         Ok(Self {
-            name: name.clone(),
+            span: tokens.node(SyntaxKind::GenericDecl, tokens.span_from(name.span)),
+            name,
             constraints,
         })
     }
 }
 
 impl Parsable for Type {
-    fn parse(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
-        Ok(if tokens.peek().cannot_end().token_type == TokenType::LParen{
+    fn parse(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let start = tokens.peek_span();
+        Ok(if tokens.peek_token()?.token_type == TokenType::LParen && !Self::is_tuple(tokens){
             let traits = tokens.list_parse::<TraitType>(TokenType::LParen, TokenType::Plus, TokenType::RParen)?;
             if traits.is_empty(){
-                Self::Unit
+                Self::Unit(tokens.node(SyntaxKind::UnitType, tokens.span_from(start)))
             }else{
-                Self::DynamicType(traits)
+                Self::DynamicType(traits, tokens.node(SyntaxKind::DynamicType, tokens.span_from(start)))
+            }
+        }else if tokens.peek_token()?.token_type == TokenType::LParen{
+            tokens.consume(TokenType::LParen)?;
+            let mut types = vec![];
+            loop {
+                types.push(Type::parse(tokens)?);
+                if tokens.peek_consume(TokenType::Comma).is_err(){
+                    tokens.consume(TokenType::RParen).map_err(|err|ParseError{ expected: vec![TokenType::Comma, TokenType::RParen], ..err })?;
+                    // `([Int])` is only a type in parentheses
+                    if types.len() == 1{
+                        return Ok(types.remove(0));
+                    }
+                    break;
+                }
+                if tokens.peek_consume(TokenType::RParen).is_ok(){
+                    break;
+                }
             }
-        }else if tokens.peek().cannot_end().token_type == TokenType::Pipe{
+            Self::Tuple(types, tokens.node(SyntaxKind::TupleType, tokens.span_from(start)))
+        }else if tokens.peek_token()?.token_type == TokenType::Pipe{
             Self::FP(FunctionPointer::parse(tokens)?)
-        }else if tokens.peek().cannot_end().token_type == TokenType::LBrack{
+        }else if tokens.peek_token()?.token_type == TokenType::LBrack{
             tokens.consume(TokenType::LBrack)?;
-            let res = Self::Array(SolidType::parse(tokens)?);
+            let type_ = SolidType::parse(tokens)?;
             tokens.consume(TokenType::RBrack)?;
-            res
+            Self::Array(type_, tokens.node(SyntaxKind::ArrayType, tokens.span_from(start)))
         }else{
             Self::PhysicalType(SolidType::parse(tokens)?)
         })
     }
 }
 
+impl Type {
+    /// `(` starts a tuple unless it's `()` or traits joined by `+`, which `(Trait)` on its own
+    /// is too.
+    fn is_tuple(tokens: &mut TokenStream) -> bool {
+        let mut ahead = tokens.fork();
+        ahead.next();
+        if ahead.peek().is_some_and(|token| token.token_type == TokenType::RParen) {
+            return false;
+        }
+        !(TraitType::parse(&mut ahead).is_ok()
+            && ahead.peek().is_some_and(|token| matches!(token.token_type, TokenType::Plus | TokenType::RParen)))
+    }
+}
+
 impl Parsable for FunctionPointer{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,ParseError> {
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.peek_span();
         let args = tokens.list_parse(TokenType::Pipe, TokenType::Comma, TokenType::Pipe)?;
         let out = Box::new(if tokens.peek_consume(TokenType::Colon).is_ok(){
             Type::parse(tokens)?
         }else{
-            Type::Unit
+            Type::Unit(tokens.prev_span().end_point())
         });
         Ok(Self{
             args,
             out,
+            span: tokens.node(SyntaxKind::FunctionPointer, tokens.span_from(start)),
         })
     }
 }
 
 impl Parsable for SolidType{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,ParseError> {
-        let type_ = tokens.consume_multiple(vec![TokenType::Ident, TokenType::Self_])?;
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.peek_span();
+        let (path, type_) = path_parse(tokens)?;
         let generics = tokens.optional_list_parse::<Type>(
             TokenType::LArrow,
             TokenType::Comma,
             TokenType::RArrow,
         )?;
         Ok(Self {
+            span: tokens.node(SyntaxKind::SolidType, tokens.span_from(start)),
+            path,
             name: type_,
             generics,
         })
@@ -412,16 +838,30 @@ impl Parsable for SolidType{
 }
 
 impl Parsable for TraitType{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,ParseError> {
-        let type_ = tokens.consume_multiple(vec![TokenType::Ident, TokenType::Self_])?;
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.peek_span();
+        let (path, type_) = path_parse(tokens)?;
         let generics = tokens.optional_list_parse::<Type>(
             TokenType::LArrow,
             TokenType::Comma,
             TokenType::RArrow,
         )?;
         Ok(Self {
+            span: tokens.node(SyntaxKind::TraitType, tokens.span_from(start)),
+            path,
             name: type_,
             generics,
         })
     }
 }
+
+/// `a::b::Name`, the modules on the way and the name at the end. `Self` is never in a module.
+fn path_parse(tokens: &mut TokenStream) -> ParseResult<(Vec<Token>, Token)> {
+    let mut path = vec![];
+    let mut name = tokens.consume_multiple(vec![TokenType::Ident, TokenType::Self_])?;
+    while name.token_type == TokenType::Ident && tokens.peek_consume(TokenType::DoubleColon).is_ok() {
+        path.push(name);
+        name = tokens.consume(TokenType::Ident)?;
+    }
+    Ok((path, name))
+}
@@ -1,43 +1,81 @@
-use std::{iter::Peekable, slice::Iter};
-
 use pub_fields::pub_fields;
 
-use crate::scanner::{Token, TokenType};
+use crate::scanner::{FloatSuffix, IntSuffix, Span, Token, TokenType, TokenValue};
 
-use super::{decl::{SolidType, Type}, parser::{Parsable, ParseError, ParseResult, TokenExt, Wrapper}, statments::{Block, Statment}};
+use super::{cst::SyntaxKind, decl::{SolidType, Type}, parser::{Parsable, ParseError, ParseResult, Spanned, TokenExt, TokenStream}, statments::{Block, Pattern}};
 
 #[derive(Debug,Clone)]
 pub enum Expresion{
-    IntLitteral(Token),//Int
-    FloatLitteral(Token),//Float
-    StringLitteral(Token),//String
+    IntLitteral(IntLitteral),
+    FloatLitteral(FloatLitteral),
+    StringLitteral(StringLitteral),
+    CharLitteral(CharLitteral),
     TypeAssocatedFunction(TypeAssocatedFunction),
     VarAccess(VarAccess),
-    True,
-    False,
+    True(Token),
+    False(Token),
     FunctionCall(FuncCall),
     FieldAcess(FieldAccess),
     Index(Index),
     Constructor(Constructor),
     Cast(Cast),
     Parens(Parens),
+    Tuple(Tuple),
     Unary(Unary),
     Binary(Binary),
     Block(Block),
+    Match(Match),
+    Closure(Closure),
+}
+
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct IntLitteral{
+    token:Token, //Int
+    value:u64, //two's complement when negative
+    negative:bool, //written with a `-` in front
+    suffix:Option<IntSuffix>,
+    span:Span, //includes the `-` of a negative literal
+}
+
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct FloatLitteral{
+    token:Token, //Float
+    value:f64,
+    suffix:Option<FloatSuffix>,
+    span:Span, //includes the `-` of a negative literal
+}
+
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct StringLitteral{
+    token:Token, //String
+    value:String,
+}
+
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct CharLitteral{
+    token:Token, //Char
+    value:char,
 }
 
 #[derive(Debug,Clone)]
 #[pub_fields]
 pub struct TypeAssocatedFunction{
+    path:Vec<Token>, //Ident, the modules the type is in
     type_:Token,
     func_name:Token,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
 #[pub_fields]
 pub struct Constructor{
     areana:Option<Box<Expresion>>,
-    object_to_construct:ConstructorType
+    object_to_construct:ConstructorType,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
@@ -53,15 +91,18 @@ pub type ArrayConstructor = Vec<Expresion>;
 #[pub_fields]
 pub struct Cast{
     expr:Box<Expresion>,
-    traits_to_cast_to:Vec<SolidType>
+    traits_to_cast_to:Vec<SolidType>,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
 #[pub_fields]
 pub struct EnumConstructor{
+    path:Vec<Token>, //Ident, the modules the enum is in
     enum_name:Token, //Ident
     varient_name:Token, //Ident
-    fields:Vec<FieldConstructor>
+    fields:Vec<FieldConstructor>,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
@@ -69,6 +110,7 @@ pub struct EnumConstructor{
 pub struct StructConstructor{
     struct_name:Token, // Ident
     fields:Vec<FieldConstructor>,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
@@ -76,13 +118,15 @@ pub struct StructConstructor{
 pub struct FieldConstructor{
     name:Token, //Ident
     value:Box<Expresion>,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
 #[pub_fields]
 pub struct Index{
     expr:Box<Expresion>,
-    index:Box<Expresion>
+    index:Box<Expresion>,
+    span:Span,
 }
 
 
@@ -90,16 +134,60 @@ pub struct Index{
 #[pub_fields]
 pub struct FieldAccess{
     expr:Box<Expresion>,
-    field:Token, //Ident
+    field:Token, //Ident, or Int for a field of a tuple
+    span:Span,
 }
 
-pub type Parens = Box<Expresion>;
+/// `|x: Int, y: Int|: Int x + y`, a function value that can use the locals around it. The
+/// return type is `()` when it is left off, and the body runs as far as an expression can.
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct Closure{
+    parameters:Vec<(Token,Type)>,
+    out:Option<Type>,
+    body:Box<Expresion>,
+    span:Span,
+}
+
+/// `match scrutinee { pattern if guard => value, .. }`, the arms are tried in order.
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct Match{
+    scrutinee:Box<Expresion>,
+    arms:Vec<MatchArm>,
+    span:Span,
+}
+
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct MatchArm{
+    pattern:Pattern,
+    guard:Option<Expresion>,
+    value:Expresion,
+    span:Span,
+}
+
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct Parens{
+    expr:Box<Expresion>,
+    span:Span,
+}
+
+/// `(a, b)`, or `(a,)` with one element so it isn't just `a` in parentheses.
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct Tuple{
+    elements:Vec<Expresion>,
+    span:Span,
+}
 
 #[derive(Debug,Clone)]
 #[pub_fields]
 pub struct Unary{
     unary_op:UnaryOp,
     expr:Box<Expresion>,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
@@ -114,6 +202,7 @@ pub struct FuncCall{
     function:Box<Expresion>,
     generics:Vec<Type>,
     arguments:Vec<Expresion>,
+    span:Span,
 }
 
 pub type VarAccess = Token;//Ident
@@ -124,6 +213,7 @@ pub struct Binary{
     lhs:Box<Expresion>,
     rhs:Box<Expresion>,
     binary_op:BinaryOp,
+    span:Span,
 }
 
 #[derive(Debug,Clone, PartialEq, Eq)]
@@ -142,21 +232,32 @@ pub enum BinaryOp{
     LogicalAnd,
     LogicalOr, 
     Assign,
+    AddAssign,
+    SubtractAssign,
+    MultAssign,
+    DivAssign,
+    ModAssign,
+    BitwiseAndAssign,
+    BitwiseOrAssign,
+    SHLAssign,
+    SHRAssign,
     BitwiseOr,
     BitwiseAnd,
     SHL,
     SHR,
+    Range,
+    RangeInclusive,
 }
 
 impl Parsable for Expresion{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->ParseResult<Self> {
+    fn parse(tokens: &mut TokenStream)->ParseResult<Self> {
         enum OpOrExpr{
             Op(BinaryOp),
-            Expr(Expresion),
+            Expr(Box<Expresion>),
         }
         let mut value_stack = vec![];
         let mut op_stack: Vec<BinaryOp> = vec![];
-        value_stack.push(OpOrExpr::Expr(Self::primary(tokens)?));
+        value_stack.push(OpOrExpr::Expr(Box::new(Self::primary(tokens)?)));
         while BinaryOp::is_next_bin_op(tokens){
             let op = BinaryOp::parse(tokens)?;
             while let Some(last_op) = op_stack.last(){
@@ -167,32 +268,33 @@ impl Parsable for Expresion{
                 }
             }
             op_stack.push(op);
-            value_stack.push(OpOrExpr::Expr(Expresion::primary(tokens)?));
+            value_stack.push(OpOrExpr::Expr(Box::new(Expresion::primary(tokens)?)));
         }
         while let Some(op) = op_stack.pop(){
             value_stack.push(OpOrExpr::Op(op));
         }
-        let mut work_stack = vec![];
+        let mut work_stack: Vec<Expresion> = vec![];
         value_stack.into_iter().for_each(|value|{
             match value{
                 OpOrExpr::Op(op) => {
-                    let right = work_stack.pop().unwrap();
-                    let left = work_stack.pop().unwrap();
+                    let right = work_stack.pop().expect("an operator always comes after both of its operands");
+                    let left = work_stack.pop().expect("an operator always comes after both of its operands");
                     work_stack.push(Expresion::Binary(Binary{
+                        span: tokens.node(SyntaxKind::Binary, left.span().to(right.span())),
                         lhs: Box::new(left),
                         rhs: Box::new(right),
                         binary_op: op,
                     }));
                 },
-                OpOrExpr::Expr(expr) => work_stack.push(expr),
+                OpOrExpr::Expr(expr) => work_stack.push(*expr),
             }
         });
-        Ok(work_stack.remove(0))
+        Ok(work_stack.pop().expect("there is always at least one primary"))
     }
 }
 
 impl Parsable for BinaryOp{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,ParseError> {
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
         Ok(match tokens.consume_multiple(vec![
             TokenType::Plus,
             TokenType::Minus,
@@ -211,6 +313,16 @@ impl Parsable for BinaryOp{
             TokenType::Ampersand,
             TokenType::Pipe,
             TokenType::SHL,
+            TokenType::DotDot,
+            TokenType::DotDotEqual,
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+            TokenType::PercentEqual,
+            TokenType::AmpersandEqual,
+            TokenType::PipeEqual,
+            TokenType::SHLEqual,
         ])?.token_type{
             TokenType::Plus=>Self::Add,
             TokenType::Minus=>Self::Subtract,
@@ -218,7 +330,15 @@ impl Parsable for BinaryOp{
             TokenType::Slash=>Self::Div,
             TokenType::Percent=>Self::Mod,
             TokenType::LArrow=>Self::Lessthan,
-            TokenType::RArrow=>{if tokens.peek_consume(TokenType::RArrow).is_ok(){Self::Greaterthan}else{Self::SHR}},
+            // `>=`, `>>` and `>>=` are scanned a `>` at a time so generics can close with them,
+            // the parts only make one operator with nothing between them
+            TokenType::RArrow=>if tokens.consume_joined(TokenType::Equal){
+                Self::GE
+            }else if tokens.consume_joined(TokenType::RArrow){
+                if tokens.consume_joined(TokenType::Equal){ Self::SHRAssign }else{ Self::SHR }
+            }else{
+                Self::Greaterthan
+            },
             TokenType::LessOrEqual=>Self::LE,
             TokenType::MoreOrEqual=>Self::GE,
             TokenType::EqualEqual=>Self::Equal,
@@ -229,14 +349,24 @@ impl Parsable for BinaryOp{
             TokenType::Ampersand=>Self::BitwiseAnd,
             TokenType::Pipe=>Self::BitwiseOr,
             TokenType::SHL=>Self::SHL,
+            TokenType::DotDot=>Self::Range,
+            TokenType::DotDotEqual=>Self::RangeInclusive,
+            TokenType::PlusEqual=>Self::AddAssign,
+            TokenType::MinusEqual=>Self::SubtractAssign,
+            TokenType::StarEqual=>Self::MultAssign,
+            TokenType::SlashEqual=>Self::DivAssign,
+            TokenType::PercentEqual=>Self::ModAssign,
+            TokenType::AmpersandEqual=>Self::BitwiseAndAssign,
+            TokenType::PipeEqual=>Self::BitwiseOrAssign,
+            TokenType::SHLEqual=>Self::SHLAssign,
             _=>unreachable!()
         })
     }
 }
 
 impl BinaryOp{
-    fn is_next_bin_op(tokens:&mut Peekable<Iter<Token>>)->bool{
-        match tokens.peek().cannot_end().token_type{
+    fn is_next_bin_op(tokens:&mut TokenStream)->bool{
+        matches!(tokens.peek().map_or(TokenType::EOF, |token|token.token_type),
             TokenType::Plus|
             TokenType::Minus|
             TokenType::Star|
@@ -251,30 +381,52 @@ impl BinaryOp{
             TokenType::And|
             TokenType::Or|
             TokenType::SHL|
-            TokenType::Equal => true,
-            _=>false,
-        }
+            TokenType::Ampersand|
+            TokenType::Pipe|
+            TokenType::DotDot|
+            TokenType::DotDotEqual|
+            TokenType::PlusEqual|
+            TokenType::MinusEqual|
+            TokenType::StarEqual|
+            TokenType::SlashEqual|
+            TokenType::PercentEqual|
+            TokenType::AmpersandEqual|
+            TokenType::PipeEqual|
+            TokenType::SHLEqual|
+            TokenType::Equal
+        )
     }
     fn precedence(&self)->u8{
         match self{
-            BinaryOp::Mod => 9,
-            BinaryOp::Mult => 9,
-            BinaryOp::Div => 9,
-            BinaryOp::Subtract => 8,
-            BinaryOp::Add => 8,
-            BinaryOp::SHL => 7,
-            BinaryOp::SHR => 7,
-            BinaryOp::BitwiseAnd=>6,
-            BinaryOp::BitwiseOr=>5,
-            BinaryOp::Lessthan => 4,
-            BinaryOp::Greaterthan => 4,
-            BinaryOp::GE => 4,
-            BinaryOp::LE => 4,
-            BinaryOp::Equal => 3,
-            BinaryOp::NE => 3,
-            BinaryOp::LogicalAnd => 2,
-            BinaryOp::LogicalOr => 1,
-            BinaryOp::Assign => 0,
+            BinaryOp::Mod => 10,
+            BinaryOp::Mult => 10,
+            BinaryOp::Div => 10,
+            BinaryOp::Subtract => 9,
+            BinaryOp::Add => 9,
+            BinaryOp::SHL => 8,
+            BinaryOp::SHR => 8,
+            BinaryOp::BitwiseAnd=>7,
+            BinaryOp::BitwiseOr=>6,
+            BinaryOp::Lessthan => 5,
+            BinaryOp::Greaterthan => 5,
+            BinaryOp::GE => 5,
+            BinaryOp::LE => 5,
+            BinaryOp::Equal => 4,
+            BinaryOp::NE => 4,
+            BinaryOp::LogicalAnd => 3,
+            BinaryOp::LogicalOr => 2,
+            BinaryOp::Range => 1,
+            BinaryOp::RangeInclusive => 1,
+            BinaryOp::Assign |
+            BinaryOp::AddAssign |
+            BinaryOp::SubtractAssign |
+            BinaryOp::MultAssign |
+            BinaryOp::DivAssign |
+            BinaryOp::ModAssign |
+            BinaryOp::BitwiseAndAssign |
+            BinaryOp::BitwiseOrAssign |
+            BinaryOp::SHLAssign |
+            BinaryOp::SHRAssign => 0,
         }
     }
     fn left_assocative(&self)->bool{
@@ -292,24 +444,180 @@ impl BinaryOp{
             BinaryOp::NE => true,
             BinaryOp::LogicalAnd => true,
             BinaryOp::LogicalOr => true,
-            BinaryOp::Assign => false,
+            BinaryOp::Assign |
+            BinaryOp::AddAssign |
+            BinaryOp::SubtractAssign |
+            BinaryOp::MultAssign |
+            BinaryOp::DivAssign |
+            BinaryOp::ModAssign |
+            BinaryOp::BitwiseAndAssign |
+            BinaryOp::BitwiseOrAssign |
+            BinaryOp::SHLAssign |
+            BinaryOp::SHRAssign => false,
             BinaryOp::BitwiseOr => true,
             BinaryOp::BitwiseAnd => true,
             BinaryOp::SHL => true,
             BinaryOp::SHR => true,
+            BinaryOp::Range => true,
+            BinaryOp::RangeInclusive => true,
+        }
+    }
+    /// The operator `x op= y` applies before storing into `x`, `None` for anything that
+    /// isn't a compound assignment.
+    pub fn compound(&self)->Option<BinaryOp>{
+        Some(match self{
+            BinaryOp::AddAssign => BinaryOp::Add,
+            BinaryOp::SubtractAssign => BinaryOp::Subtract,
+            BinaryOp::MultAssign => BinaryOp::Mult,
+            BinaryOp::DivAssign => BinaryOp::Div,
+            BinaryOp::ModAssign => BinaryOp::Mod,
+            BinaryOp::BitwiseAndAssign => BinaryOp::BitwiseAnd,
+            BinaryOp::BitwiseOrAssign => BinaryOp::BitwiseOr,
+            BinaryOp::SHLAssign => BinaryOp::SHL,
+            BinaryOp::SHRAssign => BinaryOp::SHR,
+            _ => return None,
+        })
+    }
+}
+
+impl Spanned for Expresion{
+    fn span(&self)->Span {
+        match self{
+            Expresion::IntLitteral(int) => int.span,
+            Expresion::FloatLitteral(float) => float.span,
+            Expresion::StringLitteral(string) => string.token.span,
+            Expresion::CharLitteral(char) => char.token.span,
+            Expresion::TypeAssocatedFunction(type_assocated_function) => type_assocated_function.span,
+            Expresion::VarAccess(token) => token.span,
+            Expresion::True(token) => token.span,
+            Expresion::False(token) => token.span,
+            Expresion::FunctionCall(func_call) => func_call.span,
+            Expresion::FieldAcess(field_access) => field_access.span,
+            Expresion::Index(index) => index.span,
+            Expresion::Constructor(constructor) => constructor.span,
+            Expresion::Cast(cast) => cast.span,
+            Expresion::Parens(parens) => parens.span,
+            Expresion::Tuple(tuple) => tuple.span,
+            Expresion::Unary(unary) => unary.span,
+            Expresion::Binary(binary) => binary.span,
+            Expresion::Block(block) => block.span,
+            Expresion::Match(match_) => match_.span,
+            Expresion::Closure(closure) => closure.span,
         }
     }
 }
 
 impl Expresion{
-    fn primary(tokens:&mut Peekable<Iter<Token>>)->ParseResult<Self>{
-        let mut expr  = match tokens.peek().cannot_end().token_type{
+    /// Whether the next tokens are a `-` directly followed by a number, which get folded into
+    /// one negative literal rather than a negation.
+    fn is_negative_number(tokens:&mut TokenStream)->bool{
+        let mut ahead = tokens.fork();
+        ahead.next();
+        matches!(ahead.peek().map(|token|token.token_type), Some(TokenType::Int | TokenType::Float))
+    }
+    /// Parses an `Int` or `Float` token, negating it when it came after the `-` at `minus`.
+    fn number(tokens:&mut TokenStream, minus:Option<Span>)->ParseResult<Self>{
+        let token = tokens.consume_multiple(vec![TokenType::Int, TokenType::Float])?;
+        let span = minus.unwrap_or(token.span).to(token.span);
+        let negative = minus.is_some();
+        Ok(match token.token_type{
+            TokenType::Int => {
+                let (value, suffix) = match &token.value{
+                    Some(TokenValue::Int(value, suffix)) => (*value, *suffix),
+                    _ => (token.lexeme.parse().unwrap_or_default(), None),
+                };
+                let max = match suffix{
+                    Some(suffix) => suffix.max_magnitude(negative),
+                    None => if negative {1 << 63} else {u64::MAX},
+                };
+                if value > max || (negative && suffix.is_some_and(|suffix|!suffix.is_signed())){
+                    let type_ = suffix.map(|suffix|suffix.to_string()).unwrap_or("u64".to_string());
+                    return Err(ParseError{
+                        expected: vec![],
+                        note: Some(format!("`{}{}` is out of range for `{type_}`", if negative {"-"} else {""}, token.lexeme)),
+                        got: Box::new(token),
+                    });
+                }
+                let value = if negative {value.wrapping_neg()} else {value};
+                Self::IntLitteral(IntLitteral { token, value, negative, suffix, span: tokens.node(SyntaxKind::IntLitteral, span) })
+            },
+            _ => {
+                let (value, suffix) = match &token.value{
+                    Some(TokenValue::Float(value, suffix)) => (*value, *suffix),
+                    _ => (token.lexeme.parse().unwrap_or_default(), None),
+                };
+                let value = if negative {-value} else {value};
+                Self::FloatLitteral(FloatLitteral { token, value, suffix, span: tokens.node(SyntaxKind::FloatLitteral, span) })
+            },
+        })
+    }
+    /// A `<` after an expression only starts a generic call if a type list and a `(` follow it,
+    /// otherwise it is a less-than.
+    fn is_generic_call(tokens:&mut TokenStream)->bool{
+        let mut ahead = tokens.fork();
+        ahead.list_parse::<Type>(TokenType::LArrow, TokenType::Comma, TokenType::RArrow).is_ok()
+            && ahead.peek().is_some_and(|token|token.token_type == TokenType::LParen)
+    }
+    /// Splits a float like `0.1` after a `.` into the two tuple fields it stands for.
+    fn tuple_fields(float:Token)->ParseResult<(Token, Token)>{
+        let whole = |part:&str|!part.is_empty() && part.bytes().all(|byte|byte.is_ascii_digit());
+        let Some((first, second)) = float.lexeme.split_once('.').filter(|(first, second)|whole(first) && whole(second)) else {
+            return Err(ParseError{ expected: vec![TokenType::Ident, TokenType::Int], got: Box::new(float), note: Some("a tuple field is a whole number".to_string()) });
+        };
+        let field = |lexeme:&str, byte_start:usize|Token{
+            token_type: TokenType::Int,
+            value: lexeme.parse().ok().map(|value|TokenValue::Int(value, None)),
+            lexeme: lexeme.to_string(),
+            span: Span{ byte_start, byte_end: byte_start + lexeme.len(), ..float.span },
+            ..float.clone()
+        };
+        Ok((field(first, float.span.byte_start), field(second, float.span.byte_start + first.len() + 1)))
+    }
+    /// A number, string, character or `true`/`false` on its own, what a literal pattern is.
+    pub fn literal(tokens:&mut TokenStream)->ParseResult<Self>{
+        Ok(match tokens.peek_token()?.token_type{
+        TokenType::Minus => {
+            let minus = tokens.consume(TokenType::Minus)?;
+            Self::number(tokens, Some(minus.span))?
+        },
+        TokenType::Int | TokenType::Float => Self::number(tokens, None)?,
+        TokenType::String => {
+            let token = tokens.next_token()?.clone();
+            let value = match &token.value{
+                Some(TokenValue::Str(value)) => value.clone(),
+                _ => token.lexeme.trim_matches('"').to_string(),
+            };
+            tokens.node(SyntaxKind::StringLitteral, token.span);
+            Self::StringLitteral(StringLitteral { token, value })
+        },
+        TokenType::Char => {
+            let token = tokens.next_token()?.clone();
+            let value = match &token.value{
+                Some(TokenValue::Char(value)) => *value,
+                _ => token.lexeme.trim_matches('\'').chars().next().unwrap_or_default(),
+            };
+            tokens.node(SyntaxKind::CharLitteral, token.span);
+            Self::CharLitteral(CharLitteral { token, value })
+        },
+        TokenType::True => {
+            let token = tokens.next_token()?.clone();
+            tokens.node(SyntaxKind::BoolLitteral, token.span);
+            Self::True(token)
+        },
+        TokenType::False => {
+            let token = tokens.next_token()?.clone();
+            tokens.node(SyntaxKind::BoolLitteral, token.span);
+            Self::False(token)
+        },
+        _=>Err(ParseError{ expected: vec![TokenType::Minus,TokenType::Int,TokenType::Float,TokenType::String,TokenType::Char,TokenType::True,TokenType::False], got: Box::new(tokens.peek_token()?.clone()), note: None })?
+        })
+    }
+    fn primary(tokens:&mut TokenStream)->ParseResult<Self>{
+        let start = tokens.peek_span();
+        let mut expr  = match tokens.peek_token()?.token_type{
+            TokenType::Minus if Self::is_negative_number(tokens) => Self::literal(tokens)?,
             TokenType::Minus | TokenType::Bang => Self::Unary(Unary::parse(tokens)?),
-            TokenType::Int => Self::IntLitteral(tokens.next().expect("we just checked that there is another token").clone()),
-            TokenType::Float => Self::FloatLitteral(tokens.next().expect("we just checked that there is another token").clone()),
-            TokenType::String => Self::StringLitteral(tokens.next().expect("we just checked that there is another token").clone()),
-            TokenType::True => {tokens.next();Self::True},
-            TokenType::False => {tokens.next();Self::False},
+            TokenType::Int | TokenType::Float | TokenType::String | TokenType::Char | TokenType::True | TokenType::False => Self::literal(tokens)?,
             TokenType::LBrack => {
                 let cnstrcr = ConstructorType::ArrayConstructor(ArrayConstructor::parse(tokens)?);
                 Self::Constructor(Constructor { areana: 
@@ -319,23 +627,47 @@ impl Expresion{
                             None
                         },
                         object_to_construct:cnstrcr,
+                        span: tokens.node(SyntaxKind::Constructor, tokens.span_from(start)),
                     }
                 )
             },
-            TokenType::LBrace => {tokens.next();Self::Block({let mut temp = vec![];while tokens.peek_consume(TokenType::RBrace).is_err(){temp.push(Statment::parse(tokens)?)}temp})},
-            TokenType::Ident => {
-                let name = tokens.next().expect("we just checked that there is another token").clone();
-                if tokens.peek_consume(TokenType::DoubleColon).is_ok(){
-                    let varient_name = tokens.consume(TokenType::Ident)?;
-                    let next_token = (*tokens.peek().cannot_end()).clone();
-                    [next_token].iter().peekable().consume_multiple(vec![TokenType::LBrace, TokenType::LParen, TokenType::LArrow])?;
-                    match tokens.peek().cannot_end().token_type{
-                        TokenType::LBrace=>{
+            TokenType::LBrace => Self::Block(Block::parse(tokens)?),
+            TokenType::Match => Self::Match(Match::parse(tokens)?),
+            // nothing can follow a closure, its body already took all of it
+            TokenType::Pipe => return Ok(Self::Closure(Closure::parse(tokens)?)),
+            TokenType::Ident | TokenType::Self_ => {
+                let name = tokens.next_token()?.clone();
+                // `Self` either starts a path, as in `Self::NAME`, or makes the impl's own struct
+                let is_path = if name.token_type == TokenType::Self_ {
+                    if tokens.peek_token()?.token_type == TokenType::LBrace && tokens.struct_literals_allowed(){
+                        false
+                    }else{
+                        tokens.consume(TokenType::DoubleColon)?;
+                        true
+                    }
+                }else{
+                    tokens.peek_consume(TokenType::DoubleColon).is_ok()
+                };
+                if is_path{
+                    // `a::b::Type::name`, everything before the type is the modules it is in
+                    let mut path = vec![];
+                    let mut type_name = name;
+                    let mut varient_name = tokens.consume(TokenType::Ident)?;
+                    while tokens.peek_consume(TokenType::DoubleColon).is_ok(){
+                        path.push(type_name);
+                        type_name = varient_name;
+                        varient_name = tokens.consume(TokenType::Ident)?;
+                    }
+                    let next_token = tokens.peek_token()?;
+                    match next_token.token_type{
+                        TokenType::LBrace if tokens.struct_literals_allowed() =>{
                             let fields = tokens.optional_list_parse(TokenType::LBrace, TokenType::Comma, TokenType::RBrace)?;
                             let cnstrcr = ConstructorType::EnumConstructor(EnumConstructor{
-                                enum_name: name,
+                                path,
+                                enum_name: type_name,
                                 varient_name,
                                 fields,
+                                span: tokens.node(SyntaxKind::EnumConstructor, tokens.span_from(start)),
                             });
                             Self::Constructor(Constructor { areana: 
                                 if tokens.peek_consume(TokenType::At).is_ok(){
@@ -344,25 +676,30 @@ impl Expresion{
                                     None
                                 },
                                 object_to_construct:cnstrcr,
+                                span: tokens.node(SyntaxKind::Constructor, tokens.span_from(start)),
                             })
                         },
                         TokenType::LArrow =>{
+                            let function = Box::new(Self::TypeAssocatedFunction(TypeAssocatedFunction { path, type_: type_name, func_name: varient_name, span: tokens.node(SyntaxKind::TypeAssocatedFunction, tokens.span_from(start)) }));
                             let generics = tokens.list_parse(TokenType::LArrow, TokenType::Comma, TokenType::RArrow)?;
                             let arguments = tokens.list_parse(TokenType::LParen, TokenType::Comma, TokenType::RParen)?;
-                            Self::FunctionCall(FuncCall { function: Box::new(Self::TypeAssocatedFunction(TypeAssocatedFunction { type_: name, func_name: varient_name })), generics, arguments })
+                            Self::FunctionCall(FuncCall { function, generics, arguments, span: tokens.node(SyntaxKind::FunctionCall, tokens.span_from(start)) })
                         },
                         TokenType::LParen => {
+                            let function = Box::new(Self::TypeAssocatedFunction(TypeAssocatedFunction { path, type_: type_name, func_name: varient_name, span: tokens.node(SyntaxKind::TypeAssocatedFunction, tokens.span_from(start)) }));
                             let generics = vec![];
                             let arguments = tokens.list_parse(TokenType::LParen, TokenType::Comma, TokenType::RParen)?;
-                            Self::FunctionCall(FuncCall { function: Box::new(Self::TypeAssocatedFunction(TypeAssocatedFunction { type_: name, func_name: varient_name })), generics, arguments })
+                            Self::FunctionCall(FuncCall { function, generics, arguments, span: tokens.node(SyntaxKind::FunctionCall, tokens.span_from(start)) })
                         }
-                        _=>unreachable!()
+                        // a path on its own, like an associated constant
+                        _=>Self::TypeAssocatedFunction(TypeAssocatedFunction { path, type_: type_name, func_name: varient_name, span: tokens.node(SyntaxKind::TypeAssocatedFunction, tokens.span_from(start)) }),
                     }
-                }else if tokens.peek().cannot_end().token_type == (TokenType::LBrace){
+                }else if tokens.peek_token()?.token_type == (TokenType::LBrace) && tokens.struct_literals_allowed(){
                     let fields = tokens.list_parse(TokenType::LBrace, TokenType::Comma, TokenType::RBrace)?;
                     let cnstrcr = ConstructorType::StructConstructor(StructConstructor{
                         struct_name: name,
                         fields,
+                        span: tokens.node(SyntaxKind::StructConstructor, tokens.span_from(start)),
                     });
                     Self::Constructor(Constructor { areana: 
                         if tokens.peek_consume(TokenType::At).is_ok(){
@@ -371,68 +708,154 @@ impl Expresion{
                             None
                         },
                         object_to_construct:cnstrcr,
+                        span: tokens.node(SyntaxKind::Constructor, tokens.span_from(start)),
                     }
                 )
                 }else{
+                    tokens.node(SyntaxKind::VarAccess, name.span);
                     Self::VarAccess(name)
                 }
             },
             TokenType::LParen=> {
                 tokens.consume(TokenType::LParen)?;
-                let expr = Expresion::parse(tokens)?;
-                tokens.consume(TokenType::RParen)?;
-                expr
+                let expr = tokens.struct_literals(true, Expresion::parse)?;
+                if tokens.peek_consume(TokenType::Comma).is_ok(){
+                    let mut elements = vec![expr];
+                    while tokens.peek_consume(TokenType::RParen).is_err(){
+                        elements.push(tokens.struct_literals(true, Expresion::parse)?);
+                        if tokens.peek_consume(TokenType::Comma).is_err(){
+                            tokens.consume(TokenType::RParen).map_err(|err|ParseError{ expected: vec![TokenType::Comma, TokenType::RParen], ..err })?;
+                            break;
+                        }
+                    }
+                    Self::Tuple(Tuple{ elements, span: tokens.node(SyntaxKind::Tuple, tokens.span_from(start)) })
+                }else{
+                    tokens.consume(TokenType::RParen)?;
+                    Self::Parens(Parens{ expr: Box::new(expr), span: tokens.node(SyntaxKind::Parens, tokens.span_from(start)) })
+                }
             }
-            _=>Err(ParseError{ expected: vec![TokenType::Minus,TokenType::Int,TokenType::Float,TokenType::String,TokenType::Ident,TokenType::True,TokenType::False, TokenType::LBrace], got: tokens.next().unwrap().clone() })?
+            _=>Err(ParseError{ expected: vec![TokenType::Minus,TokenType::Int,TokenType::Float,TokenType::String,TokenType::Char,TokenType::Ident,TokenType::True,TokenType::False, TokenType::LBrace, TokenType::Match, TokenType::Pipe], got: Box::new(tokens.peek_token()?.clone()), note: None })?
         };
         if tokens.peek_consume(TokenType::Colon).is_ok(){
-            expr = Self::Cast(Cast{ expr:Box::new(expr), traits_to_cast_to: tokens.list_parse::<SolidType>(TokenType::LParen, TokenType::Plus, TokenType::RParen)?});
+            expr = Self::Cast(Cast{ expr:Box::new(expr), traits_to_cast_to: tokens.list_parse::<SolidType>(TokenType::LParen, TokenType::Plus, TokenType::RParen)?, span: tokens.node(SyntaxKind::Cast, tokens.span_from(start))});
         }
-        loop {
-            match tokens.peek(){
-                Some(token) => {
-                    match token.token_type{
-                        TokenType::Dot=> {
-                            tokens.next();
-                            expr = Self::FieldAcess(FieldAccess { expr:Box::new(expr), field: tokens.consume(TokenType::Ident)? });
-                        },
-                        TokenType::LArrow =>{
-                            let generics = tokens.list_parse(TokenType::LArrow, TokenType::Comma, TokenType::RArrow)?;
-                            let arguments = tokens.list_parse(TokenType::LParen, TokenType::Comma, TokenType::RParen)?;
-                            expr = Self::FunctionCall(FuncCall { function: Box::new(expr), generics, arguments })
-                        }
-                        TokenType::LParen => {
-                            let generics = vec![];
-                            let arguments = tokens.list_parse(TokenType::LParen, TokenType::Comma, TokenType::RParen)?;
-                            expr = Self::FunctionCall(FuncCall { function: Box::new(expr), generics, arguments })
-                        }
-                        TokenType::LBrack => {
-                            tokens.next();
-                            expr = Self::Index(Index { expr: Box::new(expr), index: Box::new(Expresion::parse(tokens)?)});
-                            tokens.consume(TokenType::RBrack)?;
-                        }
-                        _=>break,
+        while let Some(token) = tokens.peek() {
+            match token.token_type{
+                TokenType::Dot=> {
+                    tokens.next();
+                    let field = tokens.consume_multiple(vec![TokenType::Ident, TokenType::Int, TokenType::Float])?;
+                    if field.token_type == TokenType::Float{
+                        // `pair.0.1` scans as `pair`, `.` and the float `0.1`
+                        let (first, second) = Self::tuple_fields(field)?;
+                        let inner = FieldAccess { expr:Box::new(expr), field: first, span: tokens.span_from(start) };
+                        expr = Self::FieldAcess(inner);
+                        expr = Self::FieldAcess(FieldAccess { expr:Box::new(expr), field: second, span: tokens.node(SyntaxKind::FieldAccess, tokens.span_from(start)) });
+                    }else{
+                        expr = Self::FieldAcess(FieldAccess { expr:Box::new(expr), field, span: tokens.node(SyntaxKind::FieldAccess, tokens.span_from(start)) });
                     }
                 },
-                None => break,
+                TokenType::LArrow if Self::is_generic_call(tokens) =>{
+                    let generics = tokens.list_parse(TokenType::LArrow, TokenType::Comma, TokenType::RArrow)?;
+                    let arguments = tokens.list_parse(TokenType::LParen, TokenType::Comma, TokenType::RParen)?;
+                    expr = Self::FunctionCall(FuncCall { function: Box::new(expr), generics, arguments, span: tokens.node(SyntaxKind::FunctionCall, tokens.span_from(start)) })
+                }
+                TokenType::LParen => {
+                    let generics = vec![];
+                    let arguments = tokens.list_parse(TokenType::LParen, TokenType::Comma, TokenType::RParen)?;
+                    expr = Self::FunctionCall(FuncCall { function: Box::new(expr), generics, arguments, span: tokens.node(SyntaxKind::FunctionCall, tokens.span_from(start)) })
+                }
+                TokenType::LBrack => {
+                    tokens.next();
+                    let index = Box::new(tokens.struct_literals(true, Expresion::parse)?);
+                    tokens.consume(TokenType::RBrack)?;
+                    expr = Self::Index(Index { expr: Box::new(expr), index, span: tokens.node(SyntaxKind::Index, tokens.span_from(start)) });
+                }
+                _=>break,
             }
         }
         Ok(expr)
     }
 }
 
+impl Parsable for Match{
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.consume(TokenType::Match)?.span;
+        let scrutinee = Box::new(tokens.struct_literals(false, Expresion::parse)?);
+        tokens.consume(TokenType::LBrace)?;
+        let mut arms = vec![];
+        while tokens.peek_consume(TokenType::RBrace).is_err(){
+            let arm = tokens.struct_literals(true, MatchArm::parse)?;
+            // an arm that ends in a block doesn't need a `,` after it
+            let is_block = matches!(arm.value, Expresion::Block(_));
+            arms.push(arm);
+            if tokens.peek_consume(TokenType::Comma).is_err() && !is_block{
+                tokens.consume(TokenType::RBrace).map_err(|mut err|{err.expected.insert(0, TokenType::Comma); err})?;
+                break;
+            }
+        }
+        Ok(Self{
+            scrutinee,
+            arms,
+            span: tokens.node(SyntaxKind::Match, tokens.span_from(start)),
+        })
+    }
+}
+
+impl Parsable for Closure{
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.peek_span();
+        let parameters = tokens.list_parse(TokenType::Pipe, TokenType::Comma, TokenType::Pipe)?;
+        let out = if tokens.peek_consume(TokenType::Colon).is_ok(){
+            Some(Type::parse(tokens)?)
+        }else{
+            None
+        };
+        let body = Box::new(Expresion::parse(tokens)?);
+        Ok(Self{
+            parameters,
+            out,
+            body,
+            span: tokens.node(SyntaxKind::Closure, tokens.span_from(start)),
+        })
+    }
+}
+
+impl Parsable for MatchArm{
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.peek_span();
+        let pattern = Pattern::parse(tokens)?;
+        let guard = if tokens.peek_consume(TokenType::If).is_ok(){
+            Some(Expresion::parse(tokens)?)
+        }else{
+            None
+        };
+        tokens.consume(TokenType::FatArrow)?;
+        let value = Expresion::parse(tokens)?;
+        Ok(Self{
+            pattern,
+            guard,
+            value,
+            span: tokens.node(SyntaxKind::MatchArm, tokens.span_from(start)),
+        })
+    }
+}
+
 impl Parsable for ArrayConstructor{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,ParseError> {
-        tokens.list_parse(TokenType::LBrack, TokenType::Comma, TokenType::RBrack)
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.peek_span();
+        let elements = tokens.list_parse(TokenType::LBrack, TokenType::Comma, TokenType::RBrack)?;
+        tokens.node(SyntaxKind::ArrayConstructor, tokens.span_from(start));
+        Ok(elements)
     }
 }
 
 impl Parsable for FieldConstructor{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,ParseError> {
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
         let name = tokens.consume(TokenType::Ident)?;
         tokens.consume(TokenType::Colon)?;
         let value = Box::new(Expresion::parse(tokens)?);
         Ok(Self{
+            span: tokens.node(SyntaxKind::FieldConstructor, name.span.to(value.span())),
             name,
             value,
         }) 
@@ -440,22 +863,24 @@ impl Parsable for FieldConstructor{
 }
 
 impl Parsable for Unary{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,super::parser::ParseError> {
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.peek_span();
         let op = UnaryOp::parse(tokens)?;
         let expr = Box::new(Expresion::primary(tokens)?);
         Ok(Self{
             unary_op: op,
             expr,
+            span: tokens.node(SyntaxKind::Unary, tokens.span_from(start)),
         })
     }
 }
 
 impl Parsable for UnaryOp{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,super::parser::ParseError> {
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
         Ok(match tokens.peek_consume_multiple(vec![TokenType::Minus,TokenType::Bang])?.token_type {
             TokenType::Minus=>Self::Negate,
             TokenType::Bang=>Self::Not,
             _=>unreachable!("we just checked if it is of one of the two type")
         })
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,6 @@
 pub mod parser;
 pub mod expresions;
 pub mod statments;
-pub mod decl;
\ No newline at end of file
+pub mod decl;
+pub mod cst;
+pub mod visit;
@@ -1,17 +1,276 @@
-use std::{iter::Peekable, slice::Iter};
-use crate::scanner::{Token, TokenType};
+use std::fmt::Display;
+
+use crate::scanner::{ContextualKeyword, Span, Token, TokenType};
+
+use super::cst::SyntaxKind;
 
 pub type ParseResult<T> = Result<T,ParseError>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ParseError{
     pub expected:Vec<TokenType>,
-    pub got:Token,
+    /// Boxed to keep the error, and so every `ParseResult`, small.
+    pub got:Box<Token>,
+    pub note:Option<String>,
+}
+
+/// The tokens an expression can start with, listed in full when a primary is missing.
+const EXPRESSION_START:[TokenType;11] = [TokenType::Minus, TokenType::Int, TokenType::Float, TokenType::String, TokenType::Char, TokenType::Ident, TokenType::True, TokenType::False, TokenType::LBrace, TokenType::Match, TokenType::Pipe];
+/// The keywords only a statement can start with.
+const STATEMENT_START:[TokenType;9] = [TokenType::Let, TokenType::Fn, TokenType::Continue, TokenType::Break, TokenType::If, TokenType::While, TokenType::For, TokenType::Return, TokenType::Panic];
+
+impl ParseError{
+    /// What the parser would have taken instead, deduplicated and worded for people,
+    /// "`;` or `}`". Long lists of everything an expression or statement can start
+    /// with are summed up as just that.
+    pub fn expected_description(&self)->Option<String>{
+        let mut expected = self.expected.clone();
+        let mut words = vec![];
+        let has_all = |expected:&[TokenType], set:&[TokenType]|set.iter().all(|token_type|expected.contains(token_type));
+        if has_all(&expected, &STATEMENT_START) && has_all(&expected, &EXPRESSION_START){
+            expected.retain(|token_type|!STATEMENT_START.contains(token_type) && !EXPRESSION_START.contains(token_type));
+            words.push("a statement");
+        }else if has_all(&expected, &EXPRESSION_START){
+            expected.retain(|token_type|!EXPRESSION_START.contains(token_type));
+            words.push("an expression");
+        }
+        for token_type in expected{
+            if !words.contains(&token_type.describe()){
+                words.push(token_type.describe());
+            }
+        }
+        match words.as_slice(){
+            [] => None,
+            [only] => Some(only.to_string()),
+            [rest @ .., last] => Some(format!("{} or {last}", rest.join(", "))),
+        }
+    }
+    /// The token that was there instead, by its text where that says more than its kind.
+    pub fn found_description(&self)->String{
+        match self.got.token_type{
+            TokenType::Ident | TokenType::Int | TokenType::Float | TokenType::String | TokenType::Char | TokenType::Error => format!("`{}`", self.got.lexeme),
+            other => other.describe().to_string(),
+        }
+    }
+}
+
+impl Display for ParseError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.expected_description(){
+            Some(expected) => write!(f, "expected {expected}, found {}", self.found_description()),
+            None => write!(f, "unexpected {}", self.found_description()),
+        }
+    }
 }
 
 pub trait Parsable
 where Self:Sized{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,ParseError>;
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError>;
+}
+
+/// Anything that knows which part of the source it was parsed from.
+pub trait Spanned{
+    fn span(&self)->Span;
+}
+
+impl Spanned for Token{
+    fn span(&self)->Span {
+        self.span
+    }
+}
+
+/// A cursor over the scanned tokens that, unlike a plain `Peekable`, remembers
+/// what it has already handed out so nodes can work out where they end.
+///
+/// Doc comments are stepped over by `peek` and `next`, the items that can be
+/// documented pick them up explicitly with `docs` and `inner_docs`.
+///
+/// A recording stream also keeps the kind and span of every node the parser
+/// finishes, which is what `cst::build_tree` hangs the trivia off.
+///
+/// Errors the parser recovered from are kept here too, until `take_errors`.
+#[derive(Debug, Clone)]
+pub struct TokenStream<'a>{
+    tokens:&'a [Token],
+    current:usize,
+    last:Option<usize>,
+    nodes:Option<Vec<(SyntaxKind,Span)>>,
+    errors:Vec<ParseError>,
+    struct_literals:bool,
+}
+
+/// Where a statement that failed to parse is given up on, the `;` that ends it
+/// or the `}` or item that comes after it.
+pub const STATEMENT_SYNC:[TokenType;7] = [TokenType::SemiColon, TokenType::RBrace, TokenType::Fn, TokenType::Struct, TokenType::Enum, TokenType::Trait, TokenType::Impl];
+/// Where an item that failed to parse is given up on, the start of the next one.
+pub const ITEM_SYNC:[TokenType;11] = [TokenType::Fn, TokenType::Struct, TokenType::Enum, TokenType::Trait, TokenType::Impl, TokenType::Mod, TokenType::Use, TokenType::Const, TokenType::Static, TokenType::Type, TokenType::Pub];
+
+impl<'a> TokenStream<'a>{
+    pub fn new(tokens:&'a [Token])->Self{
+        Self { tokens, current: 0, last: None, nodes: None, errors: vec![], struct_literals: true }
+    }
+    pub fn recording(tokens:&'a [Token])->Self{
+        Self { nodes: Some(vec![]), ..Self::new(tokens) }
+    }
+    /// A copy of the cursor for looking ahead, nothing it parses is recorded.
+    pub fn fork(&self)->Self{
+        Self { tokens: self.tokens, current: self.current, last: self.last, nodes: None, errors: vec![], struct_literals: self.struct_literals }
+    }
+    /// Runs `parse` with `Name {` read as a struct constructor or not. The expression
+    /// right before the body of a `for`, `while` or `if` turns them off so the `{` is
+    /// left for the body, anything bracketed inside it turns them back on.
+    pub fn struct_literals<T>(&mut self, allowed:bool, parse:impl FnOnce(&mut Self)->T)->T{
+        let before = std::mem::replace(&mut self.struct_literals, allowed);
+        let result = parse(self);
+        self.struct_literals = before;
+        result
+    }
+    pub fn struct_literals_allowed(&self)->bool{
+        self.struct_literals
+    }
+    /// Notes that a node of `kind` was parsed over `span` and hands the span back.
+    pub fn node(&mut self, kind:SyntaxKind, span:Span)->Span{
+        if let Some(nodes) = &mut self.nodes{
+            nodes.push((kind,span));
+        }
+        span
+    }
+    pub fn take_nodes(&mut self)->Vec<(SyntaxKind,Span)>{
+        self.nodes.take().unwrap_or_default()
+    }
+    /// How far into the tokens the stream is, to hand back to `recover`.
+    pub fn mark(&self)->usize{
+        self.current
+    }
+    /// Keeps an error the parser is going to carry on past.
+    pub fn report(&mut self, error:ParseError){
+        self.errors.push(error);
+    }
+    pub fn take_errors(&mut self)->Vec<ParseError>{
+        std::mem::take(&mut self.errors)
+    }
+    /// Skips to the next token in `sync` after an error, taking it if it is a `;`.
+    /// Anything between braces is skipped whole, so the `}` of a block inside the
+    /// broken code is not mistaken for the end of the one around it.
+    ///
+    /// If nothing was parsed since `from` one token is always skipped so the caller
+    /// can't fail on the same token forever.
+    pub fn recover(&mut self, from:usize, sync:&[TokenType]){
+        let mut depth = 0usize;
+        let mut stalled = self.current == from;
+        while let Some(token) = self.peek(){
+            match token.token_type{
+                TokenType::EOF => return,
+                TokenType::SemiColon if depth == 0 && sync.contains(&TokenType::SemiColon) => {
+                    self.next();
+                    return;
+                },
+                token_type if depth == 0 && !stalled && sync.contains(&token_type) => return,
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace => depth = depth.saturating_sub(1),
+                _ => {},
+            }
+            self.next();
+            stalled = false;
+        }
+    }
+    fn is_doc(token:&Token)->bool{
+        matches!(token.token_type, TokenType::DocComment | TokenType::InnerDocComment)
+    }
+    fn next_index(&self)->usize{
+        let mut index = self.current;
+        while self.tokens.get(index).is_some_and(Self::is_doc){
+            index += 1;
+        }
+        index
+    }
+    pub fn peek(&mut self)->Option<&'a Token>{
+        self.tokens.get(self.next_index())
+    }
+    /// Like `peek`, but running out of tokens is an error rather than a `None`.
+    pub fn peek_token(&mut self)->ParseResult<&'a Token>{
+        self.peek().ok_or_else(||self.end_error())
+    }
+    /// Like `next`, but running out of tokens is an error rather than a `None`.
+    pub fn next_token(&mut self)->ParseResult<&'a Token>{
+        self.next().ok_or_else(||self.end_error())
+    }
+    /// An `EOF` standing in for the token that isn't there when the stream ran dry,
+    /// either by being read past its own `EOF` or by never having had one.
+    fn end_token(&self)->Token{
+        let span = self.tokens.last().map_or(self.prev_span(), |token|token.span).end_point();
+        Token{
+            token_type: TokenType::EOF,
+            line: self.tokens.last().map_or(0, |token|token.line + token.lexeme.matches('\n').count()),
+            span,
+            lexeme: String::new(),
+            value: None,
+        }
+    }
+    fn end_error(&self)->ParseError{
+        ParseError{
+            expected: vec![],
+            got: Box::new(self.end_token()),
+            note: None,
+        }
+    }
+    /// The span of the next token, or an empty span after the last one if there is none.
+    pub fn peek_span(&mut self)->Span{
+        match self.peek(){
+            Some(token) => token.span,
+            None => self.prev_span().end_point(),
+        }
+    }
+    /// The span of the last token handed out, or an empty span at the start if there is none.
+    pub fn prev_span(&self)->Span{
+        match self.last.and_then(|i|self.tokens.get(i)){
+            Some(token) => token.span,
+            None => self.tokens.first().map(|token|token.span.start_point()).unwrap_or_default(),
+        }
+    }
+    /// Takes the next token when it is `token_type` and starts right where the last one
+    /// ended, for an operator scanned in parts like `>=`.
+    pub fn consume_joined(&mut self, token_type:TokenType)->bool{
+        let end = self.prev_span().byte_end;
+        match self.peek(){
+            Some(token) if token.token_type == token_type && token.span.byte_start == end => {
+                self.next();
+                true
+            }
+            _ => false,
+        }
+    }
+    /// The span from `start` up to and including the last token handed out.
+    pub fn span_from(&self, start:Span)->Span{
+        start.to(self.prev_span())
+    }
+    /// Takes the `///` comments sitting right in front of the next token.
+    pub fn docs(&mut self)->Vec<Token>{
+        self.take_while_type(TokenType::DocComment)
+    }
+    /// Takes the `//!` comments sitting right in front of the next token.
+    pub fn inner_docs(&mut self)->Vec<Token>{
+        self.take_while_type(TokenType::InnerDocComment)
+    }
+    fn take_while_type(&mut self, token_type:TokenType)->Vec<Token>{
+        let mut taken = vec![];
+        while let Some(token) = self.tokens.get(self.current).filter(|token|token.token_type == token_type){
+            taken.push(token.clone());
+            self.current += 1;
+        }
+        taken
+    }
+}
+
+impl<'a> Iterator for TokenStream<'a>{
+    type Item = &'a Token;
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.next_index();
+        let token = self.tokens.get(index)?;
+        self.current = index + 1;
+        self.last = Some(index);
+        Some(token)
+    }
 }
 
 pub trait TokenExt{
@@ -19,11 +278,12 @@ pub trait TokenExt{
     fn peek_consume(&mut self, token_type:TokenType) -> ParseResult<Token>;
     fn consume_multiple(&mut self, token_types:Vec<TokenType>) -> ParseResult<Token>;
     fn peek_consume_multiple(&mut self, token_types:Vec<TokenType>) -> ParseResult<Token>;
+    fn peek_consume_contextual(&mut self, keyword:ContextualKeyword) -> ParseResult<Token>;
     fn optional_list_parse<T:Parsable>(&mut self, start:TokenType,delimter:TokenType,end:TokenType)->ParseResult<Vec<T>>;
     fn list_parse<T:Parsable>(&mut self, start:TokenType,delimter:TokenType,end:TokenType)->ParseResult<Vec<T>>;
 }
 
-impl TokenExt for Peekable<Iter<'_,Token>>{
+impl TokenExt for TokenStream<'_>{
     fn consume(&mut self, token_type:TokenType) -> ParseResult<Token> {
         self.consume_multiple(vec![token_type])
     }
@@ -31,21 +291,34 @@ impl TokenExt for Peekable<Iter<'_,Token>>{
         self.peek_consume_multiple(vec![token_type])
     }
     fn consume_multiple(&mut self, token_types:Vec<TokenType>) -> ParseResult<Token> {
-        let res = self.peek_consume_multiple(token_types);
-        if res.is_err(){
-            self.next();
-        }
-        res
+        self.peek_consume_multiple(token_types)
     }
     fn peek_consume_multiple(&mut self, token_types:Vec<TokenType>) -> ParseResult<Token> {
-        let token = (*self.peek().cannot_end()).clone();
+        let token = self.peek().cloned().unwrap_or_else(||self.end_token());
         if token_types.contains(&token.token_type){
             self.next();
-            return Ok(token)
+            Ok(token)
         }else{
+            let note = token.keyword()
+                .filter(|_|token_types.contains(&TokenType::Ident))
+                .map(|keyword|format!("`{keyword}` is a keyword and can't be used as a name"));
             Err(ParseError{
                 expected:token_types,
-                got:token,
+                got:Box::new(token),
+                note,
+            })
+        }
+    }
+    fn peek_consume_contextual(&mut self, keyword:ContextualKeyword) -> ParseResult<Token> {
+        let token = self.peek().cloned().unwrap_or_else(||self.end_token());
+        if token.is_contextual(keyword){
+            self.next();
+            Ok(token)
+        }else{
+            Err(ParseError{
+                expected:vec![TokenType::Ident],
+                got:Box::new(token),
+                note:Some(format!("expected `{keyword}` here")),
             })
         }
     }
@@ -53,12 +326,12 @@ impl TokenExt for Peekable<Iter<'_,Token>>{
         let mut list = vec![];
         if self.peek_consume(start).is_ok() && self.peek_consume(end).is_err(){
             loop {
-                let type_ = T::parse(self)?;
+                let type_ = self.struct_literals(true, |tokens|T::parse(tokens))?;
                 list.push(type_);
                 if self.peek_consume(delimter).is_ok(){
                     continue;
                 }
-                self.consume(end)?;
+                self.consume(end).map_err(|mut err|{err.expected.insert(0, delimter); err})?;
                 break;
             }
         }
@@ -69,25 +342,15 @@ impl TokenExt for Peekable<Iter<'_,Token>>{
         self.consume(start)?;
         if self.peek_consume(end).is_err(){
             loop {
-                let type_ = T::parse(self)?;
+                let type_ = self.struct_literals(true, |tokens|T::parse(tokens))?;
                 list.push(type_);
                 if self.peek_consume(delimter).is_ok(){
                     continue;
                 }
-                self.consume(end)?;
+                self.consume(end).map_err(|mut err|{err.expected.insert(0, delimter); err})?;
                 break;
             }
         }
         Ok(list)
     }
 }
-
-pub trait Wrapper<T>{
-    fn cannot_end(self)->T;
-}
-
-impl<T> Wrapper<T> for Option<T>{
-    fn cannot_end(self)->T {
-        self.expect("Should've found the end of file before getting here")
-    }
-}
\ No newline at end of file
@@ -1,10 +1,8 @@
-use std::{iter::Peekable, slice::Iter};
-
 use pub_fields::pub_fields;
 
-use crate::scanner::{Token, TokenType};
+use crate::scanner::{Span, Token, TokenType};
 
-use super::{decl::{FunctionDecl, Type}, expresions::Expresion, parser::{Parsable, ParseError, ParseResult, TokenExt, Wrapper}};
+use super::{cst::SyntaxKind, decl::{FunctionDecl, Type}, expresions::Expresion, parser::{Parsable, ParseError, ParseResult, Spanned, TokenExt, TokenStream, STATEMENT_SYNC}};
 
 #[derive(Debug,Clone)]
 pub enum Statment{
@@ -13,22 +11,45 @@ pub enum Statment{
     Expresion(Expresion),
     If(If),
     While(While),
+    For(For),
     Return(Return),
     ImReturn(Box<Statment>),
     Block(Block),
-    Continue,
-    Break, 
-    Panic,
+    Continue(Token),
+    Break(Token),
+    Panic(Token),
+}
+
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct Return{
+    value:Option<Box<Statment>>,
+    span:Span,
 }
 
-pub type Return = Option<Box<Statment>>;
-pub type Block=Vec<Statment>;
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct Block{
+    statments:Vec<Statment>,
+    span:Span,
+}
 
 #[derive(Debug,Clone)]
 #[pub_fields]
 pub struct While{
     condition:Expresion,
     statment:Box<Statment>,
+    span:Span,
+}
+
+/// `for variable in iterable statment`, over a range `a..b` / `a..=b` or an array.
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct For{
+    variable:Token, //Ident
+    iterable:Expresion,
+    statment:Box<Statment>,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
@@ -36,57 +57,145 @@ pub struct While{
 pub struct If{
     conditionals_and_statments:Vec<(IfType,Statment)>,
     else_statment:Option<Box<Statment>>,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
 pub enum IfType{
     Boolean(Expresion),
     IfLet{
-        pattern:Pattern,
+        pattern:Box<Pattern>,
         expresion:Expresion
     }
 }
 
+#[derive(Debug,Clone)]
+pub enum Pattern{
+    Wildcard(Token), // `_`
+    Binding(Token), //Ident
+    At(At),
+    Literal(Expresion),
+    Destructure(Destructure),
+    Tuple(TuplePattern),
+    Or(Vec<Pattern>), // `a | b`, always at least two
+}
+
+/// `name @ pattern`, binds the whole value as well as matching it.
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct At{
+    name:Token,//Ident
+    pattern:Box<Pattern>,
+    span:Span,
+}
+
+/// `(a, b)`, or `(a,)` with one element, as `(a)` is just `a`.
 #[derive(Debug,Clone)]
 #[pub_fields]
-pub struct Pattern{
+pub struct TuplePattern{
+    elements:Vec<Pattern>,
+    span:Span,
+}
+
+/// `Type.Varient{field: pattern, ..}`, the varient is left off for a struct.
+#[derive(Debug,Clone)]
+#[pub_fields]
+pub struct Destructure{
     type_of:Type,
     varient:Option<Token>,//Ident
     constructor:Vec<(Token,Option<Pattern>)>,//Ident
+    rest:bool,
+    span:Span,
 }
 
 #[derive(Debug,Clone)]
 #[pub_fields]
 pub struct VarCreation{
     mutable:bool,
-    name:Token, // Ident
+    pattern:Pattern,
     type_of:Option<Type>,
     value:Box<Statment>,
+    span:Span,
+}
+
+impl Spanned for Statment{
+    fn span(&self)->Span {
+        match self{
+            Statment::VarCreation(var_creation) => var_creation.span,
+            Statment::FuncCreation(function_decl) => function_decl.span,
+            Statment::Expresion(expresion) => expresion.span(),
+            Statment::If(if_) => if_.span,
+            Statment::While(while_) => while_.span,
+            Statment::For(for_) => for_.span,
+            Statment::Return(return_) => return_.span,
+            Statment::ImReturn(statment) => statment.span(),
+            Statment::Block(block) => block.span,
+            Statment::Continue(token) => token.span,
+            Statment::Break(token) => token.span,
+            Statment::Panic(token) => token.span,
+        }
+    }
+}
+
+impl Spanned for IfType{
+    fn span(&self)->Span {
+        match self{
+            IfType::Boolean(expresion) => expresion.span(),
+            IfType::IfLet { pattern, expresion } => pattern.span().to(expresion.span()),
+        }
+    }
+}
+
+impl Spanned for Pattern{
+    fn span(&self)->Span {
+        match self{
+            Pattern::Wildcard(token) => token.span,
+            Pattern::Binding(token) => token.span,
+            Pattern::At(at) => at.span,
+            Pattern::Literal(literal) => literal.span(),
+            Pattern::Destructure(destructure) => destructure.span,
+            Pattern::Tuple(tuple) => tuple.span,
+            Pattern::Or(alternatives) => alternatives[0].span().to(alternatives[alternatives.len()-1].span()),
+        }
+    }
 }
 
 impl Parsable for Statment{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->ParseResult<Self> {
-        Ok(match tokens.peek().cannot_end().token_type{
+    fn parse(tokens: &mut TokenStream)->ParseResult<Self> {
+        Ok(match tokens.peek_token()?.token_type{
             TokenType::Let=>Self::VarCreation(VarCreation::parse(tokens)?),
             TokenType::Fn=>Self::FuncCreation(FunctionDecl::parse(tokens)?),
-            TokenType::Continue=>{tokens.next(); tokens.consume(TokenType::SemiColon)?;Self::Continue},
-            TokenType::Break=>{tokens.next(); tokens.consume(TokenType::SemiColon)?;Self::Break},
+            TokenType::Continue=>{let token = tokens.next_token()?.clone(); tokens.consume(TokenType::SemiColon)?; tokens.node(SyntaxKind::Continue, tokens.span_from(token.span)); Self::Continue(token)},
+            TokenType::Break=>{let token = tokens.next_token()?.clone(); tokens.consume(TokenType::SemiColon)?; tokens.node(SyntaxKind::Break, tokens.span_from(token.span)); Self::Break(token)},
             TokenType::If=>Self::If(If::parse(tokens)?),
             TokenType::While=>Self::While(While::parse(tokens)?),
+            TokenType::For=>Self::For(For::parse(tokens)?),
             TokenType::Return=>Self::Return(Return::parse(tokens)?),
-            TokenType::LBrace => {tokens.next();Self::Block({let mut temp = vec![];while tokens.peek_consume(TokenType::RBrace).is_err(){temp.push(Statment::parse(tokens)?)}temp})},
-            TokenType::Panic => {tokens.next(); tokens.consume(TokenType::SemiColon)?;Self::Panic}
+            TokenType::LBrace => Self::Block(Block::parse(tokens)?),
+            TokenType::Panic => {let token = tokens.next_token()?.clone(); tokens.consume(TokenType::SemiColon)?; tokens.node(SyntaxKind::Panic, tokens.span_from(token.span)); Self::Panic(token)}
             _=>{
-                let temp = Self::Expresion(Expresion::parse(tokens).map_err(|mut err|{err.expected.append(&mut vec![TokenType::Let,TokenType::Fn,TokenType::Continue,TokenType::Break,TokenType::If,TokenType::While,TokenType::Return,TokenType::LBrace,TokenType::Panic]); err})?);
+                let start = tokens.peek_span();
+                let temp = Self::Expresion(Expresion::parse(tokens).map_err(|mut err|{
+                    // only a statement that failed on its first token could have been another kind of statement
+                    if err.got.span == start{
+                        err.expected.append(&mut vec![TokenType::Let,TokenType::Fn,TokenType::Continue,TokenType::Break,TokenType::If,TokenType::While,TokenType::For,TokenType::Return,TokenType::LBrace,TokenType::Panic]);
+                    }
+                    err
+                })?);
                 if tokens.peek_consume(TokenType::SemiColon).is_err(){
-                    if tokens.peek().cannot_end().token_type == TokenType::RBrace{
+                    if tokens.peek_token()?.token_type == TokenType::RBrace{
+                        tokens.node(SyntaxKind::ImReturn, temp.span());
                         Self::ImReturn(Box::new(temp))
+                    }else if matches!(temp, Self::Expresion(Expresion::Match(_))){
+                        // like a block, a `match` ends where its `}` does
+                        temp
                     }else{
                         Err(ParseError{
                             expected: vec![TokenType::SemiColon],
-                            got: tokens.next().cannot_end().clone(),
+                            got: Box::new(tokens.peek_token()?.clone()),
+                            note: None,
                         })?
-                    } 
+                    }
                 }else{
                     temp
                 }
@@ -95,37 +204,81 @@ impl Parsable for Statment{
     }
 }
 
+impl Parsable for Block{
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.consume(TokenType::LBrace)?.span;
+        let mut statments = vec![];
+        while tokens.peek_consume(TokenType::RBrace).is_err(){
+            if tokens.peek_token()?.token_type == TokenType::EOF{
+                tokens.consume(TokenType::RBrace)?;
+            }
+            let from = tokens.mark();
+            match tokens.struct_literals(true, Statment::parse){
+                Ok(statment) => statments.push(statment),
+                Err(err) => {
+                    tokens.report(err);
+                    tokens.recover(from, &STATEMENT_SYNC);
+                },
+            }
+        }
+        Ok(Self{
+            statments,
+            span: tokens.node(SyntaxKind::Block, tokens.span_from(start)),
+        })
+    }
+}
+
 impl Parsable for Return{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,super::parser::ParseError> {
-        tokens.consume(TokenType::Return)?;
-        Ok(if tokens.peek_consume(TokenType::SemiColon).is_err(){
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.consume(TokenType::Return)?.span;
+        let value = if tokens.peek_consume(TokenType::SemiColon).is_err(){
             Some(Box::new(Statment::parse(tokens)?))
         }else{
             None
+        };
+        Ok(Self{
+            value,
+            span: tokens.node(SyntaxKind::Return, tokens.span_from(start)),
         })
     }
 }
 
 impl Parsable for While{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,super::parser::ParseError> {
-        tokens.consume(TokenType::While)?;
-        let condition = Expresion::parse(tokens)?;
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.consume(TokenType::While)?.span;
+        let condition = tokens.struct_literals(false, Expresion::parse)?;
         let statment = Box::new(Statment::parse(tokens)?);
         Ok(Self{
             condition,
             statment,
+            span: tokens.node(SyntaxKind::While, tokens.span_from(start)),
+        })
+    }
+}
+
+impl Parsable for For{
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.consume(TokenType::For)?.span;
+        let variable = tokens.consume(TokenType::Ident)?;
+        tokens.consume(TokenType::In)?;
+        let iterable = tokens.struct_literals(false, Expresion::parse)?;
+        let statment = Box::new(Statment::parse(tokens)?);
+        Ok(Self{
+            variable,
+            iterable,
+            statment,
+            span: tokens.node(SyntaxKind::For, tokens.span_from(start)),
         })
     }
 }
 
 impl Parsable for If{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,super::parser::ParseError> {
-        tokens.consume(TokenType::If)?;
-        let mut ifs = vec![(IfType::parse(tokens)?,dbg!(Statment::parse(tokens)?))];
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.consume(TokenType::If)?.span;
+        let mut ifs = vec![(IfType::parse(tokens)?,Statment::parse(tokens)?)];
         let mut else_statment = None;
-        while tokens.next().cannot_end().token_type == TokenType::Else{
-            if tokens.peek().cannot_end().token_type == TokenType::If {
-                tokens.next();
+        while tokens.peek_consume(TokenType::Else).is_ok(){
+            if tokens.peek_consume(TokenType::If).is_ok() {
                 let condition = IfType::parse(tokens)?;
                 let statment = Statment::parse(tokens)?;
                 ifs.push((condition,statment));
@@ -137,56 +290,150 @@ impl Parsable for If{
         Ok(Self{
             conditionals_and_statments: ifs,
             else_statment,
+            span: tokens.node(SyntaxKind::If, tokens.span_from(start)),
         })
     }
 }
 
 impl Parsable for IfType{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,super::parser::ParseError> {
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.peek_span();
         Ok(if tokens.peek_consume(TokenType::Let).is_ok(){
             let pattern = Pattern::parse(tokens)?;
-            let expr = Expresion::parse(tokens)?;
-            Self::IfLet { pattern, expresion: expr }
+            let expr = tokens.struct_literals(false, Expresion::parse)?;
+            let if_let = Self::IfLet { pattern: Box::new(pattern), expresion: expr };
+            tokens.node(SyntaxKind::IfLet, tokens.span_from(start));
+            if_let
         }else{
-            Self::Boolean(Expresion::parse(tokens)?)
+            Self::Boolean(tokens.struct_literals(false, Expresion::parse)?)
         })
     }
 }
 
 impl Parsable for Pattern{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,super::parser::ParseError> {
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.peek_span();
+        let first = Self::alternative(tokens)?;
+        if tokens.peek().map(|token|token.token_type) != Some(TokenType::Pipe){
+            return Ok(first);
+        }
+        let mut alternatives = vec![first];
+        while tokens.peek_consume(TokenType::Pipe).is_ok(){
+            alternatives.push(Self::alternative(tokens)?);
+        }
+        tokens.node(SyntaxKind::OrPattern, tokens.span_from(start));
+        Ok(Self::Or(alternatives))
+    }
+}
+
+impl Pattern{
+    fn alternative(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        if matches!(tokens.peek_token()?.token_type, TokenType::Minus | TokenType::Int | TokenType::Float | TokenType::String | TokenType::Char | TokenType::True | TokenType::False){
+            let start = tokens.peek_span();
+            let literal = Expresion::literal(tokens)?;
+            tokens.node(SyntaxKind::LiteralPattern, tokens.span_from(start));
+            return Ok(Self::Literal(literal));
+        }
+        if tokens.peek_token()?.token_type == TokenType::LParen{
+            return Self::tuple(tokens);
+        }
+        // a name on its own binds whatever is there, anything more is a type to destructure
+        let mut ahead = tokens.fork();
+        let lone_name = ahead.peek_consume(TokenType::Ident).is_ok()
+            && !matches!(ahead.peek().map(|token|token.token_type), Some(TokenType::Dot | TokenType::LBrace | TokenType::LArrow));
+        if lone_name{
+            let name = tokens.next_token()?.clone();
+            if name.lexeme != "_" && tokens.peek_consume(TokenType::At).is_ok(){
+                let pattern = Box::new(Self::alternative(tokens)?);
+                return Ok(Self::At(At{
+                    span: tokens.node(SyntaxKind::AtPattern, tokens.span_from(name.span)),
+                    name,
+                    pattern,
+                }));
+            }
+            return Ok(if name.lexeme == "_"{
+                tokens.node(SyntaxKind::WildcardPattern, name.span);
+                Self::Wildcard(name)
+            }else{
+                tokens.node(SyntaxKind::BindingPattern, name.span);
+                Self::Binding(name)
+            });
+        }
+        Ok(Self::Destructure(Destructure::parse(tokens)?))
+    }
+    fn tuple(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.consume(TokenType::LParen)?.span;
+        let mut elements = vec![];
+        loop {
+            elements.push(Self::parse(tokens)?);
+            if tokens.peek_consume(TokenType::Comma).is_err(){
+                tokens.consume(TokenType::RParen).map_err(|err|ParseError{ expected: vec![TokenType::Comma, TokenType::RParen], ..err })?;
+                if elements.len() == 1{
+                    return Ok(elements.remove(0));
+                }
+                break;
+            }
+            if tokens.peek_consume(TokenType::RParen).is_ok(){
+                break;
+            }
+        }
+        Ok(Self::Tuple(TuplePattern{ elements, span: tokens.node(SyntaxKind::TuplePattern, tokens.span_from(start)) }))
+    }
+}
+
+impl Parsable for Destructure{
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
         let type_of = Type::parse(tokens)?;
         let varient = if tokens.peek_consume(TokenType::Dot).is_ok(){
             Some(tokens.consume(TokenType::Ident)?)
         }else{
             None
         };
-        let constructor = tokens.optional_list_parse(TokenType::LBrace, TokenType::Comma, TokenType::RBrace)?;
+        let mut constructor = vec![];
+        let mut rest = false;
+        if tokens.peek_consume(TokenType::LBrace).is_ok(){
+            // fields, then maybe a `..` that has to come last
+            while tokens.peek_consume(TokenType::RBrace).is_err(){
+                if tokens.peek_consume(TokenType::DotDot).is_ok(){
+                    rest = true;
+                    tokens.consume(TokenType::RBrace).map_err(|err|ParseError{ note: Some("`..` has to be the last thing in a pattern".to_string()), ..err })?;
+                    break;
+                }
+                constructor.push(<(Token,Option<Pattern>)>::parse(tokens)?);
+                if tokens.peek_consume(TokenType::Comma).is_err(){
+                    tokens.consume(TokenType::RBrace).map_err(|err|ParseError{ expected: vec![TokenType::Comma,TokenType::RBrace], ..err })?;
+                    break;
+                }
+            }
+        }
         Ok(Self{
+            span: tokens.node(SyntaxKind::Pattern, tokens.span_from(type_of.span())),
             type_of,
             varient,
             constructor,
+            rest,
         })
     }
 }
 
 impl Parsable for (Token, Option<Pattern>){
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,super::parser::ParseError> {
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
         let name = tokens.consume(TokenType::Ident)?;
         let pattern = if tokens.peek_consume(TokenType::Colon).is_ok(){
             Some(Pattern::parse(tokens)?)
         }else{
             None
         };
+        tokens.node(SyntaxKind::FieldPattern, tokens.span_from(name.span));
         Ok((name,pattern))
     }
 }
 
 impl Parsable for VarCreation{
-    fn parse(tokens: &mut Peekable<Iter<Token>>)->Result<Self,super::parser::ParseError> {
-        tokens.consume(TokenType::Let)?;
+    fn parse(tokens: &mut TokenStream)->Result<Self,ParseError> {
+        let start = tokens.consume(TokenType::Let)?.span;
         let mutable = tokens.peek_consume(TokenType::Mut).is_ok();
-        let name = tokens.consume(TokenType::Ident)?;
+        let pattern = Pattern::parse(tokens)?;
         let type_of = if tokens.peek_consume(TokenType::Colon).is_ok(){
             Some(Type::parse(tokens)?)
         }else{
@@ -196,9 +443,10 @@ impl Parsable for VarCreation{
         let value = Box::new(Statment::parse(tokens)?);
         Ok(Self{
             mutable,
-            name,
+            pattern,
             type_of,
             value,
+            span: tokens.node(SyntaxKind::VarCreation, tokens.span_from(start)),
         })
     }
-}
\ No newline at end of file
+}
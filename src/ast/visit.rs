@@ -0,0 +1,273 @@
+//! One walk over everything in a function body, for the passes that each only care about a
+//! few kinds of node. A pass implements `Visit`, or `VisitMut` to change what it walks,
+//! overrides the `visit_` methods of the nodes it cares about and calls the matching `walk_`
+//! function from those to carry on inside them.
+//!
+//! The walk opens a scope with `scoped` wherever the language does: a block, each branch of
+//! an `if`, the body of a `for`, each match arm, a closure and a function. It calls `bind`
+//! for each name bound in one, bar those in patterns, which `walk_pattern` binds so a pass
+//! that works patterns out itself decides what they bind.
+
+use crate::scanner::Token;
+
+use super::{
+    decl::{FunctionDecl, SolidType, Type},
+    expresions::{ConstructorType, Expresion},
+    statments::{IfType, Pattern, Statment},
+};
+
+macro_rules! visitor {
+    ($visit:ident, $walk_statment:ident, $walk_expresion:ident, $walk_pattern:ident, $walk_function:ident $(, $mut_:tt)?) => {
+        pub trait $visit: Sized {
+            type Error;
+            fn visit_statment(&mut self, statment: &$($mut_)? Statment) -> Result<(), Self::Error> {
+                $walk_statment(self, statment)
+            }
+            fn visit_expresion(&mut self, expresion: &$($mut_)? Expresion) -> Result<(), Self::Error> {
+                $walk_expresion(self, expresion)
+            }
+            fn visit_pattern(&mut self, pattern: &$($mut_)? Pattern) -> Result<(), Self::Error> {
+                $walk_pattern(self, pattern)
+            }
+            /// A pattern of an `if let` or a match arm, `value` is what it's matched against
+            /// and has already been visited.
+            fn visit_matched(&mut self, pattern: &$($mut_)? Pattern, _value: &Expresion) -> Result<(), Self::Error> {
+                self.visit_pattern(pattern)
+            }
+            /// A function nested in a body, its name is already bound.
+            fn visit_function(&mut self, function: &$($mut_)? FunctionDecl) -> Result<(), Self::Error> {
+                $walk_function(self, function)
+            }
+            /// A type written in a body, the walk doesn't go inside it.
+            fn visit_type(&mut self, _type: &$($mut_)? Type) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            /// A trait a value is cast to.
+            fn visit_trait(&mut self, _trait: &$($mut_)? SolidType) -> Result<(), Self::Error> {
+                Ok(())
+            }
+            fn scoped(&mut self, inside: impl FnOnce(&mut Self) -> Result<(), Self::Error>) -> Result<(), Self::Error> {
+                inside(self)
+            }
+            fn bind(&mut self, _name: &Token) {}
+        }
+
+        pub fn $walk_statment<V: $visit>(visitor: &mut V, statment: &$($mut_)? Statment) -> Result<(), V::Error> {
+            match statment {
+                Statment::VarCreation(var_creation) => {
+                    visitor.visit_statment(&$($mut_)? var_creation.value)?;
+                    if let Some(type_) = &$($mut_)? var_creation.type_of {
+                        visitor.visit_type(type_)?;
+                    }
+                    visitor.visit_pattern(&$($mut_)? var_creation.pattern)
+                }
+                Statment::FuncCreation(function) => {
+                    visitor.bind(&function.sig.name);
+                    visitor.visit_function(function)
+                }
+                Statment::Expresion(expresion) => visitor.visit_expresion(expresion),
+                Statment::If(if_) => {
+                    for (condition, statment) in &$($mut_)? if_.conditionals_and_statments {
+                        visitor.scoped(|visitor| {
+                            match condition {
+                                IfType::Boolean(expresion) => visitor.visit_expresion(expresion)?,
+                                IfType::IfLet { pattern, expresion } => {
+                                    visitor.visit_expresion(expresion)?;
+                                    visitor.visit_matched(pattern, expresion)?;
+                                }
+                            }
+                            visitor.visit_statment(statment)
+                        })?;
+                    }
+                    match &$($mut_)? if_.else_statment {
+                        Some(statment) => visitor.visit_statment(statment),
+                        None => Ok(()),
+                    }
+                }
+                Statment::While(while_) => {
+                    visitor.visit_expresion(&$($mut_)? while_.condition)?;
+                    visitor.visit_statment(&$($mut_)? while_.statment)
+                }
+                Statment::For(for_) => {
+                    visitor.visit_expresion(&$($mut_)? for_.iterable)?;
+                    visitor.scoped(|visitor| {
+                        visitor.bind(&for_.variable);
+                        visitor.visit_statment(&$($mut_)? for_.statment)
+                    })
+                }
+                Statment::Return(return_) => match &$($mut_)? return_.value {
+                    Some(statment) => visitor.visit_statment(statment),
+                    None => Ok(()),
+                },
+                Statment::ImReturn(statment) => visitor.visit_statment(statment),
+                Statment::Block(block) => visitor.scoped(|visitor| {
+                    for statment in &$($mut_)? block.statments {
+                        visitor.visit_statment(statment)?;
+                    }
+                    Ok(())
+                }),
+                Statment::Continue(_) | Statment::Break(_) | Statment::Panic(_) => Ok(()),
+            }
+        }
+
+        pub fn $walk_expresion<V: $visit>(visitor: &mut V, expresion: &$($mut_)? Expresion) -> Result<(), V::Error> {
+            match expresion {
+                Expresion::FunctionCall(call) => {
+                    visitor.visit_expresion(&$($mut_)? call.function)?;
+                    for generic in &$($mut_)? call.generics {
+                        visitor.visit_type(generic)?;
+                    }
+                    for argument in &$($mut_)? call.arguments {
+                        visitor.visit_expresion(argument)?;
+                    }
+                    Ok(())
+                }
+                Expresion::Constructor(constructor) => {
+                    if let Some(arena) = &$($mut_)? constructor.areana {
+                        visitor.visit_expresion(arena)?;
+                    }
+                    match &$($mut_)? constructor.object_to_construct {
+                        ConstructorType::StructConstructor(struct_) => {
+                            for field in &$($mut_)? struct_.fields {
+                                visitor.visit_expresion(&$($mut_)? field.value)?;
+                            }
+                        }
+                        ConstructorType::EnumConstructor(enum_) => {
+                            for field in &$($mut_)? enum_.fields {
+                                visitor.visit_expresion(&$($mut_)? field.value)?;
+                            }
+                        }
+                        ConstructorType::ArrayConstructor(elements) => {
+                            for element in elements {
+                                visitor.visit_expresion(element)?;
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+                Expresion::Cast(cast) => {
+                    visitor.visit_expresion(&$($mut_)? cast.expr)?;
+                    for trait_ in &$($mut_)? cast.traits_to_cast_to {
+                        visitor.visit_trait(trait_)?;
+                    }
+                    Ok(())
+                }
+                Expresion::Match(match_) => {
+                    visitor.visit_expresion(&$($mut_)? match_.scrutinee)?;
+                    for arm in &$($mut_)? match_.arms {
+                        visitor.scoped(|visitor| {
+                            visitor.visit_matched(&$($mut_)? arm.pattern, &match_.scrutinee)?;
+                            if let Some(guard) = &$($mut_)? arm.guard {
+                                visitor.visit_expresion(guard)?;
+                            }
+                            visitor.visit_expresion(&$($mut_)? arm.value)
+                        })?;
+                    }
+                    Ok(())
+                }
+                Expresion::Closure(closure) => {
+                    for (_, type_) in &$($mut_)? closure.parameters {
+                        visitor.visit_type(type_)?;
+                    }
+                    if let Some(out) = &$($mut_)? closure.out {
+                        visitor.visit_type(out)?;
+                    }
+                    visitor.scoped(|visitor| {
+                        for (name, _) in &closure.parameters {
+                            visitor.bind(name);
+                        }
+                        visitor.visit_expresion(&$($mut_)? closure.body)
+                    })
+                }
+                Expresion::FieldAcess(access) => visitor.visit_expresion(&$($mut_)? access.expr),
+                Expresion::Index(index) => {
+                    visitor.visit_expresion(&$($mut_)? index.expr)?;
+                    visitor.visit_expresion(&$($mut_)? index.index)
+                }
+                Expresion::Parens(parens) => visitor.visit_expresion(&$($mut_)? parens.expr),
+                Expresion::Tuple(tuple) => {
+                    for element in &$($mut_)? tuple.elements {
+                        visitor.visit_expresion(element)?;
+                    }
+                    Ok(())
+                }
+                Expresion::Unary(unary) => visitor.visit_expresion(&$($mut_)? unary.expr),
+                Expresion::Binary(binary) => {
+                    visitor.visit_expresion(&$($mut_)? binary.lhs)?;
+                    visitor.visit_expresion(&$($mut_)? binary.rhs)
+                }
+                Expresion::Block(block) => visitor.scoped(|visitor| {
+                    for statment in &$($mut_)? block.statments {
+                        visitor.visit_statment(statment)?;
+                    }
+                    Ok(())
+                }),
+                Expresion::IntLitteral(_)
+                | Expresion::FloatLitteral(_)
+                | Expresion::StringLitteral(_)
+                | Expresion::CharLitteral(_)
+                | Expresion::TypeAssocatedFunction(_)
+                | Expresion::VarAccess(_)
+                | Expresion::True(_)
+                | Expresion::False(_) => Ok(()),
+            }
+        }
+
+        /// Binds every name in `pattern`. A literal has nothing in it to visit.
+        pub fn $walk_pattern<V: $visit>(visitor: &mut V, pattern: &$($mut_)? Pattern) -> Result<(), V::Error> {
+            match pattern {
+                Pattern::Wildcard(_) | Pattern::Literal(_) => Ok(()),
+                Pattern::Binding(name) => {
+                    visitor.bind(name);
+                    Ok(())
+                }
+                Pattern::At(at) => {
+                    visitor.bind(&at.name);
+                    visitor.visit_pattern(&$($mut_)? at.pattern)
+                }
+                Pattern::Destructure(destructure) => {
+                    visitor.visit_type(&$($mut_)? destructure.type_of)?;
+                    for (field, pattern) in &$($mut_)? destructure.constructor {
+                        match pattern {
+                            Some(pattern) => visitor.visit_pattern(pattern)?,
+                            None => visitor.bind(field),
+                        }
+                    }
+                    Ok(())
+                }
+                Pattern::Tuple(tuple) => {
+                    for element in &$($mut_)? tuple.elements {
+                        visitor.visit_pattern(element)?;
+                    }
+                    Ok(())
+                }
+                Pattern::Or(alternatives) => {
+                    for alternative in alternatives {
+                        visitor.visit_pattern(alternative)?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+
+        /// The types in the signature of `function`, then its body with its parameters bound.
+        pub fn $walk_function<V: $visit>(visitor: &mut V, function: &$($mut_)? FunctionDecl) -> Result<(), V::Error> {
+            for (_, type_) in &$($mut_)? function.sig.parameters {
+                visitor.visit_type(type_)?;
+            }
+            visitor.visit_type(&$($mut_)? function.sig.out)?;
+            visitor.scoped(|visitor| {
+                for (name, _) in &function.sig.parameters {
+                    visitor.bind(name);
+                }
+                for statment in &$($mut_)? function.body.statments {
+                    visitor.visit_statment(statment)?;
+                }
+                Ok(())
+            })
+        }
+    };
+}
+
+visitor!(Visit, walk_statment, walk_expresion, walk_pattern, walk_function);
+visitor!(VisitMut, walk_statment_mut, walk_expresion_mut, walk_pattern_mut, walk_function_mut, mut);
@@ -0,0 +1,100 @@
+//! Turns parse and lex errors into the messages people read: where in the file it went wrong,
+//! the line it happened on with the offending text underlined, and any notes on how to fix it.
+//!
+//! ```text
+//! error: expected `;` or `}`, found `let`
+//!  --> main.cal:3:5
+//!   |
+//! 3 |     let y = 2;
+//!   |     ^^^
+//!   = note: ...
+//! ```
+
+use pub_fields::pub_fields;
+
+use crate::{
+    ast::parser::ParseError,
+    scanner::{LexError, Span},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[pub_fields]
+pub struct Diagnostic {
+    message: String,
+    span: Span,
+    notes: Vec<String>,
+    help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            notes: vec![],
+            help: None,
+        }
+    }
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+    /// Renders against `source`, the text of the file the span points into.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let start = self.span.byte_start.min(source.len());
+        let (line, column) = line_column(source, start);
+        let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |newline| start + newline);
+        let text = source[line_start..line_end].trim_end_matches('\r');
+
+        // only the first line of a span is shown, and even an empty one gets a caret
+        let end = self.span.byte_end.clamp(start, line_end);
+        let width = source[start..end].chars().count().max(1);
+        // copy tabs so the caret lines up however wide the terminal draws them
+        let indent = source[line_start..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        let gutter = " ".repeat(line.to_string().len());
+        let mut out = format!("error: {}\n", self.message);
+        out += &format!("{gutter}--> {file_name}:{line}:{column}\n");
+        out += &format!("{gutter} |\n");
+        out += &format!("{line} | {text}\n");
+        out += &format!("{gutter} | {indent}{}\n", "^".repeat(width));
+        for note in &self.notes {
+            out += &format!("{gutter} = note: {note}\n");
+        }
+        if let Some(help) = &self.help {
+            out += &format!("{gutter} = help: {help}\n");
+        }
+        out
+    }
+}
+
+/// The 1-based line and column of `offset`, columns count characters rather than bytes.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        let diagnostic = Diagnostic::new(err.to_string(), err.got.span);
+        match &err.note {
+            Some(note) => diagnostic.with_note(note.clone()),
+            None => diagnostic,
+        }
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(err: &LexError) -> Self {
+        Diagnostic {
+            help: err.suggestion().map(|suggestion| suggestion.message),
+            ..Diagnostic::new(err.to_string(), err.span())
+        }
+    }
+}
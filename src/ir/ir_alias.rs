@@ -0,0 +1,354 @@
+//! Swaps every use of a type alias for the type it names, so the rest of the IR never sees
+//! one. `type Pair<T> = Foo<T, T>;` makes `Pair<Int>` into `Foo<Int, Int>`.
+//!
+//! An alias is worked out once with its own generics left in, then those are filled in
+//! with the types it was given wherever it's used. An alias that needs itself to be worked
+//! out, directly or through other aliases, never ends and is an error.
+//!
+//! This is a pass of its own over the whole program, before any of it is made into IR,
+//! rather than part of `Type::from`. That's a plain conversion of one type, which can't see
+//! the aliases around it or say what went wrong, and aliases are used where there is no
+//! `Type` as well, in constructors and before `::`. Done first, every later pass, the
+//! constant evaluator, the checks and lowering, only ever sees the types aliases name.
+//!
+//! A newtype, `type Meters = new Float;`, isn't swapped for anything. It's made into the
+//! struct `struct Meters{value: Float}`, which the rest of the IR takes as any other.
+
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::{
+    ast::{
+        decl::{
+            Declaration, FieldDecl, FuncSig, FunctionDecl, GenericDecl, Program, SolidType, StructDecl, TraitType, Type,
+            TypeAliasDecl,
+        },
+        expresions::{ConstructorType, Expresion},
+        visit::{walk_expresion_mut, VisitMut},
+    },
+    scanner::{Span, Token},
+};
+
+use super::ir_type_data::CompileError;
+
+/// `program` with its aliases taken out and every use of one swapped for what it names, and
+/// its newtypes made into structs.
+pub fn expand(program: &Program) -> Result<Program, CompileError> {
+    let (newtypes, aliases): (Vec<_>, Vec<_>) = program
+        .code
+        .iter()
+        .filter_map(|declaration| match declaration {
+            Declaration::TypeAliasDecl(alias) => Some(alias),
+            _ => None,
+        })
+        .partition(|alias| alias.new);
+    let duplicates = aliases.iter().chain(&newtypes).map(|alias| alias.name.lexeme.clone()).duplicates().collect_vec();
+    if !duplicates.is_empty() {
+        return Err(CompileError::DuplicateTypeAliases(duplicates));
+    }
+    let types = program
+        .code
+        .iter()
+        .filter_map(|declaration| match declaration {
+            Declaration::StructDecl(struct_) => Some(&struct_.name.lexeme),
+            Declaration::EnumDecl(enum_) => Some(&enum_.name.lexeme),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let clashes = aliases
+        .iter()
+        .chain(&newtypes)
+        .map(|alias| alias.name.lexeme.clone())
+        .filter(|name| types.contains(name))
+        .sorted()
+        .collect_vec();
+    if !clashes.is_empty() {
+        return Err(CompileError::AliasesNamedLikeTypes(clashes));
+    }
+    let mut expander = Expander {
+        aliases: aliases.iter().map(|alias| (alias.name.lexeme.clone(), *alias)).collect(),
+        expanded: HashMap::new(),
+        expanding: vec![],
+        generics: vec![],
+    };
+    // an alias nothing uses still has to make sense
+    for alias in aliases.iter().sorted_by_key(|alias| &alias.name.lexeme) {
+        expander.alias(&alias.name.lexeme, alias.name.span)?;
+    }
+    let mut code = program
+        .code
+        .iter()
+        .filter_map(|declaration| match declaration {
+            Declaration::TypeAliasDecl(alias) if alias.new => Some(Declaration::StructDecl(newtype(alias))),
+            Declaration::TypeAliasDecl(_) => None,
+            declaration => Some(declaration.clone()),
+        })
+        .collect_vec();
+    code.iter_mut().try_for_each(|declaration| expander.declaration(declaration))?;
+    Ok(Program { docs: program.docs.clone(), code })
+}
+
+/// The struct the newtype `type Name = new Type;` is, its field is as visible as it is.
+fn newtype(alias: &TypeAliasDecl) -> StructDecl {
+    let value = FieldDecl {
+        visibility: alias.visibility,
+        name: Token { lexeme: "value".to_string(), ..alias.name.clone() },
+        type_of: alias.type_of.clone(),
+        span: alias.span,
+    };
+    StructDecl {
+        docs: alias.docs.clone(),
+        visibility: alias.visibility,
+        name: alias.name.clone(),
+        generics: alias.generics.clone(),
+        fields: vec![value],
+        span: alias.span,
+    }
+}
+
+struct Expander<'a> {
+    aliases: HashMap<String, &'a TypeAliasDecl>,
+    /// What each alias names with its generics still in, once it's been worked out.
+    expanded: HashMap<String, Type>,
+    /// The aliases being worked out, to catch one that needs itself.
+    expanding: Vec<String>,
+    generics: Vec<HashSet<String>>,
+}
+
+impl Expander<'_> {
+    fn declaration(&mut self, declaration: &mut Declaration) -> Result<(), CompileError> {
+        match declaration {
+            Declaration::StructDecl(struct_) => self.with_generics(&mut struct_.generics, |expander| {
+                struct_.fields.iter_mut().try_for_each(|field| expander.type_(&mut field.type_of))
+            }),
+            Declaration::EnumDecl(enum_) => self.with_generics(&mut enum_.generics, |expander| {
+                enum_
+                    .varients
+                    .iter_mut()
+                    .flat_map(|varient| &mut varient.fields)
+                    .try_for_each(|field| expander.type_(&mut field.type_of))
+            }),
+            Declaration::TraitDecl(trait_) => self.with_generics(&mut trait_.generics, |expander| {
+                trait_.funcs.iter_mut().try_for_each(|sig| expander.sig(sig, |_| Ok(())))
+            }),
+            Declaration::ImplDecl(impl_) => self.with_generics(&mut impl_.generics, |expander| {
+                if let Some(trait_) = &mut impl_.trait_to_impl {
+                    expander.type_(trait_)?;
+                }
+                expander.type_(&mut impl_.type_to_impl_on)?;
+                for const_ in &mut impl_.consts {
+                    expander.type_(&mut const_.type_of)?;
+                    expander.visit_expresion(&mut const_.value)?;
+                }
+                impl_.funcs.iter_mut().try_for_each(|function| expander.function(function))
+            }),
+            Declaration::ConstDecl(const_) => {
+                self.type_(&mut const_.type_of)?;
+                self.visit_expresion(&mut const_.value)
+            }
+            Declaration::StaticDecl(static_) => {
+                self.type_(&mut static_.type_of)?;
+                self.visit_expresion(&mut static_.value)
+            }
+            Declaration::FunctionDecl(function) => self.function(function),
+            Declaration::ModDecl(_) | Declaration::UseDecl(_) | Declaration::TypeAliasDecl(_) => Ok(()),
+        }
+    }
+    fn with_generics(
+        &mut self,
+        generics: &mut [GenericDecl],
+        inside: impl FnOnce(&mut Self) -> Result<(), CompileError>,
+    ) -> Result<(), CompileError> {
+        self.generics.push(generics.iter().map(|generic| generic.name.lexeme.clone()).collect());
+        let result = generics
+            .iter_mut()
+            .flat_map(|generic| &mut generic.constraints)
+            .try_for_each(|constraint| self.trait_type(constraint))
+            .and_then(|_| inside(self));
+        self.generics.pop();
+        result
+    }
+    fn sig(
+        &mut self,
+        sig: &mut FuncSig,
+        body: impl FnOnce(&mut Self) -> Result<(), CompileError>,
+    ) -> Result<(), CompileError> {
+        self.with_generics(&mut sig.generics, |expander| {
+            sig.parameters.iter_mut().try_for_each(|(_, type_)| expander.type_(type_))?;
+            expander.type_(&mut sig.out)?;
+            body(expander)
+        })
+    }
+    fn function(&mut self, function: &mut FunctionDecl) -> Result<(), CompileError> {
+        let body = &mut function.body;
+        self.sig(&mut function.sig, |expander| {
+            body.statments.iter_mut().try_for_each(|statment| expander.visit_statment(statment))
+        })
+    }
+    /// What the alias `name` names with its generics still in, `used` is blamed if it never ends.
+    fn alias(&mut self, name: &str, used: Span) -> Result<Type, CompileError> {
+        if let Some(type_) = self.expanded.get(name) {
+            return Ok(type_.clone());
+        }
+        if self.expanding.iter().any(|expanding| expanding == name) {
+            return Err(CompileError::CyclicTypeAlias(name.to_string(), used));
+        }
+        let alias = self.aliases[name];
+        let mut type_ = alias.type_of.clone();
+        self.expanding.push(name.to_string());
+        // the generics around where it's used mean nothing inside the alias
+        let outer = std::mem::take(&mut self.generics);
+        let mut generics = alias.generics.clone();
+        let result = self.with_generics(&mut generics, |expander| expander.type_(&mut type_));
+        self.generics = outer;
+        self.expanding.pop();
+        result?;
+        self.expanded.insert(name.to_string(), type_.clone());
+        Ok(type_)
+    }
+    /// The type `solid` names when it is an alias, with the generics it was given filled in.
+    fn expanded(&mut self, solid: &SolidType) -> Result<Option<Type>, CompileError> {
+        let name = &solid.name.lexeme;
+        if !solid.path.is_empty() || self.generics.iter().any(|generics| generics.contains(name)) {
+            return Ok(None);
+        }
+        let Some(alias) = self.aliases.get(name.as_str()).copied() else {
+            return Ok(None);
+        };
+        if alias.generics.len() != solid.generics.len() {
+            return Err(CompileError::NumberOfGenericsAndTypesGivenDoNotMatchInItem(
+                name.clone(),
+                alias.generics.len(),
+                solid.generics.len(),
+                solid.span,
+            ));
+        }
+        let mut type_ = self.alias(name, solid.span)?;
+        let given = alias
+            .generics
+            .iter()
+            .map(|generic| generic.name.lexeme.as_str())
+            .zip(&solid.generics)
+            .collect::<HashMap<_, _>>();
+        substitute(&mut type_, &given, solid)?;
+        Ok(Some(type_))
+    }
+    /// The type the alias `name` names when it's written without generics, in a constructor
+    /// or before `::`, where it can only be a struct or an enum.
+    fn named(&mut self, path: &[Token], name: &Token) -> Result<Option<SolidType>, CompileError> {
+        let lexeme = &name.lexeme;
+        if !path.is_empty()
+            || self.generics.iter().any(|generics| generics.contains(lexeme))
+            || !self.aliases.contains_key(lexeme.as_str())
+        {
+            return Ok(None);
+        }
+        match self.alias(lexeme, name.span)? {
+            Type::PhysicalType(solid) => Ok(Some(solid)),
+            _ => Err(CompileError::AliasIsNotNamedType(lexeme.clone(), name.span)),
+        }
+    }
+    fn type_(&mut self, type_: &mut Type) -> Result<(), CompileError> {
+        match type_ {
+            Type::PhysicalType(solid) => {
+                solid.generics.iter_mut().try_for_each(|generic| self.type_(generic))?;
+                if let Some(expanded) = self.expanded(solid)? {
+                    *type_ = expanded;
+                }
+                Ok(())
+            }
+            Type::Array(solid, _) => {
+                solid.generics.iter_mut().try_for_each(|generic| self.type_(generic))?;
+                match self.expanded(solid)? {
+                    Some(Type::PhysicalType(expanded)) => *solid = expanded,
+                    Some(_) => return Err(CompileError::AliasInArrayIsNotNamedType(solid.name.lexeme.clone(), solid.span)),
+                    None => {}
+                }
+                Ok(())
+            }
+            Type::Unit(_) => Ok(()),
+            Type::FP(fp) => {
+                fp.args.iter_mut().try_for_each(|arg| self.type_(arg))?;
+                self.type_(&mut fp.out)
+            }
+            Type::DynamicType(traits, _) => traits.iter_mut().try_for_each(|trait_| self.trait_type(trait_)),
+            Type::Tuple(types, _) => types.iter_mut().try_for_each(|type_| self.type_(type_)),
+        }
+    }
+    fn trait_type(&mut self, trait_: &mut TraitType) -> Result<(), CompileError> {
+        trait_.generics.iter_mut().try_for_each(|generic| self.type_(generic))
+    }
+}
+
+impl VisitMut for Expander<'_> {
+    type Error = CompileError;
+    fn visit_expresion(&mut self, expresion: &mut Expresion) -> Result<(), CompileError> {
+        match expresion {
+            Expresion::TypeAssocatedFunction(function) => {
+                if let Some(named) = self.named(&function.path, &function.type_)? {
+                    function.path = named.path;
+                    function.type_.lexeme = named.name.lexeme;
+                }
+            }
+            Expresion::Constructor(constructor) => match &mut constructor.object_to_construct {
+                ConstructorType::StructConstructor(struct_) => {
+                    if let Some(named) = self.named(&[], &struct_.struct_name)? {
+                        // a struct constructor has no path of its own to put the modules in
+                        let segments = named.path.iter().chain([&named.name]).map(|segment| segment.lexeme.as_str());
+                        struct_.struct_name.lexeme = segments.collect_vec().join("::");
+                    }
+                }
+                ConstructorType::EnumConstructor(enum_) => {
+                    if let Some(named) = self.named(&enum_.path, &enum_.enum_name)? {
+                        enum_.path = named.path;
+                        enum_.enum_name.lexeme = named.name.lexeme;
+                    }
+                }
+                ConstructorType::ArrayConstructor(_) => {}
+            },
+            _ => {}
+        }
+        walk_expresion_mut(self, expresion)
+    }
+    fn visit_function(&mut self, function: &mut FunctionDecl) -> Result<(), CompileError> {
+        self.function(function)
+    }
+    fn visit_type(&mut self, type_: &mut Type) -> Result<(), CompileError> {
+        self.type_(type_)
+    }
+    fn visit_trait(&mut self, trait_: &mut SolidType) -> Result<(), CompileError> {
+        trait_.generics.iter_mut().try_for_each(|generic| self.type_(generic))
+    }
+}
+
+/// Swaps each generic named in `given` for the type given for it, `used` is blamed when one
+/// that goes in `[]` isn't a named type.
+fn substitute(type_: &mut Type, given: &HashMap<&str, &Type>, used: &SolidType) -> Result<(), CompileError> {
+    let generic = |solid: &SolidType| match solid.path.is_empty() && solid.generics.is_empty() {
+        true => given.get(solid.name.lexeme.as_str()).copied(),
+        false => None,
+    };
+    match type_ {
+        Type::PhysicalType(solid) => match generic(solid) {
+            Some(replacement) => *type_ = replacement.clone(),
+            None => solid.generics.iter_mut().try_for_each(|generic| substitute(generic, given, used))?,
+        },
+        Type::Array(solid, _) => match generic(solid) {
+            Some(Type::PhysicalType(replacement)) => *solid = replacement.clone(),
+            Some(_) => return Err(CompileError::AliasInArrayIsNotNamedType(used.name.lexeme.clone(), used.span)),
+            None => solid.generics.iter_mut().try_for_each(|generic| substitute(generic, given, used))?,
+        },
+        Type::Unit(_) => {}
+        Type::FP(fp) => {
+            fp.args.iter_mut().try_for_each(|arg| substitute(arg, given, used))?;
+            substitute(&mut fp.out, given, used)?;
+        }
+        Type::DynamicType(traits, _) => traits
+            .iter_mut()
+            .flat_map(|trait_| &mut trait_.generics)
+            .try_for_each(|generic| substitute(generic, given, used))?,
+        Type::Tuple(types, _) => types.iter_mut().try_for_each(|type_| substitute(type_, given, used))?,
+    }
+    Ok(())
+}
@@ -2,6 +2,8 @@ use std::collections::HashMap;
 
 use pub_fields::pub_fields;
 
+use crate::scanner::{FloatSuffix, IntSuffix};
+
 use super::ir_type_data::Function;
 
 #[derive(Debug,Clone)]
@@ -16,16 +18,35 @@ pub enum IRCode{
     VarCreate(u16,Calc),
     VarAssign(u16,Calc),
     FieldAssign(u16,u16,Calc),
+    /// Stores into the element of the array in the slot at the index.
+    IndexAssign(u16,ValueAccess,Calc),
+    /// Carries on from the instruction at this index.
     Jmp(usize),
+    /// Skips the next instruction when the two values are equal, so a `JNE` followed by a
+    /// `Jmp` only jumps when they differ.
     JNE(ValueAccess,ValueAccess),
-    Call(ValueAccess,Vec<u16>),
+    /// Calls the function with the values in the slots, and puts what it returns in the
+    /// first slot.
+    Call(u16,ValueAccess,Vec<u16>),
+    /// Stores through the reference in the slot.
+    DerefAssign(u16,Calc),
+    /// Stores into the `static mut` with that name.
+    StaticAssign(String,Calc),
+    /// Running off the end of the code returns `()`.
+    Return(ValueAccess),
+    Panic,
 }
 
 #[derive(Debug,Clone)]
 pub enum Calc{
+    /// Room for that many fields, a struct's in name order. A varient keeps which one of its
+    /// enum's varients it is, by name order, in field 0 and its own fields after it.
     Alloc(u16),
+    /// An array of the values, in the arena when one is given.
+    Array(Vec<ValueAccess>,Option<ValueAccess>),
     Clone(ValueAccess),
     ArrayAcess(ValueAccess,ValueAccess),
+    ArrayLen(ValueAccess),
     FieldAccess(ValueAccess,u16),
     Add(ValueAccess,ValueAccess),
     Subtract(ValueAccess,ValueAccess),
@@ -44,15 +65,27 @@ pub enum Calc{
     SHR(ValueAccess, ValueAccess),
     Negate(ValueAccess),
     Not(ValueAccess),
+    /// A reference to the slot, how a closure shares a `mut` local with the code around it.
+    Ref(u16),
+    Deref(ValueAccess),
+    /// The function in the chunk's `functions` with that name, along with the values it
+    /// captured in the order its first slots expect them.
+    Closure(String,Vec<ValueAccess>),
 }
 
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
 pub enum ValueAccess{
+    Unit,
     VarAccess(u16),
-    IntConst(u64),
-    FloatConst(f64),
+    IntConst(u64, Option<IntSuffix>),
+    FloatConst(f64, Option<FloatSuffix>),
     StringConst(Box<str>),
+    CharConst(char),
     True,
     False,
+    /// The value the static with that name has right then.
+    Static(String),
+    /// A function by name, one in the `functions` of the chunk or a chunk around it before
+    /// one in the IR. A method is `Type::name`.
     Func(String),
 }
\ No newline at end of file
@@ -0,0 +1,100 @@
+//! What a closure captures from around it, and whether it fits the function pointer type
+//! it is given to.
+
+use std::{collections::HashSet, convert::Infallible};
+
+use crate::{
+    ast::{
+        decl::FunctionDecl,
+        expresions::{Closure, Expresion},
+        visit::{walk_expresion, Visit},
+    },
+    scanner::Token,
+};
+
+use super::ir_type_data::{CompileError, Type, FP};
+
+/// Every name `closure` uses without binding it itself, in the order they are first used.
+/// Some may not be locals at all, a name that isn't found around the closure is left alone.
+pub fn captures(closure: &Closure) -> Vec<Token> {
+    let mut finder = CaptureFinder { scopes: vec![], free: vec![] };
+    let Ok(()) = finder.scoped(|finder| {
+        for (name, _) in &closure.parameters {
+            finder.bind(name);
+        }
+        finder.visit_expresion(&closure.body)
+    });
+    finder.free
+}
+
+/// Checks `closure` takes and gives back exactly the types `expected` says.
+pub fn check_signature(expected: &FP, closure: &Closure) -> Result<(), CompileError> {
+    let found = FP {
+        arg: closure.parameters.iter().map(|(_, type_)| Type::from(type_)).collect(),
+        out: Box::new(closure.out.as_ref().map_or(Type::Unit, Type::from)),
+    };
+    let (expected, found) = (describe(&Type::FP(expected.clone())), describe(&Type::FP(found)));
+    if expected != found {
+        return Err(CompileError::MismatchedClosureType(expected, found, closure.span));
+    }
+    Ok(())
+}
+
+/// Writes `type_` out the way it is written in source.
+pub fn describe(type_: &Type) -> String {
+    let list = |types: &[Type], separator: &str| types.iter().map(describe).collect::<Vec<_>>().join(separator);
+    let generics = |types: &[Type]| match types {
+        [] => String::new(),
+        types => format!("<{}>", list(types, ", ")),
+    };
+    match type_ {
+        Type::Unit => "()".to_string(),
+        Type::Actual(actual) => format!("{}{}", actual.name, generics(&actual.types_in_generics)),
+        Type::Array(actual) => format!("[{}{}]", actual.name, generics(&actual.types_in_generics)),
+        Type::DynType(traits) => format!(
+            "({})",
+            traits
+                .iter()
+                .map(|trait_| format!("{}{}", trait_.name, generics(&trait_.types_in_generics)))
+                .collect::<Vec<_>>()
+                .join(" + ")
+        ),
+        Type::FP(fp) => format!("|{}|: {}", list(&fp.arg, ", "), describe(&fp.out)),
+        Type::Tuple(types) if types.len() == 1 => format!("({},)", describe(&types[0])),
+        Type::Tuple(types) => format!("({})", list(types, ", ")),
+    }
+}
+
+struct CaptureFinder {
+    scopes: Vec<HashSet<String>>,
+    free: Vec<Token>,
+}
+
+impl Visit for CaptureFinder {
+    type Error = Infallible;
+    fn visit_expresion(&mut self, expresion: &Expresion) -> Result<(), Infallible> {
+        if let Expresion::VarAccess(name) = expresion {
+            let bound = self.scopes.iter().any(|scope| scope.contains(&name.lexeme));
+            if !bound && !self.free.iter().any(|free| free.lexeme == name.lexeme) {
+                self.free.push(name.clone());
+            }
+        }
+        walk_expresion(self, expresion)
+    }
+    // a nested function can't see the locals around it
+    fn visit_function(&mut self, _function: &FunctionDecl) -> Result<(), Infallible> {
+        Ok(())
+    }
+    fn scoped(&mut self, inside: impl FnOnce(&mut Self) -> Result<(), Infallible>) -> Result<(), Infallible> {
+        self.scopes.push(HashSet::new());
+        let result = inside(self);
+        self.scopes.pop();
+        result
+    }
+    fn bind(&mut self, name: &Token) {
+        self.scopes
+            .last_mut()
+            .expect("names are only bound inside a scope")
+            .insert(name.lexeme.clone());
+    }
+}
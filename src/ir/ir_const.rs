@@ -0,0 +1,303 @@
+//! Works out the value of every `const` while compiling, so a use of one can be swapped for
+//! its value, and the value every `static` starts with.
+//!
+//! The value of a constant can use literals, other constants, `-` and `!`, arithmetic,
+//! comparisons, `and`, `or`, `&`, `|`, `<<`, `>>` and `+` on two strings. Integers are
+//! worked out wider than any integer type and checked against the declared type at the
+//! end, a suffix on a literal in there doesn't change that type. `Int` is 64 bits and signed.
+//!
+//! A static can change while the program runs, so no value can use one, not even another
+//! static's.
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use itertools::Itertools;
+
+use crate::{
+    ast::{
+        decl::{ConstDecl, ImplDecl, StaticDecl, Type as ASTType},
+        expresions::{BinaryOp, Expresion, UnaryOp},
+        parser::Spanned,
+    },
+    scanner::{FloatSuffix, IntSuffix, Span},
+};
+
+use super::{
+    ir_chunk::ValueAccess,
+    ir_type_data::{CompileError, Const, Static, Type},
+};
+
+/// Every constant, by the name it's used by.
+pub type Consts = HashMap<String, Const>;
+/// Every static, by its name.
+pub type Statics = HashMap<String, Static>;
+
+/// Works out every constant, top level ones by their name and the ones in an impl as
+/// `Type::NAME`, then the value each static starts with.
+pub fn evaluate(
+    consts: &[&ConstDecl],
+    statics: &[&StaticDecl],
+    impls: &[&ImplDecl],
+) -> Result<(Consts, Statics), CompileError> {
+    let mut evaluator = Evaluator {
+        decls: HashMap::new(),
+        statics: statics.iter().map(|static_| static_.name.lexeme.clone()).collect(),
+        done: HashMap::new(),
+        evaluating: vec![],
+    };
+    let mut duplicates = vec![];
+    let top_level = consts.iter().map(|const_| (const_.name.lexeme.clone(), *const_, None));
+    let associated = impls.iter().flat_map(|impl_| {
+        let type_name = match &impl_.type_to_impl_on {
+            ASTType::PhysicalType(solid) => Some(solid.name.lexeme.clone()),
+            _ => None,
+        };
+        impl_.consts.iter().map(move |const_| {
+            let name = match &type_name {
+                Some(type_name) => format!("{type_name}::{}", const_.name.lexeme),
+                None => const_.name.lexeme.clone(),
+            };
+            (name, const_, type_name.clone())
+        })
+    });
+    for (name, const_, self_type) in top_level.chain(associated) {
+        if evaluator.decls.insert(name.clone(), (const_, self_type)).is_some() {
+            duplicates.push(name);
+        }
+    }
+    if !duplicates.is_empty() {
+        return Err(CompileError::DuplicateConsts(duplicates));
+    }
+    let mut taken = evaluator.decls.keys().cloned().collect::<HashSet<_>>();
+    let duplicates = statics
+        .iter()
+        .filter(|static_| !taken.insert(static_.name.lexeme.clone()))
+        .map(|static_| static_.name.lexeme.clone())
+        .collect::<Vec<_>>();
+    if !duplicates.is_empty() {
+        return Err(CompileError::DuplicateStatics(duplicates));
+    }
+    let names = evaluator.decls.keys().cloned().sorted().collect::<Vec<_>>();
+    let consts = names
+        .into_iter()
+        .map(|name| {
+            let value = evaluator.constant(&name, Span::default())?;
+            let (const_, _) = evaluator.decls[&name];
+            let const_ = Const {
+                type_: Type::from(&const_.type_of),
+                value: value.value_access(&const_.type_of),
+            };
+            Ok((name, const_))
+        })
+        .collect::<Result<_, CompileError>>()?;
+    let statics = statics
+        .iter()
+        .map(|decl| {
+            let value = evaluator.expresion(&decl.value, None)?;
+            let value = fit(value, &decl.type_of, decl.value.span())?;
+            let static_ = Static {
+                mutable: decl.mutable,
+                type_: Type::from(&decl.type_of),
+                value: value.value_access(&decl.type_of),
+            };
+            Ok((decl.name.lexeme.clone(), static_))
+        })
+        .collect::<Result<_, CompileError>>()?;
+    Ok((consts, statics))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Int(i128),
+    Float(f64),
+    String(String),
+    Char(char),
+    Bool(bool),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Char(_) => "char",
+            Value::Bool(_) => "bool",
+        }
+    }
+    /// The value as the type it was declared as, which `fit` has checked it can be.
+    fn value_access(&self, type_: &ASTType) -> ValueAccess {
+        let suffix = type_name(type_).and_then(|name| IntSuffix::from_str(name).ok());
+        let float_suffix = type_name(type_).and_then(|name| FloatSuffix::from_str(name).ok());
+        match self {
+            Value::Int(int) => ValueAccess::IntConst(*int as u64, suffix),
+            Value::Float(float) => ValueAccess::FloatConst(*float, float_suffix),
+            Value::String(string) => ValueAccess::StringConst(string.as_str().into()),
+            Value::Char(char) => ValueAccess::CharConst(*char),
+            Value::Bool(true) => ValueAccess::True,
+            Value::Bool(false) => ValueAccess::False,
+        }
+    }
+}
+
+fn type_name(type_: &ASTType) -> Option<&str> {
+    match type_ {
+        ASTType::PhysicalType(solid) if solid.generics.is_empty() => Some(&solid.name.lexeme),
+        _ => None,
+    }
+}
+
+/// Checks that `value` is something of `type_` can hold, `span` is where the value came from.
+fn fit(value: Value, type_: &ASTType, span: Span) -> Result<Value, CompileError> {
+    let mismatch = |value: &Value| {
+        let expected = type_name(type_).map_or_else(|| "a type a constant can have".to_string(), str::to_string);
+        Err(CompileError::MismatchedConstType(expected, value.kind().to_string(), span))
+    };
+    let (min, max) = match (type_name(type_), &value) {
+        (Some("Int"), Value::Int(_)) => (i64::MIN as i128, i64::MAX as i128),
+        (Some(name), Value::Int(_)) => match IntSuffix::from_str(name) {
+            Ok(suffix) if suffix.is_signed() => (-(suffix.max_magnitude(true) as i128), suffix.max_magnitude(false) as i128),
+            Ok(suffix) => (0, suffix.max_magnitude(false) as i128),
+            Err(_) => return mismatch(&value),
+        },
+        (Some("Float"), Value::Float(_))
+        | (Some("String"), Value::String(_))
+        | (Some("Char"), Value::Char(_))
+        | (Some("Bool"), Value::Bool(_)) => return Ok(value),
+        (Some(name), Value::Float(_)) if FloatSuffix::from_str(name).is_ok() => return Ok(value),
+        _ => return mismatch(&value),
+    };
+    match value {
+        Value::Int(int) if (min..=max).contains(&int) => Ok(value),
+        _ => Err(CompileError::ConstOverflow(span)),
+    }
+}
+
+struct Evaluator<'a> {
+    /// Every constant by its full name, with the type `Self` is for one in an impl.
+    decls: HashMap<String, (&'a ConstDecl, Option<String>)>,
+    /// The name of every static, which a value can't use.
+    statics: HashSet<String>,
+    done: HashMap<String, Value>,
+    /// The constants being worked out, to catch one that needs its own value.
+    evaluating: Vec<String>,
+}
+
+impl Evaluator<'_> {
+    fn constant(&mut self, name: &str, used: Span) -> Result<Value, CompileError> {
+        if let Some(value) = self.done.get(name) {
+            return Ok(value.clone());
+        }
+        let Some((const_, self_type)) = self.decls.get(name).cloned() else {
+            if self.statics.contains(name) {
+                return Err(CompileError::NotConstant(used));
+            }
+            return Err(CompileError::UnknownVariable(name.to_string(), used));
+        };
+        if self.evaluating.iter().any(|evaluating| evaluating == name) {
+            return Err(CompileError::CyclicConst(name.to_string(), const_.name.span));
+        }
+        self.evaluating.push(name.to_string());
+        let value = self.expresion(&const_.value, self_type.as_deref());
+        self.evaluating.pop();
+        let value = fit(value?, &const_.type_of, const_.value.span())?;
+        self.done.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+    fn expresion(&mut self, expresion: &Expresion, self_type: Option<&str>) -> Result<Value, CompileError> {
+        Ok(match expresion {
+            Expresion::IntLitteral(int) if int.negative => Value::Int(int.value as i64 as i128),
+            Expresion::IntLitteral(int) => Value::Int(int.value as i128),
+            Expresion::FloatLitteral(float) => Value::Float(float.value),
+            Expresion::StringLitteral(string) => Value::String(string.value.clone()),
+            Expresion::CharLitteral(char) => Value::Char(char.value),
+            Expresion::True(_) => Value::Bool(true),
+            Expresion::False(_) => Value::Bool(false),
+            Expresion::Parens(parens) => self.expresion(&parens.expr, self_type)?,
+            Expresion::VarAccess(name) => self.constant(&name.lexeme, name.span)?,
+            Expresion::TypeAssocatedFunction(function) => {
+                let type_name = match function.type_.lexeme.as_str() {
+                    "Self" => self_type.unwrap_or("Self"),
+                    type_name => type_name,
+                };
+                self.constant(&format!("{type_name}::{}", function.func_name.lexeme), function.span)?
+            }
+            Expresion::Unary(unary) => match (&unary.unary_op, self.expresion(&unary.expr, self_type)?) {
+                (UnaryOp::Negate, Value::Int(int)) => Value::Int(-int),
+                (UnaryOp::Negate, Value::Float(float)) => Value::Float(-float),
+                (UnaryOp::Not, Value::Bool(bool)) => Value::Bool(!bool),
+                (UnaryOp::Negate, value) => return Err(mismatch("a number", &value, unary.expr.span())),
+                (UnaryOp::Not, value) => return Err(mismatch("bool", &value, unary.expr.span())),
+            },
+            Expresion::Binary(binary) => {
+                let lhs = self.expresion(&binary.lhs, self_type)?;
+                let rhs = self.expresion(&binary.rhs, self_type)?;
+                binary_op(&binary.binary_op, lhs, rhs, binary.rhs.span(), binary.span)?
+            }
+            _ => return Err(CompileError::NotConstant(expresion.span())),
+        })
+    }
+}
+
+fn mismatch(expected: &str, found: &Value, span: Span) -> CompileError {
+    CompileError::MismatchedConstType(expected.to_string(), found.kind().to_string(), span)
+}
+
+/// `lhs op rhs`, `rhs_span` is blamed when the two don't go together and `span` when the
+/// result doesn't make sense.
+fn binary_op(op: &BinaryOp, lhs: Value, rhs: Value, rhs_span: Span, span: Span) -> Result<Value, CompileError> {
+    use BinaryOp as Op;
+    let overflow = || CompileError::ConstOverflow(span);
+    Ok(match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => match op {
+            Op::Add => Value::Int(lhs.checked_add(rhs).ok_or_else(overflow)?),
+            Op::Subtract => Value::Int(lhs.checked_sub(rhs).ok_or_else(overflow)?),
+            Op::Mult => Value::Int(lhs.checked_mul(rhs).ok_or_else(overflow)?),
+            Op::Div | Op::Mod if rhs == 0 => return Err(CompileError::ConstDivideByZero(span)),
+            Op::Div => Value::Int(lhs / rhs),
+            Op::Mod => Value::Int(lhs % rhs),
+            Op::BitwiseAnd => Value::Int(lhs & rhs),
+            Op::BitwiseOr => Value::Int(lhs | rhs),
+            Op::SHL | Op::SHR if !(0..64).contains(&rhs) => return Err(overflow()),
+            Op::SHL => Value::Int(lhs.checked_shl(rhs as u32).filter(|int| int >> rhs == lhs).ok_or_else(overflow)?),
+            Op::SHR => Value::Int(lhs >> rhs),
+            op => compare(op, &lhs, &rhs, span)?,
+        },
+        (Value::Float(lhs), Value::Float(rhs)) => match op {
+            Op::Add => Value::Float(lhs + rhs),
+            Op::Subtract => Value::Float(lhs - rhs),
+            Op::Mult => Value::Float(lhs * rhs),
+            Op::Div => Value::Float(lhs / rhs),
+            Op::Mod => Value::Float(lhs % rhs),
+            op => compare(op, &lhs, &rhs, span)?,
+        },
+        (Value::String(lhs), Value::String(rhs)) => match op {
+            Op::Add => Value::String(lhs + &rhs),
+            op => compare(op, &lhs, &rhs, span)?,
+        },
+        (Value::Char(lhs), Value::Char(rhs)) => compare(op, &lhs, &rhs, span)?,
+        (Value::Bool(lhs), Value::Bool(rhs)) => match op {
+            Op::LogicalAnd | Op::BitwiseAnd => Value::Bool(lhs && rhs),
+            Op::LogicalOr | Op::BitwiseOr => Value::Bool(lhs || rhs),
+            Op::Equal => Value::Bool(lhs == rhs),
+            Op::NE => Value::Bool(lhs != rhs),
+            _ => return Err(CompileError::NotConstant(span)),
+        },
+        (lhs, rhs) => return Err(mismatch(lhs.kind(), &rhs, rhs_span)),
+    })
+}
+
+fn compare<T: PartialOrd>(op: &BinaryOp, lhs: &T, rhs: &T, span: Span) -> Result<Value, CompileError> {
+    Ok(Value::Bool(match op {
+        BinaryOp::Equal => lhs == rhs,
+        BinaryOp::NE => lhs != rhs,
+        BinaryOp::Lessthan => lhs < rhs,
+        BinaryOp::Greaterthan => lhs > rhs,
+        BinaryOp::LE => lhs <= rhs,
+        BinaryOp::GE => lhs >= rhs,
+        _ => return Err(CompileError::NotConstant(span)),
+    }))
+}
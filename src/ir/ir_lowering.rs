@@ -0,0 +1,1063 @@
+//! Lowers function bodies from the AST into `IRChunk` code.
+//!
+//! Every local gets a slot of its own, numbered in the order they are created starting with
+//! the parameters, and every intermediate result goes in a fresh slot too. Loops of all kinds
+//! come out in the same shape, with `break` jumping to `exit` and `continue` to `top`:
+//!
+//! ```text
+//! top:  <condition into c>
+//!       JNE(c, True)
+//!       Jmp(exit)
+//!       <body>
+//!       Jmp(top)
+//! exit:
+//! ```
+//!
+//! A closure becomes a function of its own in the chunk's `functions`, with what it captures
+//! in its first slots and its parameters after them. A local that isn't `mut` can't change, so
+//! a copy of it is captured, a `mut` one is captured by reference so both sides see every
+//! change to it. A `fn` inside a body becomes one too, but captures nothing.
+//!
+//! A pattern in an `if let` or a `match` is tested a part at a time, jumping to whatever comes
+//! next as soon as a part doesn't match. The fields of a struct are found by name, which needs
+//! the type of the value, so the struct or enum each local holds is kept when it is known.
+
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use crate::{
+    ast::{
+        decl::FunctionDecl,
+        expresions::{Binary, BinaryOp, Closure, Constructor, ConstructorType, Expresion, FuncCall, Match, Tuple, UnaryOp},
+        parser::Spanned,
+        statments::{Block, For, If, IfType, Pattern, Statment, While},
+    },
+    scanner::{Span, Token, TokenType},
+};
+
+use super::{
+    ir_chunk::{Calc, IRChunk, IRCode, ValueAccess},
+    ir_closure,
+    ir_pattern::{self, IRPattern},
+    ir_type_data::{ActualType, CompileError, FuncTag, Function, Type, IR},
+};
+
+/// Lowers the body of `function`, its parameters take the first slots in order.
+pub fn lower_function(ir: &IR, function: &FunctionDecl) -> Result<IRChunk, CompileError> {
+    FunctionLowerer::new(ir, None, HashSet::new()).function(function)
+}
+
+/// Lowers the body of `function` from an impl on `self_type`, which `Self` stands for in it.
+pub fn lower_method(ir: &IR, function: &FunctionDecl, self_type: &str) -> Result<IRChunk, CompileError> {
+    FunctionLowerer::new(ir, Some(self_type.to_string()), HashSet::new()).function(function)
+}
+
+struct FunctionLowerer<'a> {
+    ir: &'a IR,
+    code: Vec<IRCode>,
+    // the closures made so far, named by the order they were made in, and the `fn`s
+    functions: HashMap<String, Function>,
+    scopes: Vec<HashMap<String, Local>>,
+    next_slot: u16,
+    loops: Vec<Loop>,
+    self_type: Option<String>,
+    // the `fn`s declared in the bodies around this one so far
+    items: HashSet<String>,
+    // the type of what is in a slot, when it is known, with `Self` swapped for the impl's type
+    types: HashMap<u16, Type>,
+    // the slots the names an or-pattern binds go in, whichever alternative matches
+    or_slots: Vec<HashMap<String, u16>>,
+    // what the function being lowered gives back
+    out: Type,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Local {
+    slot: u16,
+    mutable: bool,
+    // the slot holds a reference to the value rather than the value
+    by_ref: bool,
+}
+
+/// Somewhere an assignment can store to.
+enum Place {
+    Local(Local),
+    /// A `static mut`, by name.
+    Static(String),
+    /// A field of the struct or tuple in the slot.
+    Field(u16, u16),
+    /// An element of the array in the slot.
+    Index(u16, ValueAccess),
+}
+
+/// The loop `break` and `continue` are in.
+struct Loop {
+    top: usize,
+    // jumps to the end of the loop, pointed at it once the end is known
+    exits: Vec<usize>,
+}
+
+impl<'a> FunctionLowerer<'a> {
+    fn new(ir: &'a IR, self_type: Option<String>, items: HashSet<String>) -> Self {
+        Self {
+            ir,
+            code: vec![],
+            functions: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            next_slot: 0,
+            loops: vec![],
+            self_type,
+            items,
+            types: HashMap::new(),
+            or_slots: vec![],
+            out: Type::Unit,
+        }
+    }
+    /// A lowerer for a function inside this one, which can see the same `fn`s.
+    fn inner(&self) -> FunctionLowerer<'a> {
+        FunctionLowerer::new(self.ir, self.self_type.clone(), self.items.clone())
+    }
+    /// The value of the body is returned when it has one.
+    fn function(mut self, function: &FunctionDecl) -> Result<IRChunk, CompileError> {
+        for (name, type_) in &function.sig.parameters {
+            let slot = self.bind(name, false);
+            self.set_type(slot, &Type::from(type_));
+        }
+        self.out = Type::from(&function.sig.out);
+        if let Some(Statment::ImReturn(value)) = function.body.statments.last() {
+            if let Statment::Expresion(value) = &**value {
+                fits(&self.out, value)?;
+            }
+        }
+        let value = self.block(&function.body)?;
+        if value != ValueAccess::Unit {
+            self.code.push(IRCode::Return(value));
+        }
+        Ok(IRChunk {
+            functions: self.functions,
+            code: self.code,
+        })
+    }
+    fn slot(&mut self) -> u16 {
+        let slot = self.next_slot;
+        self.next_slot = self
+            .next_slot
+            .checked_add(1)
+            .expect("a function has fewer than u16::MAX locals");
+        slot
+    }
+    /// Gives `name` a new slot in the innermost scope, shadowing anything it had before.
+    fn bind(&mut self, name: &Token, mutable: bool) -> u16 {
+        self.bind_name(name.lexeme.clone(), mutable, false)
+    }
+    fn bind_name(&mut self, name: String, mutable: bool, by_ref: bool) -> u16 {
+        let slot = self.slot();
+        self.scopes
+            .last_mut()
+            .expect("there is always a function scope")
+            .insert(name, Local { slot, mutable, by_ref });
+        slot
+    }
+    fn lookup(&self, name: &Token) -> Result<Local, CompileError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).copied())
+            .ok_or_else(|| CompileError::UnknownVariable(name.lexeme.clone(), name.span))
+    }
+    /// Puts `calc` in a fresh slot.
+    fn temp(&mut self, calc: Calc) -> ValueAccess {
+        let slot = self.slot();
+        self.code.push(IRCode::VarCreate(slot, calc));
+        ValueAccess::VarAccess(slot)
+    }
+    /// The slot `value` is in, putting it in a fresh one when it isn't in one already.
+    fn in_slot(&mut self, value: ValueAccess) -> u16 {
+        match value {
+            ValueAccess::VarAccess(slot) => slot,
+            value => match self.temp(Calc::Clone(value)) {
+                ValueAccess::VarAccess(slot) => slot,
+                _ => unreachable!("`temp` always gives back a slot"),
+            },
+        }
+    }
+    /// A fresh slot holding `()`, for a value that is only known once one of several
+    /// branches has run.
+    fn result_slot(&mut self) -> u16 {
+        let slot = self.slot();
+        self.code.push(IRCode::VarCreate(slot, Calc::Clone(ValueAccess::Unit)));
+        slot
+    }
+    /// Keeps that what is in `slot` is a `type_`.
+    fn set_type(&mut self, slot: u16, type_: &Type) {
+        self.types.insert(slot, with_self(type_, self.self_type.as_deref()));
+    }
+    /// The struct or enum `type_` is, with `Self` standing for `self_type`.
+    fn named(&self, type_: &Type, self_type: Option<&str>) -> Option<String> {
+        let Type::Actual(actual) = type_ else {
+            return None;
+        };
+        let name = match actual.name.as_str() {
+            "Self" => self_type?,
+            name => name,
+        };
+        (self.ir.structs.contains_key(name) || self.ir.enums.contains_key(name)).then(|| name.to_string())
+    }
+    /// The struct or enum `expresion` is, when it can be told without working it out.
+    fn struct_of(&self, expresion: &Expresion) -> Option<String> {
+        self.named(&self.type_of(expresion)?, self.self_type.as_deref())
+    }
+    /// The type of `expresion`, when it can be told without working it out.
+    fn type_of(&self, expresion: &Expresion) -> Option<Type> {
+        match expresion {
+            Expresion::Parens(parens) => self.type_of(&parens.expr),
+            Expresion::VarAccess(name) => self.types.get(&self.lookup(name).ok()?.slot).cloned(),
+            Expresion::Constructor(Constructor { object_to_construct: ConstructorType::StructConstructor(struct_), .. }) => {
+                Some(actual(self.struct_name(&struct_.struct_name)?))
+            }
+            Expresion::Constructor(Constructor { object_to_construct: ConstructorType::EnumConstructor(enum_), .. }) => {
+                Some(actual(self.type_name(&enum_.enum_name)))
+            }
+            Expresion::Constructor(Constructor { object_to_construct: ConstructorType::ArrayConstructor(elements), .. }) => {
+                match self.type_of(elements.first()?)? {
+                    Type::Actual(element) => Some(Type::Array(element)),
+                    _ => None,
+                }
+            }
+            // an element whose type isn't known is `()`, which has no fields to find
+            Expresion::Tuple(tuple) => Some(Type::Tuple(
+                tuple.elements.iter().map(|element| self.type_of(element).unwrap_or(Type::Unit)).collect(),
+            )),
+            Expresion::FieldAcess(access) if access.field.token_type == TokenType::Int => {
+                let Type::Tuple(elements) = self.type_of(&access.expr)? else {
+                    return None;
+                };
+                elements.get(usize::from(element(&access.field).ok()?)).cloned()
+            }
+            Expresion::FieldAcess(access) => {
+                let struct_ = self.ir.structs.get(&self.struct_of(&access.expr)?)?;
+                let type_ = struct_.fields.get(&access.field.lexeme)?;
+                // a generic field could be anything
+                let named = match type_ {
+                    Type::Actual(named) | Type::Array(named) => Some(&named.name),
+                    _ => None,
+                };
+                match named {
+                    Some(name) if struct_.generics.iter().any(|generic| generic.name == *name) => None,
+                    _ => Some(type_.clone()),
+                }
+            }
+            Expresion::Index(index) => match self.type_of(&index.expr)? {
+                Type::Array(element) => Some(Type::Actual(element)),
+                _ => None,
+            },
+            Expresion::FunctionCall(call) => {
+                let (owner, tag) = match &*call.function {
+                    Expresion::VarAccess(name) if self.lookup(name).is_err() => (None, &self.ir.function.get(&name.lexeme)?.tag),
+                    Expresion::TypeAssocatedFunction(function) => {
+                        let owner = self.type_name(&function.type_);
+                        let (_, tag) = self.method(&owner, &function.func_name.lexeme)?;
+                        (Some(owner), tag)
+                    }
+                    Expresion::FieldAcess(access) if access.field.token_type == TokenType::Ident => {
+                        let owner = self.struct_of(&access.expr)?;
+                        let (_, tag) = self.method(&owner, &access.field.lexeme)?;
+                        (Some(owner), tag)
+                    }
+                    _ => return None,
+                };
+                Some(with_self(&tag.output, owner.as_deref()))
+            }
+            _ => None,
+        }
+    }
+    fn value_type(&self, value: &ValueAccess) -> Option<Type> {
+        match value {
+            ValueAccess::VarAccess(slot) => self.types.get(slot).cloned(),
+            _ => None,
+        }
+    }
+    /// The name a type is written with in an expression, `Self` being the type of the impl.
+    fn type_name(&self, type_: &Token) -> String {
+        match (type_.lexeme.as_str(), &self.self_type) {
+            ("Self", Some(self_type)) => self_type.clone(),
+            (name, _) => name.to_string(),
+        }
+    }
+    fn struct_name(&self, name: &Token) -> Option<String> {
+        let name = self.type_name(name);
+        self.ir.structs.contains_key(&name).then_some(name)
+    }
+    /// The function `name` from an impl on `type_name`, by the name it is called by.
+    fn method(&self, type_name: &str, name: &str) -> Option<(String, &'a FuncTag)> {
+        self.ir.impls.iter().find_map(|impl_| match &impl_.type_to_impl_on {
+            Type::Actual(actual) if actual.name == type_name => impl_
+                .funcs
+                .iter()
+                .find(|(func_name, _)| func_name == name)
+                .map(|(_, function)| (format!("{type_name}::{name}"), &function.tag)),
+            _ => None,
+        })
+    }
+    /// A jump whose target isn't known yet, to be filled in by `patch`.
+    fn jump_placeholder(&mut self) -> usize {
+        self.code.push(IRCode::Jmp(usize::MAX));
+        self.code.len() - 1
+    }
+    fn patch(&mut self, jumps: Vec<usize>, target: usize) {
+        for jump in jumps {
+            self.code[jump] = IRCode::Jmp(target);
+        }
+    }
+    /// Jumps away unless `value` is `expected`, returns the jump for `patch`.
+    fn exit_unless(&mut self, value: ValueAccess, expected: ValueAccess) -> usize {
+        self.code.push(IRCode::JNE(value, expected));
+        self.jump_placeholder()
+    }
+    fn block(&mut self, block: &Block) -> Result<ValueAccess, CompileError> {
+        self.scopes.push(HashMap::new());
+        let mut value = ValueAccess::Unit;
+        for (index, statment) in block.statments.iter().enumerate() {
+            let statment_value = self.statment(statment)?;
+            let last = index + 1 == block.statments.len();
+            if let Statment::ImReturn(_) = statment {
+                value = statment_value;
+            } else if last && matches!(statment, Statment::Block(_) | Statment::If(_) | Statment::Expresion(Expresion::Match(_))) {
+                // like in rust a block-like statement at the end doesn't need to be an `ImReturn`
+                value = statment_value;
+            }
+        }
+        self.scopes.pop();
+        Ok(value)
+    }
+    /// Lowers `statment` and gives back its value, `Unit` for the kinds that don't have one.
+    /// A block only takes the value of its last statement, but the value of a `let` is whatever
+    /// statement comes after the `=`.
+    fn statment(&mut self, statment: &Statment) -> Result<ValueAccess, CompileError> {
+        match statment {
+            Statment::VarCreation(var_creation) => {
+                if let (Some(type_), Statment::Expresion(value)) = (&var_creation.type_of, &*var_creation.value) {
+                    fits(&Type::from(type_), value)?;
+                }
+                let type_ = match (&var_creation.type_of, &*var_creation.value) {
+                    (Some(type_), _) => Some(with_self(&Type::from(type_), self.self_type.as_deref())),
+                    (None, Statment::Expresion(value)) => self.type_of(value),
+                    (None, _) => None,
+                };
+                let value = self.statment(&var_creation.value)?;
+                let pattern = ir_pattern::resolve(self.ir, &var_creation.pattern, None)?;
+                self.destructure(&pattern, value, var_creation.mutable, var_creation.pattern.span())?;
+                if let (Pattern::Binding(name), Some(type_)) = (&var_creation.pattern, type_) {
+                    let slot = self.lookup(name)?.slot;
+                    self.types.insert(slot, type_);
+                }
+            }
+            Statment::Expresion(expresion) => return self.expresion(expresion),
+            Statment::While(while_) => self.while_loop(while_)?,
+            Statment::For(for_) => self.for_loop(for_)?,
+            Statment::ImReturn(statment) => return self.statment(statment),
+            Statment::Block(block) => return self.block(block),
+            Statment::Continue(token) => {
+                let top = self
+                    .loops
+                    .last()
+                    .ok_or(CompileError::ContinueOutsideLoop(token.span))?
+                    .top;
+                self.code.push(IRCode::Jmp(top));
+            }
+            Statment::Break(token) => {
+                if self.loops.is_empty() {
+                    return Err(CompileError::BreakOutsideLoop(token.span));
+                }
+                let jump = self.jump_placeholder();
+                self.loops
+                    .last_mut()
+                    .expect("just checked there is a loop")
+                    .exits
+                    .push(jump);
+            }
+            Statment::FuncCreation(function) => {
+                // bound before its body is lowered so it can call itself
+                self.items.insert(function.sig.name.lexeme.clone());
+                let body = self.inner().function(function)?;
+                let (name, tag) = <(String, FuncTag)>::from(&function.sig);
+                self.functions.insert(name, Function { tag, body });
+            }
+            Statment::If(if_) => return self.if_(if_),
+            Statment::Return(return_) => {
+                if let Some(Statment::Expresion(value)) = return_.value.as_deref() {
+                    fits(&self.out, value)?;
+                }
+                let value = match &return_.value {
+                    Some(value) => self.statment(value)?,
+                    None => ValueAccess::Unit,
+                };
+                self.code.push(IRCode::Return(value));
+            }
+            Statment::Panic(_) => self.code.push(IRCode::Panic),
+        }
+        Ok(ValueAccess::Unit)
+    }
+    /// Binds the names in the irrefutable `pattern` to the parts of `value` they match.
+    fn destructure(&mut self, pattern: &IRPattern, value: ValueAccess, mutable: bool, span: Span) -> Result<(), CompileError> {
+        if !pattern.is_irrefutable() {
+            return Err(CompileError::RefutablePatternInLet(span));
+        }
+        let fails = self.matches(pattern, value, mutable)?;
+        // only an alternative after one that matches everything can fail, and it never runs
+        self.patch(fails, self.code.len());
+        Ok(())
+    }
+    /// Tests `value` against `pattern` and binds the names in it as it goes, gives back the
+    /// jumps taken when it doesn't match for `patch`. The fields of a struct or varient are
+    /// read out by their index in name order.
+    fn matches(&mut self, pattern: &IRPattern, value: ValueAccess, mutable: bool) -> Result<Vec<usize>, CompileError> {
+        Ok(match pattern {
+            IRPattern::Wildcard => vec![],
+            IRPattern::Binding(name, pattern) => {
+                let type_ = self.value_type(&value);
+                let or_slot = self.or_slots.last().and_then(|slots| slots.get(name)).copied();
+                let slot = match or_slot {
+                    Some(slot) => {
+                        self.code.push(IRCode::VarAssign(slot, Calc::Clone(value)));
+                        slot
+                    }
+                    None => {
+                        let slot = self.bind_name(name.clone(), mutable, false);
+                        self.code.push(IRCode::VarCreate(slot, Calc::Clone(value)));
+                        slot
+                    }
+                };
+                if let Some(type_) = type_ {
+                    self.types.insert(slot, type_);
+                }
+                self.matches(pattern, ValueAccess::VarAccess(slot), mutable)?
+            }
+            IRPattern::Literal(literal) => vec![self.exit_unless(value, literal.clone())],
+            IRPattern::Struct(struct_, fields) => {
+                let types = ir_pattern::fields(self.ir, struct_, None).unwrap_or_default();
+                self.match_fields(fields, &value, 0, &types, mutable)?
+            }
+            IRPattern::Tuple(fields) => self.match_fields(fields, &value, 0, &[], mutable)?,
+            IRPattern::Varient(enum_, varient, fields) => {
+                let mut varients = self.ir.enums[enum_].varients.keys().collect::<Vec<_>>();
+                varients.sort();
+                let index = varients.iter().position(|name| *name == varient).expect("patterns name real varients");
+                let tag = self.temp(Calc::FieldAccess(value.clone(), 0));
+                let mut fails = vec![self.exit_unless(tag, ValueAccess::IntConst(index as u64, None))];
+                let types = ir_pattern::fields(self.ir, enum_, Some(varient)).unwrap_or_default();
+                fails.extend(self.match_fields(fields, &value, 1, &types, mutable)?);
+                fails
+            }
+            IRPattern::Or(alternatives) => {
+                let mut slots = self.or_slots.last().cloned().unwrap_or_default();
+                for name in pattern.bindings() {
+                    if let Entry::Vacant(entry) = slots.entry(name) {
+                        let slot = self.bind_name(entry.key().clone(), mutable, false);
+                        self.code.push(IRCode::VarCreate(slot, Calc::Clone(ValueAccess::Unit)));
+                        entry.insert(slot);
+                    }
+                }
+                self.or_slots.push(slots);
+                let mut matched = vec![];
+                let mut fails = vec![];
+                for (index, alternative) in alternatives.iter().enumerate() {
+                    // the last alternative failing fails the whole pattern
+                    self.patch(fails, self.code.len());
+                    fails = self.matches(alternative, value.clone(), mutable)?;
+                    if index + 1 < alternatives.len() {
+                        matched.push(self.jump_placeholder());
+                    }
+                }
+                self.or_slots.pop();
+                self.patch(matched, self.code.len());
+                fails
+            }
+        })
+    }
+    /// Tests the fields of `value` from `first` on against `fields`, `types` being what
+    /// `ir_pattern::fields` gives for them when they have names.
+    fn match_fields(
+        &mut self,
+        fields: &[IRPattern],
+        value: &ValueAccess,
+        first: u16,
+        types: &[(String, Option<String>)],
+        mutable: bool,
+    ) -> Result<Vec<usize>, CompileError> {
+        let mut fails = vec![];
+        for (index, field) in (first..).zip(fields) {
+            if *field != IRPattern::Wildcard {
+                let field_value = self.temp(Calc::FieldAccess(value.clone(), index));
+                if let (ValueAccess::VarAccess(slot), Some((_, Some(type_name)))) = (&field_value, types.get(usize::from(index - first))) {
+                    self.types.insert(*slot, actual(type_name.clone()));
+                }
+                fails.extend(self.matches(field, field_value, mutable)?);
+            }
+        }
+        Ok(fails)
+    }
+    /// Each condition is tried in turn and the statment of the first to hold gives the value.
+    fn if_(&mut self, if_: &If) -> Result<ValueAccess, CompileError> {
+        let result = self.result_slot();
+        let mut ends = vec![];
+        for (condition, statment) in &if_.conditionals_and_statments {
+            self.scopes.push(HashMap::new());
+            let fails = match condition {
+                IfType::Boolean(condition) => {
+                    let condition = self.expresion(condition)?;
+                    vec![self.exit_unless(condition, ValueAccess::True)]
+                }
+                IfType::IfLet { pattern, expresion } => {
+                    let value = self.expresion(expresion)?;
+                    let pattern = ir_pattern::resolve(self.ir, pattern, None)?;
+                    self.matches(&pattern, value, false)?
+                }
+            };
+            let value = self.statment(statment)?;
+            self.code.push(IRCode::VarAssign(result, Calc::Clone(value)));
+            ends.push(self.jump_placeholder());
+            self.scopes.pop();
+            self.patch(fails, self.code.len());
+        }
+        if let Some(else_statment) = &if_.else_statment {
+            let value = self.statment(else_statment)?;
+            self.code.push(IRCode::VarAssign(result, Calc::Clone(value)));
+        }
+        self.patch(ends, self.code.len());
+        Ok(ValueAccess::VarAccess(result))
+    }
+    /// The arms are tried in order like the branches of an `if`, the match check already made
+    /// sure one of them always matches.
+    fn match_(&mut self, match_: &Match) -> Result<ValueAccess, CompileError> {
+        let scrutinee = self.expresion(&match_.scrutinee)?;
+        let result = self.result_slot();
+        let mut ends = vec![];
+        for arm in &match_.arms {
+            self.scopes.push(HashMap::new());
+            let pattern = ir_pattern::resolve(self.ir, &arm.pattern, None)?;
+            let mut fails = self.matches(&pattern, scrutinee.clone(), false)?;
+            if let Some(guard) = &arm.guard {
+                let guard = self.expresion(guard)?;
+                fails.push(self.exit_unless(guard, ValueAccess::True));
+            }
+            let value = self.expresion(&arm.value)?;
+            self.code.push(IRCode::VarAssign(result, Calc::Clone(value)));
+            ends.push(self.jump_placeholder());
+            self.scopes.pop();
+            self.patch(fails, self.code.len());
+        }
+        self.patch(ends, self.code.len());
+        Ok(ValueAccess::VarAccess(result))
+    }
+    /// Lowers the body of a loop that starts at `top` and leaves through `exits`.
+    fn loop_body(&mut self, top: usize, exits: Vec<usize>, body: &Statment) -> Result<(), CompileError> {
+        self.loops.push(Loop { top, exits });
+        self.statment(body)?;
+        self.code.push(IRCode::Jmp(top));
+        let loop_ = self.loops.pop().expect("the loop was just pushed");
+        self.patch(loop_.exits, self.code.len());
+        Ok(())
+    }
+    fn while_loop(&mut self, while_: &While) -> Result<(), CompileError> {
+        let top = self.code.len();
+        let condition = self.expresion(&while_.condition)?;
+        let exit = self.exit_unless(condition, ValueAccess::True);
+        self.loop_body(top, vec![exit], &while_.statment)
+    }
+    fn for_loop(&mut self, for_: &For) -> Result<(), CompileError> {
+        let mut iterable = &for_.iterable;
+        while let Expresion::Parens(parens) = iterable {
+            iterable = &parens.expr;
+        }
+        self.scopes.push(HashMap::new());
+        match iterable {
+            Expresion::Binary(Binary { lhs, rhs, binary_op: binary_op @ (BinaryOp::Range | BinaryOp::RangeInclusive), .. }) => {
+                self.for_range(for_, lhs, rhs, *binary_op == BinaryOp::RangeInclusive)?
+            }
+            array => self.for_array(for_, array)?,
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+    /// `for i in start..end`, the end is worked out once up front. An inclusive range keeps
+    /// a flag for having reached the end instead of comparing past it, so `..=` up to the
+    /// largest value of a type doesn't overflow into an endless loop.
+    fn for_range(&mut self, for_: &For, start: &Expresion, end: &Expresion, inclusive: bool) -> Result<(), CompileError> {
+        let start = self.expresion(start)?;
+        let end = self.expresion(end)?;
+        let current = self.temp(Calc::Clone(start));
+        let end = self.temp(Calc::Clone(end));
+        let done = inclusive.then(|| self.slot());
+        if let Some(done) = done {
+            self.code.push(IRCode::VarCreate(done, Calc::Clone(ValueAccess::False)));
+        }
+
+        let top = self.code.len();
+        let mut exits = vec![];
+        if let Some(done) = done {
+            exits.push(self.exit_unless(ValueAccess::VarAccess(done), ValueAccess::False));
+        }
+        let in_range = self.temp(if inclusive {
+            Calc::LE(current.clone(), end.clone())
+        } else {
+            Calc::LT(current.clone(), end.clone())
+        });
+        exits.push(self.exit_unless(in_range, ValueAccess::True));
+        let variable = self.bind(&for_.variable, false);
+        self.code.push(IRCode::VarCreate(variable, Calc::Clone(current.clone())));
+        if let Some(done) = done {
+            self.code.push(IRCode::VarAssign(done, Calc::EQ(current.clone(), end)));
+        }
+        self.step(&current);
+        self.loop_body(top, exits, &for_.statment)
+    }
+    /// `for x in array`, each element is read out by index.
+    fn for_array(&mut self, for_: &For, array: &Expresion) -> Result<(), CompileError> {
+        let array = self.expresion(array)?;
+        let index = self.temp(Calc::Clone(ValueAccess::IntConst(0, None)));
+        let len = self.temp(Calc::ArrayLen(array.clone()));
+
+        let top = self.code.len();
+        let in_range = self.temp(Calc::LT(index.clone(), len));
+        let exit = self.exit_unless(in_range, ValueAccess::True);
+        let variable = self.bind(&for_.variable, false);
+        self.code.push(IRCode::VarCreate(variable, Calc::ArrayAcess(array, index.clone())));
+        self.step(&index);
+        self.loop_body(top, vec![exit], &for_.statment)
+    }
+    /// Adds one to the counter in `counter`, before the body so `continue` can't skip it.
+    fn step(&mut self, counter: &ValueAccess) {
+        let ValueAccess::VarAccess(slot) = counter else {
+            unreachable!("loop counters are always put in a slot")
+        };
+        self.code.push(IRCode::VarAssign(
+            *slot,
+            Calc::Add(counter.clone(), ValueAccess::IntConst(1, None)),
+        ));
+    }
+    fn expresion(&mut self, expresion: &Expresion) -> Result<ValueAccess, CompileError> {
+        Ok(match expresion {
+            Expresion::IntLitteral(int) => ValueAccess::IntConst(int.value, int.suffix),
+            Expresion::FloatLitteral(float) => ValueAccess::FloatConst(float.value, float.suffix),
+            Expresion::StringLitteral(string) => ValueAccess::StringConst(string.value.as_str().into()),
+            Expresion::CharLitteral(char) => ValueAccess::CharConst(char.value),
+            Expresion::True(_) => ValueAccess::True,
+            Expresion::False(_) => ValueAccess::False,
+            Expresion::VarAccess(name) => {
+                let local = match self.lookup(name) {
+                    Ok(local) => local,
+                    // a local of the same name hides a constant, static or function
+                    Err(err) => {
+                        return match self.ir.consts.get(&name.lexeme) {
+                            Some(const_) => Ok(const_.value.clone()),
+                            None if self.ir.statics.contains_key(&name.lexeme) => Ok(ValueAccess::Static(name.lexeme.clone())),
+                            None if self.items.contains(&name.lexeme) || self.ir.function.contains_key(&name.lexeme) => {
+                                Ok(ValueAccess::Func(name.lexeme.clone()))
+                            }
+                            None => Err(err),
+                        }
+                    }
+                };
+                if local.by_ref {
+                    self.temp(Calc::Deref(ValueAccess::VarAccess(local.slot)))
+                } else {
+                    ValueAccess::VarAccess(local.slot)
+                }
+            }
+            Expresion::Closure(closure) => self.closure(closure)?,
+            Expresion::Parens(parens) => self.expresion(&parens.expr)?,
+            Expresion::Tuple(tuple) => self.tuple(tuple)?,
+            Expresion::FieldAcess(access) => {
+                let index = self.field(&access.expr, &access.field)?;
+                let value = self.expresion(&access.expr)?;
+                self.temp(Calc::FieldAccess(value, index))
+            }
+            Expresion::Block(block) => self.block(block)?,
+            Expresion::Index(index) => {
+                let array = self.expresion(&index.expr)?;
+                let index = self.expresion(&index.index)?;
+                self.temp(Calc::ArrayAcess(array, index))
+            }
+            Expresion::Unary(unary) => {
+                let value = self.expresion(&unary.expr)?;
+                self.temp(match unary.unary_op {
+                    UnaryOp::Negate => Calc::Negate(value),
+                    UnaryOp::Not => Calc::Not(value),
+                })
+            }
+            Expresion::Binary(binary) => self.binary(binary)?,
+            Expresion::TypeAssocatedFunction(function) => {
+                let type_name = self.type_name(&function.type_);
+                let name = &function.func_name.lexeme;
+                match (self.ir.consts.get(&format!("{type_name}::{name}")), self.method(&type_name, name)) {
+                    (Some(const_), _) => const_.value.clone(),
+                    (None, Some((method, _))) => ValueAccess::Func(method),
+                    (None, None) => {
+                        return Err(CompileError::NoFunctionWithThatName(type_name, name.clone(), function.func_name.span))
+                    }
+                }
+            }
+            Expresion::FunctionCall(call) => self.call(call)?,
+            Expresion::Constructor(constructor) => self.constructor(constructor)?,
+            // the IR isn't typed, the value is the same whatever traits it is seen through
+            Expresion::Cast(cast) => self.expresion(&cast.expr)?,
+            Expresion::Match(match_) => self.match_(match_)?,
+        })
+    }
+    /// Which field of `owner` `field` is, by its index in name order for a struct.
+    fn field(&self, owner: &Expresion, field: &Token) -> Result<u16, CompileError> {
+        if field.token_type == TokenType::Int {
+            return element(field);
+        }
+        let type_name = self.struct_of(owner).ok_or(CompileError::TypeNotKnown(owner.span()))?;
+        ir_pattern::fields(self.ir, &type_name, None)
+            .and_then(|fields| fields.iter().position(|(name, _)| *name == field.lexeme))
+            .map(|index| u16::try_from(index).expect("a struct has fewer than u16::MAX fields"))
+            .ok_or_else(|| CompileError::NoFieldWithThatName(type_name, field.lexeme.clone(), field.span))
+    }
+    /// The arguments go in slots of their own, a method is given what it was called on first.
+    fn call(&mut self, call: &FuncCall) -> Result<ValueAccess, CompileError> {
+        let (function, mut arguments, parameters) = match &*call.function {
+            Expresion::FieldAcess(access) if access.field.token_type == TokenType::Ident => {
+                let type_name = self.struct_of(&access.expr).ok_or(CompileError::TypeNotKnown(access.expr.span()))?;
+                let (name, tag) = self.method(&type_name, &access.field.lexeme).ok_or_else(|| {
+                    CompileError::NoFunctionWithThatName(type_name.clone(), access.field.lexeme.clone(), access.field.span)
+                })?;
+                let this = self.expresion(&access.expr)?;
+                (ValueAccess::Func(name), vec![self.in_slot(this)], tag.inputs.get(1..).unwrap_or_default())
+            }
+            function => (self.expresion(function)?, vec![], self.parameters(function)),
+        };
+        for (index, argument) in call.arguments.iter().enumerate() {
+            if let Some((_, expected)) = parameters.get(index) {
+                fits(expected, argument)?;
+            }
+            let argument = self.expresion(argument)?;
+            arguments.push(self.in_slot(argument));
+        }
+        let result = self.slot();
+        self.code.push(IRCode::Call(result, function, arguments));
+        Ok(ValueAccess::VarAccess(result))
+    }
+    /// The parameters of the `fn` or associated function `function` names, none when it is
+    /// something else.
+    fn parameters(&self, function: &Expresion) -> &'a [(String, Type)] {
+        let tag = match function {
+            Expresion::VarAccess(name) if self.lookup(name).is_err() => self.ir.function.get(&name.lexeme).map(|function| &function.tag),
+            Expresion::TypeAssocatedFunction(function) => {
+                self.method(&self.type_name(&function.type_), &function.func_name.lexeme).map(|(_, tag)| tag)
+            }
+            _ => None,
+        };
+        tag.map_or(&[], |tag| &tag.inputs)
+    }
+    /// A struct or varient is allocated then has its fields filled in, in the order they were
+    /// written so they are worked out in that order.
+    fn constructor(&mut self, constructor: &Constructor) -> Result<ValueAccess, CompileError> {
+        let (type_name, varient, fields, span) = match &constructor.object_to_construct {
+            ConstructorType::ArrayConstructor(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| self.expresion(element))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let arena = match &constructor.areana {
+                    Some(arena) => Some(self.expresion(arena)?),
+                    None => None,
+                };
+                return Ok(self.temp(Calc::Array(elements, arena)));
+            }
+            ConstructorType::StructConstructor(struct_) => {
+                (self.type_name(&struct_.struct_name), None, &struct_.fields, struct_.struct_name.span)
+            }
+            ConstructorType::EnumConstructor(enum_) => {
+                (self.type_name(&enum_.enum_name), Some(&enum_.varient_name), &enum_.fields, enum_.enum_name.span)
+            }
+        };
+        let Some(declared) = ir_pattern::fields(self.ir, &type_name, varient.map(|varient| varient.lexeme.as_str())) else {
+            return Err(match varient {
+                Some(varient) if self.ir.enums.contains_key(&type_name) => {
+                    CompileError::NoVarientWithThatName(type_name, varient.lexeme.clone(), varient.span)
+                }
+                _ => CompileError::NoTypeWithThatNameFound(Type::Actual(ActualType {
+                    name: type_name,
+                    types_in_generics: vec![],
+                    span,
+                })),
+            });
+        };
+        let full_name = match varient {
+            Some(varient) => format!("{type_name}.{}", varient.lexeme),
+            None => type_name.clone(),
+        };
+        // a varient's fields come after which varient it is
+        let first = u16::from(varient.is_some());
+        let len = u16::try_from(declared.len()).expect("a struct has fewer than u16::MAX fields") + first;
+        let value = self.temp(Calc::Alloc(len));
+        let slot = self.in_slot(value.clone());
+        if let Some(varient) = varient {
+            let mut varients = self.ir.enums[&type_name].varients.keys().collect::<Vec<_>>();
+            varients.sort();
+            let index = varients.iter().position(|name| **name == varient.lexeme).expect("the varient has fields");
+            self.code.push(IRCode::FieldAssign(slot, 0, Calc::Clone(ValueAccess::IntConst(index as u64, None))));
+        }
+        let mut missing = declared.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+        for field in fields {
+            let index = declared
+                .iter()
+                .position(|(name, _)| *name == field.name.lexeme)
+                .ok_or_else(|| CompileError::NoFieldWithThatName(full_name.clone(), field.name.lexeme.clone(), field.name.span))?;
+            missing.retain(|name| *name != field.name.lexeme);
+            let field_value = self.expresion(&field.value)?;
+            let index = u16::try_from(index).expect("a struct has fewer than u16::MAX fields") + first;
+            self.code.push(IRCode::FieldAssign(slot, index, Calc::Clone(field_value)));
+        }
+        if !missing.is_empty() {
+            return Err(CompileError::MissingFields(full_name, missing, span));
+        }
+        Ok(value)
+    }
+    /// Lowers the body of `closure` into a function of its own and makes the closure value
+    /// out of it and what it captures.
+    fn closure(&mut self, closure: &Closure) -> Result<ValueAccess, CompileError> {
+        let mut lowerer = self.inner();
+        lowerer.out = closure.out.as_ref().map_or(Type::Unit, Type::from);
+        fits(&lowerer.out, &closure.body)?;
+        let mut captured = vec![];
+        for name in ir_closure::captures(closure) {
+            // anything that isn't a local here is left for the body to find or report
+            let Ok(local) = self.lookup(&name) else {
+                continue;
+            };
+            captured.push(if local.mutable && !local.by_ref {
+                self.temp(Calc::Ref(local.slot))
+            } else {
+                // a reference captured from further out is passed along as it is
+                ValueAccess::VarAccess(local.slot)
+            });
+            lowerer.bind_name(name.lexeme.clone(), local.mutable, local.mutable);
+        }
+        for (name, _) in &closure.parameters {
+            lowerer.bind(name, false);
+        }
+        let value = lowerer.expresion(&closure.body)?;
+        lowerer.code.push(IRCode::Return(value));
+        let name = format!("closure#{}", self.functions.len());
+        self.functions.insert(
+            name.clone(),
+            Function {
+                tag: FuncTag {
+                    generics: vec![],
+                    inputs: closure
+                        .parameters
+                        .iter()
+                        .map(|(name, type_)| (name.lexeme.clone(), Type::from(type_)))
+                        .collect(),
+                    output: closure.out.as_ref().map_or(Type::Unit, Type::from),
+                },
+                body: IRChunk {
+                    functions: lowerer.functions,
+                    code: lowerer.code,
+                },
+            },
+        );
+        Ok(self.temp(Calc::Closure(name, captured)))
+    }
+    /// Allocates room for every element first, then fills them in from left to right.
+    fn tuple(&mut self, tuple: &Tuple) -> Result<ValueAccess, CompileError> {
+        let elements = tuple
+            .elements
+            .iter()
+            .map(|element| self.expresion(element))
+            .collect::<Result<Vec<_>, _>>()?;
+        let len = u16::try_from(elements.len()).expect("a tuple has fewer than u16::MAX elements");
+        let tuple = self.temp(Calc::Alloc(len));
+        let ValueAccess::VarAccess(slot) = tuple else {
+            unreachable!("temporaries are always put in a slot")
+        };
+        for (index, element) in (0..).zip(elements) {
+            self.code.push(IRCode::FieldAssign(slot, index, Calc::Clone(element)));
+        }
+        Ok(tuple)
+    }
+    fn binary(&mut self, binary: &Binary) -> Result<ValueAccess, CompileError> {
+        match binary.binary_op {
+            BinaryOp::Assign
+            | BinaryOp::AddAssign
+            | BinaryOp::SubtractAssign
+            | BinaryOp::MultAssign
+            | BinaryOp::DivAssign
+            | BinaryOp::ModAssign
+            | BinaryOp::BitwiseAndAssign
+            | BinaryOp::BitwiseOrAssign
+            | BinaryOp::SHLAssign
+            | BinaryOp::SHRAssign => return self.assign(binary),
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr => {
+                // the right side is only worked out while the left one leaves the answer open,
+                // `true` for an `and` and `false` for an `or`
+                let open = match binary.binary_op {
+                    BinaryOp::LogicalAnd => ValueAccess::True,
+                    _ => ValueAccess::False,
+                };
+                let lhs = self.expresion(&binary.lhs)?;
+                let result = self.temp(Calc::Clone(lhs));
+                let decided = self.exit_unless(result.clone(), open);
+                let rhs = self.expresion(&binary.rhs)?;
+                let ValueAccess::VarAccess(slot) = result else {
+                    unreachable!("`temp` always gives back a slot")
+                };
+                self.code.push(IRCode::VarAssign(slot, Calc::Clone(rhs)));
+                self.patch(vec![decided], self.code.len());
+                return Ok(result);
+            }
+            BinaryOp::Range | BinaryOp::RangeInclusive => {
+                return Err(CompileError::RangeOutsideForLoop(binary.span))
+            }
+            _ => {}
+        }
+        let lhs = self.expresion(&binary.lhs)?;
+        let rhs = self.expresion(&binary.rhs)?;
+        Ok(self.temp(calc(&binary.binary_op, lhs, rhs)))
+    }
+    /// `place = value` and `place op= value`. Whatever the place is worked out from is only
+    /// worked out once, before the value.
+    fn assign(&mut self, binary: &Binary) -> Result<ValueAccess, CompileError> {
+        let place = self.place(&binary.lhs)?;
+        let value = self.expresion(&binary.rhs)?;
+        let calc = match binary.binary_op.compound() {
+            None => Calc::Clone(value),
+            Some(op) => {
+                let current = match place {
+                    Place::Local(local) if local.by_ref => self.temp(Calc::Deref(ValueAccess::VarAccess(local.slot))),
+                    Place::Local(local) => ValueAccess::VarAccess(local.slot),
+                    Place::Static(ref name) => ValueAccess::Static(name.clone()),
+                    Place::Field(slot, index) => self.temp(Calc::FieldAccess(ValueAccess::VarAccess(slot), index)),
+                    Place::Index(slot, ref index) => self.temp(Calc::ArrayAcess(ValueAccess::VarAccess(slot), index.clone())),
+                };
+                calc(&op, current, value)
+            }
+        };
+        self.code.push(match place {
+            Place::Local(local) if local.by_ref => IRCode::DerefAssign(local.slot, calc),
+            Place::Local(local) => IRCode::VarAssign(local.slot, calc),
+            Place::Static(name) => IRCode::StaticAssign(name, calc),
+            Place::Field(slot, index) => IRCode::FieldAssign(slot, index, calc),
+            Place::Index(slot, index) => IRCode::IndexAssign(slot, index, calc),
+        });
+        Ok(ValueAccess::Unit)
+    }
+    /// Where the left side of an assignment stores to, which has to be a `static mut`, a
+    /// `mut` local or a field or element of one.
+    fn place(&mut self, expresion: &Expresion) -> Result<Place, CompileError> {
+        let (owner, place) = match expresion {
+            Expresion::Parens(parens) => return self.place(&parens.expr),
+            Expresion::VarAccess(name) => {
+                let local = match (self.lookup(name), self.ir.statics.get(&name.lexeme)) {
+                    (Ok(local), _) => local,
+                    (Err(_), Some(static_)) if static_.mutable => return Ok(Place::Static(name.lexeme.clone())),
+                    (Err(_), Some(_)) => return Err(CompileError::AssignToImmutable(name.lexeme.clone(), name.span)),
+                    (Err(err), None) => return Err(err),
+                };
+                if !local.mutable {
+                    return Err(CompileError::AssignToImmutable(name.lexeme.clone(), name.span));
+                }
+                return Ok(Place::Local(local));
+            }
+            Expresion::FieldAcess(access) => (&access.expr, Ok(&access.field)),
+            Expresion::Index(index) => (&index.expr, Err(&index.index)),
+            _ => return Err(CompileError::InvalidAssignTarget(expresion.span())),
+        };
+        let mut root = &**owner;
+        loop {
+            root = match root {
+                Expresion::Parens(parens) => &parens.expr,
+                Expresion::FieldAcess(access) => &access.expr,
+                Expresion::Index(index) => &index.expr,
+                _ => break,
+            }
+        }
+        let Expresion::VarAccess(name) = root else {
+            return Err(CompileError::InvalidAssignTarget(expresion.span()));
+        };
+        let local = self.lookup(name)?;
+        if !local.mutable {
+            return Err(CompileError::AssignToImmutable(name.lexeme.clone(), name.span));
+        }
+        let value = self.expresion(owner)?;
+        let slot = self.in_slot(value);
+        Ok(match place {
+            Ok(field) => Place::Field(slot, self.field(owner, field)?),
+            Err(index) => Place::Index(slot, self.expresion(index)?),
+        })
+    }
+}
+
+/// Which element of a tuple `.0` names.
+/// Checks `value` takes and gives back what `expected` says when it is a closure given where a
+/// function pointer is wanted.
+fn fits(expected: &Type, value: &Expresion) -> Result<(), CompileError> {
+    match (expected, value) {
+        (Type::FP(expected), Expresion::Closure(closure)) => ir_closure::check_signature(expected, closure),
+        _ => Ok(()),
+    }
+}
+
+/// The type of the struct or enum `name`.
+fn actual(name: String) -> Type {
+    Type::Actual(ActualType { name, types_in_generics: vec![], span: Span::default() })
+}
+
+/// `type_` with `Self` swapped for `self_type`, as far down as arrays and tuples go.
+fn with_self(type_: &Type, self_type: Option<&str>) -> Type {
+    match (type_, self_type) {
+        (Type::Actual(named), Some(self_type)) if named.name == "Self" => {
+            Type::Actual(ActualType { name: self_type.to_string(), ..named.clone() })
+        }
+        (Type::Array(named), Some(self_type)) if named.name == "Self" => {
+            Type::Array(ActualType { name: self_type.to_string(), ..named.clone() })
+        }
+        (Type::Tuple(elements), _) => Type::Tuple(elements.iter().map(|element| with_self(element, self_type)).collect()),
+        (type_, _) => type_.clone(),
+    }
+}
+
+fn element(field: &Token) -> Result<u16, CompileError> {
+    field
+        .lexeme
+        .parse()
+        .map_err(|_| CompileError::NoFieldWithThatName("tuple".to_string(), field.lexeme.clone(), field.span))
+}
+
+/// The calculation a plain binary operator does.
+fn calc(op: &BinaryOp, lhs: ValueAccess, rhs: ValueAccess) -> Calc {
+    match op {
+        BinaryOp::Add => Calc::Add(lhs, rhs),
+        BinaryOp::Subtract => Calc::Subtract(lhs, rhs),
+        BinaryOp::Mult => Calc::Multiply(lhs, rhs),
+        BinaryOp::Div => Calc::Div(lhs, rhs),
+        BinaryOp::Mod => Calc::Mod(lhs, rhs),
+        BinaryOp::Lessthan => Calc::LT(lhs, rhs),
+        BinaryOp::Greaterthan => Calc::GT(lhs, rhs),
+        BinaryOp::GE => Calc::GE(lhs, rhs),
+        BinaryOp::LE => Calc::LE(lhs, rhs),
+        BinaryOp::Equal => Calc::EQ(lhs, rhs),
+        BinaryOp::NE => Calc::NE(lhs, rhs),
+        BinaryOp::BitwiseOr => Calc::BITOr(lhs, rhs),
+        BinaryOp::BitwiseAnd => Calc::BITAnd(lhs, rhs),
+        BinaryOp::SHL => Calc::SHL(lhs, rhs),
+        BinaryOp::SHR => Calc::SHR(lhs, rhs),
+        BinaryOp::Assign
+        | BinaryOp::AddAssign
+        | BinaryOp::SubtractAssign
+        | BinaryOp::MultAssign
+        | BinaryOp::DivAssign
+        | BinaryOp::ModAssign
+        | BinaryOp::BitwiseAndAssign
+        | BinaryOp::BitwiseOrAssign
+        | BinaryOp::SHLAssign
+        | BinaryOp::SHRAssign
+        | BinaryOp::LogicalAnd
+        | BinaryOp::LogicalOr
+        | BinaryOp::Range
+        | BinaryOp::RangeInclusive => unreachable!("handled by `FunctionLowerer::binary`"),
+    }
+}
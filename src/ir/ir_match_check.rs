@@ -0,0 +1,264 @@
+//! Checks every `match` against the structs and enums its patterns name: every value the
+//! scrutinee can hold has to be handled by some arm, and no arm can be hidden behind the
+//! ones above it.
+//!
+//! Both come down to the usefulness check from Maranget's "Warnings for pattern matching".
+//! A pattern is useful against a list of rows if some value matches it and none of the rows.
+//! A `match` is exhaustive when a `_` after its last arm would not be useful, and an arm is
+//! unreachable when it is not useful against the arms before it. An arm with a guard can
+//! fail to match, so it never counts towards either for the arms after it.
+//!
+//! The pattern of a `let` gets the same check as a `match` with a single arm, it has to
+//! match whatever value it is given.
+
+use crate::ast::{
+    decl::{FunctionDecl, Type as ASTType},
+    expresions::{Expresion, Match},
+    parser::Spanned,
+    statments::{IfType, Statment},
+    visit::{walk_expresion, walk_function, walk_statment, Visit},
+};
+
+use super::{
+    ir_chunk::ValueAccess,
+    ir_pattern::{self, IRPattern},
+    ir_type_data::{CompileError, IR},
+};
+
+/// Checks every `match` in the body of `function`, and in any functions nested in it.
+pub fn check_function(ir: &IR, function: &FunctionDecl) -> Result<(), CompileError> {
+    walk_function(&mut MatchChecker { ir }, function)
+}
+
+/// A pattern with everything but its shape taken out, fields are in name order.
+#[derive(Debug, Clone)]
+enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+    Or(Vec<Pat>),
+}
+
+impl From<&IRPattern> for Pat {
+    fn from(pattern: &IRPattern) -> Self {
+        let fields = |fields: &[IRPattern]| fields.iter().map(Pat::from).collect();
+        match pattern {
+            IRPattern::Wildcard => Pat::Wild,
+            IRPattern::Binding(_, pattern) => Pat::from(&**pattern),
+            IRPattern::Literal(ValueAccess::True) => Pat::Ctor(Ctor::Bool(true), vec![]),
+            IRPattern::Literal(ValueAccess::False) => Pat::Ctor(Ctor::Bool(false), vec![]),
+            IRPattern::Literal(value) => Pat::Ctor(Ctor::Literal(value.clone()), vec![]),
+            IRPattern::Struct(type_name, patterns) => Pat::Ctor(
+                Ctor::Named { type_name: type_name.clone(), varient: None },
+                fields(patterns),
+            ),
+            IRPattern::Varient(type_name, varient, patterns) => Pat::Ctor(
+                Ctor::Named { type_name: type_name.clone(), varient: Some(varient.clone()) },
+                fields(patterns),
+            ),
+            IRPattern::Tuple(elements) => Pat::Ctor(Ctor::Tuple(elements.len()), fields(elements)),
+            IRPattern::Or(alternatives) => Pat::Or(fields(alternatives)),
+        }
+    }
+}
+
+/// One way to build a value: a struct, one varient of an enum, a tuple of so many elements,
+/// `true` or `false`, or any other literal, which there are always more of.
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    Named { type_name: String, varient: Option<String> },
+    Tuple(usize),
+    Bool(bool),
+    Literal(ValueAccess),
+}
+
+impl Ctor {
+    fn name(&self) -> String {
+        match self {
+            Ctor::Named { type_name, varient: Some(varient) } => format!("{type_name}.{varient}"),
+            Ctor::Named { type_name, varient: None } => type_name.clone(),
+            Ctor::Tuple(len) => ir_pattern::tuple_name(*len),
+            Ctor::Bool(bool) => bool.to_string(),
+            Ctor::Literal(value) => format!("{value:?}"),
+        }
+    }
+}
+
+struct MatchChecker<'a> {
+    ir: &'a IR,
+}
+
+impl Visit for MatchChecker<'_> {
+    type Error = CompileError;
+    fn visit_statment(&mut self, statment: &Statment) -> Result<(), CompileError> {
+        match statment {
+            Statment::VarCreation(var_creation) => {
+                let expected = match &var_creation.type_of {
+                    Some(ASTType::PhysicalType(solid))
+                        if self.ir.structs.contains_key(&solid.name.lexeme) || self.ir.enums.contains_key(&solid.name.lexeme) =>
+                    {
+                        Some(solid.name.lexeme.as_str())
+                    }
+                    _ => None,
+                };
+                let pattern = ir_pattern::resolve(self.ir, &var_creation.pattern, expected)?;
+                if self.useful(&expand(&[vec![Pat::from(&pattern)]]), &[Pat::Wild]) {
+                    return Err(CompileError::RefutablePatternInLet(var_creation.pattern.span()));
+                }
+            }
+            Statment::If(if_) => {
+                for (condition, _) in &if_.conditionals_and_statments {
+                    if let IfType::IfLet { pattern, .. } = condition {
+                        ir_pattern::resolve(self.ir, pattern, None)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        walk_statment(self, statment)
+    }
+    fn visit_expresion(&mut self, expresion: &Expresion) -> Result<(), CompileError> {
+        if let Expresion::Match(match_) = expresion {
+            self.check_match(match_)?;
+        }
+        walk_expresion(self, expresion)
+    }
+}
+
+impl MatchChecker<'_> {
+    fn check_match(&self, match_: &Match) -> Result<(), CompileError> {
+        // the first arm that names a type decides what every arm has to match
+        let mut scrutinee_type = None;
+        for arm in &match_.arms {
+            scrutinee_type = ir_pattern::pattern_type(&arm.pattern)?;
+            if scrutinee_type.is_some() {
+                break;
+            }
+        }
+        let mut rows: Vec<Vec<Pat>> = vec![];
+        for arm in &match_.arms {
+            let pat = Pat::from(&ir_pattern::resolve(self.ir, &arm.pattern, scrutinee_type.as_deref())?);
+            if !self.useful(&rows, std::slice::from_ref(&pat)) {
+                return Err(CompileError::UnreachableMatchArm(arm.pattern.span()));
+            }
+            if arm.guard.is_none() {
+                rows.extend(expand(&[vec![pat]]));
+            }
+        }
+        if self.useful(&rows, &[Pat::Wild]) {
+            let missing = match self.column_ctors(&rows) {
+                Some(ctors) => ctors
+                    .into_iter()
+                    .filter(|(ctor, arity)| {
+                        self.useful(&specialize(&rows, ctor, *arity), &vec![Pat::Wild; *arity])
+                    })
+                    .map(|(ctor, _)| ctor.name())
+                    .collect(),
+                None => vec!["_".to_string()],
+            };
+            return Err(CompileError::NonExhaustiveMatch(missing, match_.span));
+        }
+        Ok(())
+    }
+    /// Every constructor of the type the first column of `rows` is matching on, with how
+    /// many fields each has, or `None` when no row names the type.
+    fn column_ctors(&self, rows: &[Vec<Pat>]) -> Option<Vec<(Ctor, usize)>> {
+        let type_name = match rows.iter().find_map(|row| match row.first() {
+            Some(Pat::Ctor(ctor, _)) => Some(ctor),
+            _ => None,
+        })? {
+            Ctor::Named { type_name, .. } => type_name.clone(),
+            Ctor::Tuple(len) => return Some(vec![(Ctor::Tuple(*len), *len)]),
+            Ctor::Bool(_) => return Some(vec![(Ctor::Bool(false), 0), (Ctor::Bool(true), 0)]),
+            Ctor::Literal(_) => return None,
+        };
+        let ctors = match self.ir.enums.get(&type_name) {
+            Some(enum_) => {
+                let mut varients = enum_.varients.keys().cloned().collect::<Vec<_>>();
+                varients.sort();
+                varients
+                    .into_iter()
+                    .map(|varient| (type_name.clone(), Some(varient)))
+                    .collect()
+            }
+            None => vec![(type_name, None)],
+        };
+        Some(
+            ctors
+                .into_iter()
+                .map(|(type_name, varient)| {
+                    let arity = ir_pattern::fields(self.ir, &type_name, varient.as_deref()).map_or(0, |fields| fields.len());
+                    (Ctor::Named { type_name, varient }, arity)
+                })
+                .collect(),
+        )
+    }
+    /// Whether some value matches `row` but none of `rows`.
+    fn useful(&self, rows: &[Vec<Pat>], row: &[Pat]) -> bool {
+        let Some((head, rest)) = row.split_first() else {
+            return rows.is_empty();
+        };
+        let rows = &expand(rows);
+        match head {
+            Pat::Or(alternatives) => alternatives
+                .iter()
+                .any(|alternative| self.useful(rows, &[std::slice::from_ref(alternative), rest].concat())),
+            Pat::Ctor(ctor, fields) => {
+                let row = [fields.as_slice(), rest].concat();
+                self.useful(&specialize(rows, ctor, fields.len()), &row)
+            }
+            Pat::Wild => {
+                let ctors = self.column_ctors(rows);
+                let complete = ctors.as_ref().is_some_and(|ctors| {
+                    ctors.iter().all(|(ctor, _)| {
+                        rows.iter().any(|row| matches!(row.first(), Some(Pat::Ctor(seen, _)) if seen == ctor))
+                    })
+                });
+                if complete {
+                    ctors.into_iter().flatten().any(|(ctor, arity)| {
+                        let row = [vec![Pat::Wild; arity].as_slice(), rest].concat();
+                        self.useful(&specialize(rows, &ctor, arity), &row)
+                    })
+                } else {
+                    // a constructor no row mentions is only matched by the rows that start with `_`
+                    let rows = rows
+                        .iter()
+                        .filter(|row| matches!(row.first(), Some(Pat::Wild)))
+                        .map(|row| row[1..].to_vec())
+                        .collect::<Vec<_>>();
+                    self.useful(&rows, rest)
+                }
+            }
+        }
+    }
+}
+
+/// The rows that can match a value built with `ctor`, with that value's fields in place of
+/// their first column.
+fn specialize(rows: &[Vec<Pat>], ctor: &Ctor, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| {
+            let (head, rest) = row.split_first()?;
+            match head {
+                Pat::Ctor(other, fields) if other == ctor => Some([fields.as_slice(), rest].concat()),
+                Pat::Ctor(..) => None,
+                Pat::Wild => Some([vec![Pat::Wild; arity].as_slice(), rest].concat()),
+                Pat::Or(_) => unreachable!("rows are expanded before they are specialized"),
+            }
+        })
+        .collect()
+}
+
+/// Splits every row starting with an or-pattern into a row for each of its alternatives.
+fn expand(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .flat_map(|row| match row.first() {
+            Some(Pat::Or(alternatives)) => expand(
+                &alternatives
+                    .iter()
+                    .map(|alternative| [std::slice::from_ref(alternative), &row[1..]].concat())
+                    .collect::<Vec<_>>(),
+            ),
+            _ => vec![row.clone()],
+        })
+        .collect()
+}
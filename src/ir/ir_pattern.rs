@@ -0,0 +1,265 @@
+//! Patterns with the types, varients and fields they name looked up, for `match`, `if let`
+//! and `let` alike.
+
+use crate::{
+    ast::{
+        decl::Type as ASTType,
+        expresions::Expresion,
+        parser::Spanned,
+        statments::{Destructure, Pattern},
+    },
+    scanner::{Span, Token},
+};
+
+use super::{
+    ir_chunk::ValueAccess,
+    ir_type_data::{CompileError, Type, IR},
+};
+
+/// A pattern checked against the types it names. The fields of a struct or varient are in
+/// name order, with a `Wildcard` for every field the pattern leaves out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IRPattern {
+    Wildcard,
+    /// Binds the whole value once the pattern in it matches, a lone name binds a `Wildcard`.
+    Binding(String, Box<IRPattern>),
+    Literal(ValueAccess),
+    Struct(String, Vec<IRPattern>),
+    Varient(String, String, Vec<IRPattern>),
+    Tuple(Vec<IRPattern>),
+    Or(Vec<IRPattern>),
+}
+
+impl IRPattern {
+    /// Every name the pattern binds, in the order they appear.
+    pub fn bindings(&self) -> Vec<String> {
+        match self {
+            IRPattern::Wildcard | IRPattern::Literal(_) => vec![],
+            IRPattern::Binding(name, pattern) => [vec![name.clone()], pattern.bindings()].concat(),
+            IRPattern::Struct(_, fields) | IRPattern::Varient(_, _, fields) | IRPattern::Tuple(fields) => {
+                fields.iter().flat_map(IRPattern::bindings).collect()
+            }
+            // every alternative binds the same names
+            IRPattern::Or(alternatives) => alternatives[0].bindings(),
+        }
+    }
+    /// Whether the pattern matches every value of its type just from its shape, a varient
+    /// never does even when it is the only one its enum has.
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            IRPattern::Wildcard => true,
+            IRPattern::Binding(_, pattern) => pattern.is_irrefutable(),
+            IRPattern::Struct(_, fields) | IRPattern::Tuple(fields) => fields.iter().all(IRPattern::is_irrefutable),
+            IRPattern::Or(alternatives) => alternatives.iter().any(IRPattern::is_irrefutable),
+            IRPattern::Literal(_) | IRPattern::Varient(..) => false,
+        }
+    }
+}
+
+/// Checks `pattern` names a real type, varient and fields, that everything it matches is of
+/// type `expected` when that is known, and that it binds no name twice.
+pub fn resolve(ir: &IR, pattern: &Pattern, expected: Option<&str>) -> Result<IRPattern, CompileError> {
+    let mut bound = vec![];
+    names(pattern, &mut bound);
+    for (index, name) in bound.iter().enumerate() {
+        if bound[..index].iter().any(|earlier| earlier.lexeme == name.lexeme) {
+            return Err(CompileError::DuplicateBinding(name.lexeme.clone(), name.span));
+        }
+    }
+    resolve_part(ir, pattern, expected)
+}
+
+/// The names `pattern` binds in the order they are written, those of the first alternative
+/// for an or-pattern as the others have to bind the same.
+fn names<'a>(pattern: &'a Pattern, bound: &mut Vec<&'a Token>) {
+    match pattern {
+        Pattern::Wildcard(_) | Pattern::Literal(_) => {}
+        Pattern::Binding(name) => bound.push(name),
+        Pattern::At(at) => {
+            bound.push(&at.name);
+            names(&at.pattern, bound);
+        }
+        Pattern::Destructure(destructure) => {
+            for (field, pattern) in &destructure.constructor {
+                match pattern {
+                    Some(pattern) => names(pattern, bound),
+                    None => bound.push(field),
+                }
+            }
+        }
+        Pattern::Tuple(tuple) => tuple.elements.iter().for_each(|element| names(element, bound)),
+        Pattern::Or(alternatives) => names(&alternatives[0], bound),
+    }
+}
+
+fn resolve_part(ir: &IR, pattern: &Pattern, expected: Option<&str>) -> Result<IRPattern, CompileError> {
+    let expected = match expected {
+        Some(expected) => Some(expected.to_string()),
+        None => pattern_type(pattern)?,
+    };
+    let expected = expected.as_deref();
+    Ok(match pattern {
+        Pattern::Wildcard(_) => IRPattern::Wildcard,
+        Pattern::Binding(name) => IRPattern::Binding(name.lexeme.clone(), Box::new(IRPattern::Wildcard)),
+        Pattern::At(at) => IRPattern::Binding(at.name.lexeme.clone(), Box::new(resolve_part(ir, &at.pattern, expected)?)),
+        Pattern::Literal(literal) => {
+            let (kind, value) = literal_value(literal);
+            check_type(expected, kind, literal.span())?;
+            IRPattern::Literal(value)
+        }
+        Pattern::Destructure(destructure) => destructure_pattern(ir, destructure, expected)?,
+        Pattern::Tuple(tuple) => {
+            check_type(expected, &tuple_name(tuple.elements.len()), tuple.span)?;
+            let elements = tuple
+                .elements
+                .iter()
+                .map(|element| resolve_part(ir, element, None))
+                .collect::<Result<Vec<_>, _>>()?;
+            IRPattern::Tuple(elements)
+        }
+        Pattern::Or(alternatives) => {
+            let resolved = alternatives
+                .iter()
+                .map(|alternative| resolve_part(ir, alternative, expected))
+                .collect::<Result<Vec<_>, _>>()?;
+            // whichever alternative matches, the same names have to end up bound
+            let mut first = resolved[0].bindings();
+            first.sort();
+            for (alternative, resolved) in alternatives.iter().zip(&resolved).skip(1) {
+                let mut bindings = resolved.bindings();
+                bindings.sort();
+                if bindings != first {
+                    return Err(CompileError::MismatchedOrBindings(alternative.span()));
+                }
+            }
+            IRPattern::Or(resolved)
+        }
+    })
+}
+
+/// The name of the type `pattern` matches, or the kind of literal it is, `None` when it
+/// matches anything.
+pub fn pattern_type(pattern: &Pattern) -> Result<Option<String>, CompileError> {
+    Ok(match pattern {
+        Pattern::Wildcard(_) | Pattern::Binding(_) => None,
+        Pattern::At(at) => pattern_type(&at.pattern)?,
+        Pattern::Literal(literal) => Some(literal_value(literal).0.to_string()),
+        Pattern::Destructure(destructure) => Some(type_name(destructure)?.to_string()),
+        Pattern::Tuple(tuple) => Some(tuple_name(tuple.elements.len())),
+        Pattern::Or(alternatives) => {
+            for alternative in alternatives {
+                if let Some(type_) = pattern_type(alternative)? {
+                    return Ok(Some(type_));
+                }
+            }
+            None
+        }
+    })
+}
+
+/// The fields of a struct, or of a varient when there is one, in name order with the name
+/// of their type when it is a struct or enum rather than a generic.
+pub fn fields(ir: &IR, type_name: &str, varient: Option<&str>) -> Option<Vec<(String, Option<String>)>> {
+    let (generics, fields) = match varient {
+        None => {
+            let struct_ = ir.structs.get(type_name)?;
+            (&struct_.generics, &struct_.fields)
+        }
+        Some(varient) => {
+            let enum_ = ir.enums.get(type_name)?;
+            (&enum_.generics, enum_.varients.get(varient)?)
+        }
+    };
+    let mut fields = fields
+        .iter()
+        .map(|(name, type_)| {
+            let type_name = match type_ {
+                Type::Actual(actual)
+                    if !generics.iter().any(|generic| generic.name == actual.name)
+                        && (ir.structs.contains_key(&actual.name) || ir.enums.contains_key(&actual.name)) =>
+                {
+                    Some(actual.name.clone())
+                }
+                _ => None,
+            };
+            (name.clone(), type_name)
+        })
+        .collect::<Vec<_>>();
+    fields.sort();
+    Some(fields)
+}
+
+fn destructure_pattern(ir: &IR, destructure: &Destructure, expected: Option<&str>) -> Result<IRPattern, CompileError> {
+    let name = type_name(destructure)?;
+    check_type(expected, name, destructure.span)?;
+    let varient = destructure.varient.as_ref().map(|varient| varient.lexeme.as_str());
+    if varient.is_none() && ir.enums.contains_key(name) {
+        // naming just the enum matches any of its varients
+        return Ok(IRPattern::Wildcard);
+    }
+    let fields = fields(ir, name, varient).ok_or_else(|| match &destructure.varient {
+        Some(varient) if ir.enums.contains_key(name) || ir.structs.contains_key(name) => {
+            CompileError::NoVarientWithThatName(name.to_string(), varient.lexeme.clone(), varient.span)
+        }
+        _ => CompileError::NoTypeWithThatNameFound(Type::from(&destructure.type_of)),
+    })?;
+    let full_name = match varient {
+        Some(varient) => format!("{name}.{varient}"),
+        None => name.to_string(),
+    };
+    let mut patterns = vec![IRPattern::Wildcard; fields.len()];
+    for (field, pattern) in &destructure.constructor {
+        let index = fields
+            .iter()
+            .position(|(name, _)| *name == field.lexeme)
+            .ok_or_else(|| CompileError::NoFieldWithThatName(full_name.clone(), field.lexeme.clone(), field.span))?;
+        patterns[index] = match pattern {
+            Some(pattern) => resolve_part(ir, pattern, fields[index].1.as_deref())?,
+            // `Point{x}` is short for `Point{x: x}`
+            None => IRPattern::Binding(field.lexeme.clone(), Box::new(IRPattern::Wildcard)),
+        };
+    }
+    Ok(match varient {
+        Some(varient) => IRPattern::Varient(name.to_string(), varient.to_string(), patterns),
+        None => IRPattern::Struct(name.to_string(), patterns),
+    })
+}
+
+/// What a tuple of `len` elements is called where a type name is expected, `(_, _)` for two.
+pub fn tuple_name(len: usize) -> String {
+    match len {
+        1 => "(_,)".to_string(),
+        len => format!("({})", vec!["_"; len].join(", ")),
+    }
+}
+
+fn check_type(expected: Option<&str>, found: &str, span: Span) -> Result<(), CompileError> {
+    match expected {
+        Some(expected) if expected != found => Err(CompileError::MismatchedPatternType(
+            expected.to_string(),
+            found.to_string(),
+            span,
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// What kind of literal `literal` is, and its value.
+fn literal_value(literal: &Expresion) -> (&'static str, ValueAccess) {
+    match literal {
+        Expresion::IntLitteral(int) => ("integer", ValueAccess::IntConst(int.value, int.suffix)),
+        Expresion::FloatLitteral(float) => ("float", ValueAccess::FloatConst(float.value, float.suffix)),
+        Expresion::StringLitteral(string) => ("string", ValueAccess::StringConst(string.value.as_str().into())),
+        Expresion::CharLitteral(char) => ("char", ValueAccess::CharConst(char.value)),
+        Expresion::True(_) => ("bool", ValueAccess::True),
+        Expresion::False(_) => ("bool", ValueAccess::False),
+        other => unreachable!("literal patterns are only ever parsed as literals, not {other:?}"),
+    }
+}
+
+fn type_name(destructure: &Destructure) -> Result<&str, CompileError> {
+    match &destructure.type_of {
+        ASTType::PhysicalType(solid) => Ok(&solid.name.lexeme),
+        other => Err(CompileError::NoTypeWithThatNameFound(Type::from(other))),
+    }
+}
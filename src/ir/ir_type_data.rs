@@ -3,11 +3,11 @@ use std::collections::HashMap;
 use itertools::Itertools;
 use pub_fields::pub_fields;
 
-use crate::ast::decl::{
-    self, EnumDecl, FieldDecl, FuncSig, FunctionDecl, FunctionPointer, GenericDecl, ImplDecl, Program, SolidType, StructDecl, TraitDecl, Type as ASTType, VarientDecl
-};
+use crate::{ast::decl::{
+    self, EnumDecl, FieldDecl, FuncSig, FunctionDecl, FunctionPointer, GenericDecl, ImplDecl, Program, SolidType, StructDecl, TraitDecl, Type as ASTType, VarientDecl, Visibility
+}, module::Crate, scanner::Span};
 
-use super::{ir_chunk::IRChunk, ir_validator::IRValidator};
+use super::{ir_alias, ir_chunk::{IRChunk, ValueAccess}, ir_const, ir_lowering, ir_match_check, ir_validator::IRValidator, ir_visibility};
 
 #[derive(Debug, Clone)]
 #[pub_fields]
@@ -17,6 +17,25 @@ pub struct IR {
     traits: HashMap<String, Trait>,
     impls: Vec<Impl>,
     function: HashMap<String, Function>,
+    consts: HashMap<String, Const>,
+    statics: HashMap<String, Static>,
+}
+
+/// A constant, with the value it was worked out to have.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct Const {
+    type_: Type,
+    value: ValueAccess,
+}
+
+/// A static, with the value it starts with.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct Static {
+    mutable: bool,
+    type_: Type,
+    value: ValueAccess,
 }
 
 #[derive(Debug, Clone)]
@@ -62,10 +81,19 @@ pub struct Enum {
 pub struct Struct {
     generics: Vec<Generic>,
     fields: Fields,
+    visibility: HashMap<String, Declared>,
 }
 
 pub type Fields = HashMap<String, Type>;
 
+/// Who can see a field, and where it was declared.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct Declared {
+    visibility: Visibility,
+    span: Span,
+}
+
 #[derive(Debug, Clone)]
 #[pub_fields]
 pub struct Generic {
@@ -80,6 +108,7 @@ pub enum Type {
     Array(ActualType),
     DynType(Vec<TraitType>),
     FP(FP),
+    Tuple(Vec<Type>),
 }
 
 #[derive(Debug, Clone)]
@@ -94,6 +123,7 @@ pub struct FP {
 pub struct ActualType {
     name: String,
     types_in_generics: Vec<Type>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -101,19 +131,64 @@ pub struct ActualType {
 pub struct TraitType {
     name: String,
     types_in_generics: Vec<Type>,
+    span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum CompileError {
     GenericsCantHaveTypeParameters(ActualType),
-    NumberOfGenericsAndTypesGivenDoNotMatchInItem(String,usize, usize),
+    NumberOfGenericsAndTypesGivenDoNotMatchInItem(String,usize, usize, Span),
     OnlyTraitsCanBeImplimentedOn(Type),
-    NoTraitWithThatNameFound(String),
+    NoTraitWithThatNameFound(String, Span),
     NoTypeWithThatNameFound(Type),
     DuplicateStructs(Vec<String>),
     DuplicateEnums(Vec<String>),
     DuplicateTraits(Vec<String>),
     DuplicateFunctions(Vec<String>),
+    UnknownVariable(String, Span),
+    BreakOutsideLoop(Span),
+    ContinueOutsideLoop(Span),
+    RangeOutsideForLoop(Span),
+    NotLoweredYet(Span),
+    NoVarientWithThatName(String, String, Span),
+    NoFieldWithThatName(String, String, Span),
+    MismatchedPatternType(String, String, Span),
+    NonExhaustiveMatch(Vec<String>, Span),
+    UnreachableMatchArm(Span),
+    MismatchedOrBindings(Span),
+    /// A name bound twice in one pattern, at the second time.
+    DuplicateBinding(String, Span),
+    RefutablePatternInLet(Span),
+    MismatchedClosureType(String, String, Span),
+    AssignToImmutable(String, Span),
+    InvalidAssignTarget(Span),
+    TypeNotKnown(Span),
+    NoFunctionWithThatName(String, String, Span),
+    MissingFields(String, Vec<String>, Span),
+    DuplicateNames(String, Vec<String>),
+    UnresolvedPath(String, Span),
+    PrivateItem(String, Span, Span),
+    PrivateField(String, String, Span, Span),
+    DuplicateConsts(Vec<String>),
+    NotConstant(Span),
+    ConstOverflow(Span),
+    ConstDivideByZero(Span),
+    MismatchedConstType(String, String, Span),
+    CyclicConst(String, Span),
+    /// Statics that share a name with each other or with a constant.
+    DuplicateStatics(Vec<String>),
+    DuplicateTypeAliases(Vec<String>),
+    /// Aliases and newtypes that share a name with a struct or an enum.
+    AliasesNamedLikeTypes(Vec<String>),
+    CyclicTypeAlias(String, Span),
+    /// An alias that ends up in `[]` as something other than a named type, written there
+    /// itself or given for a generic it puts there.
+    AliasInArrayIsNotNamedType(String, Span),
+    /// An alias used in a constructor or before `::` that names something other than a
+    /// struct or an enum.
+    AliasIsNotNamedType(String, Span),
+    /// Everything `IRValidator` found wrong with the types the declarations name.
+    Invalid(Vec<CompileError>),
 }
 
 
@@ -122,11 +197,18 @@ impl TryFrom<&Program> for IR {
     type Error = CompileError;
 
     fn try_from(program: &Program) -> Result<Self, Self::Error> {
-        let (structs, enums, traits, impls, functions) =
+        IR::from_expanded(&ir_alias::expand(program)?)
+    }
+}
+
+impl IR {
+    /// The IR of `program` once its aliases have been swapped for what they name.
+    fn from_expanded(program: &Program) -> Result<Self, CompileError> {
+        let (structs, enums, traits, impls, functions, consts, statics) =
             program
                 .code
                 .iter()
-                .fold((vec![], vec![], vec![], vec![], vec![]), |mut acc, decl| {
+                .fold((vec![], vec![], vec![], vec![], vec![], vec![], vec![]), |mut acc, decl| {
                     match decl {
                         crate::ast::decl::Declaration::StructDecl(struct_) => acc.0.push(struct_),
                         crate::ast::decl::Declaration::EnumDecl(enum_) => acc.1.push(enum_),
@@ -135,6 +217,12 @@ impl TryFrom<&Program> for IR {
                         crate::ast::decl::Declaration::FunctionDecl(function_) => {
                             acc.4.push(function_)
                         }
+                        crate::ast::decl::Declaration::ConstDecl(const_) => acc.5.push(const_),
+                        crate::ast::decl::Declaration::StaticDecl(static_) => acc.6.push(static_),
+                        // linking a crate resolves these, they mean nothing in one file on its own
+                        crate::ast::decl::Declaration::ModDecl(_) | crate::ast::decl::Declaration::UseDecl(_) => {}
+                        // already swapped for what they name
+                        crate::ast::decl::Declaration::TypeAliasDecl(_) => {}
                     }
                     acc
                 });
@@ -154,6 +242,7 @@ impl TryFrom<&Program> for IR {
         if !duplicate_things.is_empty(){
             Err(CompileError::DuplicateFunctions(duplicate_things))?;
         }
+        let (consts, statics) = ir_const::evaluate(&consts, &statics, &impls)?;
         let mut ir = IR {
             structs: structs
                 .into_iter()
@@ -167,18 +256,66 @@ impl TryFrom<&Program> for IR {
                 .into_iter()
                 .map(<(String, Trait)>::from)
                 .collect::<HashMap<String, Trait>>(),
-            impls: impls
-                .into_iter()
-                .map(Impl::try_from)
-                .collect::<Result<Vec<Impl>, CompileError>>()?,
-            function: functions
-                .into_iter()
-                .map(<(String, Function)>::from)
-                .collect::<HashMap<String, Function>>()
+            impls: vec![],
+            function: HashMap::new(),
+            consts,
+            statics,
         };
-        
-        IRValidator::validate(&ir);
+        // bodies are checked once every type they can refer to is known
+        for function in functions.iter().copied().chain(impls.iter().flat_map(|impl_| &impl_.funcs)) {
+            ir_match_check::check_function(&ir, function)?;
+        }
+        // the signatures go in first
+        ir.impls = impls
+            .iter()
+            .copied()
+            .map(Impl::try_from)
+            .collect::<Result<Vec<Impl>, CompileError>>()?;
+        ir.function = functions
+            .iter()
+            .copied()
+            .map(<(String, Function)>::from)
+            .collect::<HashMap<String, Function>>();
+        // which is all the validator looks at
+        IRValidator::validate(&ir).map_err(CompileError::Invalid)?;
+        // and the bodies are lowered once every function they can call is known
+        let bodies = functions
+            .iter()
+            .map(|function| Ok((function.sig.name.lexeme.clone(), ir_lowering::lower_function(&ir, function)?)))
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        for (name, body) in bodies {
+            ir.function.get_mut(&name).expect("every function was just added").body = body;
+        }
+        let bodies = impls
+            .iter()
+            .zip(&ir.impls)
+            .map(|(decl, impl_)| {
+                decl.funcs
+                    .iter()
+                    .map(|function| match &impl_.type_to_impl_on {
+                        Type::Actual(actual) => ir_lowering::lower_method(&ir, function, &actual.name),
+                        _ => ir_lowering::lower_function(&ir, function),
+                    })
+                    .collect::<Result<Vec<_>, CompileError>>()
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        for (impl_, bodies) in ir.impls.iter_mut().zip(bodies) {
+            for ((_, function), body) in impl_.funcs.iter_mut().zip(bodies) {
+                function.body = body;
+            }
+        }
+        Ok(ir)
+    }
+}
+
+impl TryFrom<&Crate> for IR {
+    type Error = CompileError;
 
+    fn try_from(crate_: &Crate) -> Result<Self, Self::Error> {
+        let program = ir_alias::expand(&crate_.link()?)?;
+        let ir = IR::from_expanded(&program)?;
+        let modules = crate_.modules.iter().map(|module| module.path.clone()).collect::<Vec<_>>();
+        ir_visibility::check_program(&ir, &program, &modules)?;
         Ok(ir)
     }
 }
@@ -217,7 +354,8 @@ impl From<&FunctionDecl> for (String, Function) {
             value.sig.name.lexeme.clone(),
             Function {
                 tag: <(String, FuncTag)>::from(&value.sig).1,
-                body: todo!(),
+                // lowered by `IR::try_from` once every signature is known
+                body: IRChunk { functions: HashMap::new(), code: vec![] },
             },
         )
     }
@@ -308,6 +446,14 @@ impl From<&StructDecl> for (String, Struct) {
                     .iter()
                     .map(<(String, Type)>::from)
                     .collect::<HashMap<String, Type>>(),
+                visibility: value
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let declared = Declared { visibility: field.visibility, span: field.name.span };
+                        (field.name.lexeme.clone(), declared)
+                    })
+                    .collect(),
             },
         )
     }
@@ -333,6 +479,7 @@ impl From<&decl::TraitType> for TraitType{
         Self {
             name: value.name.lexeme.clone(),
             types_in_generics: value.generics.iter().map(Type::from).collect(),
+            span: value.span,
         }
     }
 }
@@ -341,12 +488,13 @@ impl From<&ASTType> for Type {
     fn from(value: &ASTType) -> Self {
         match value {
             ASTType::PhysicalType(actual_type) => Self::Actual(ActualType::from(actual_type)),
-            ASTType::Unit => Self::Unit,
-            ASTType::Array(actual_type) => Self::Array(ActualType::from(actual_type)),
+            ASTType::Unit(_) => Self::Unit,
+            ASTType::Array(actual_type, _) => Self::Array(ActualType::from(actual_type)),
             ASTType::FP(fp) => Self::FP(FP::from(fp)),
-            ASTType::DynamicType(traits) => {
+            ASTType::DynamicType(traits, _) => {
                 Self::DynType(traits.iter().map(TraitType::from).collect())
             }
+            ASTType::Tuple(types, _) => Self::Tuple(types.iter().map(Type::from).collect()),
         }
     }
 }
@@ -365,6 +513,7 @@ impl From<&SolidType> for ActualType {
         Self {
             name: value.name.lexeme.clone(),
             types_in_generics: value.generics.iter().map(Type::from).collect(),
+            span: value.span,
         }
     }
 }
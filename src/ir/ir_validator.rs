@@ -1,96 +1,172 @@
-use std::collections::{HashMap, HashSet};
+//! Checks that the types every declaration names are there: each struct, enum and trait a
+//! type or constraint names is declared or built in, a generic is in scope where it is used,
+//! and each is given as many types as it has generics. Every problem is kept, not just the
+//! first.
 
-use super::ir_type_data::{CompileError, Enum, Function, Generic, Impl, Struct, Trait, TraitType, Type, IR};
+use std::collections::HashSet;
 
-trait ValidateCollect {
-    fn collect_ce(self) -> Result<(), CompileError>;
-}
+use itertools::Itertools;
 
-impl<T> ValidateCollect for T
-where
-    T: Iterator<Item = Result<(), CompileError>>,
-{
-    fn collect_ce(self) -> Result<(), CompileError> {
-        self.collect::<Result<Vec<()>, CompileError>>()
-            .and_then(|_| Ok(()))
-    }
-}
+use crate::scanner::{FloatSuffix, IntSuffix, Span};
 
-pub struct IRValidator {
-    current_generics:Vec<Vec<HashMap<String,Generic>>>,
-    valid_structs:HashSet<String>,
-    valid_functions:Vec<HashSet<String>>,
-    valid_enums:HashSet<String>,
-    valid_traits:HashSet<String>,
-    valid_impls:HashSet<usize>,
-}
+use super::ir_type_data::{ActualType, CompileError, FuncTag, Generic, Impl, TraitType, Type, IR};
 
+/// The types that are there without being declared, besides the sized ints and floats.
+const BUILT_IN: &[&str] = &["Int", "Float", "Bool", "Char", "String"];
 
-impl IRValidator {
-    pub fn validate(ir:&IR) -> Result<(), CompileError> {
-        let mut validator = Self{ current_generics: vec![vec![]], valid_structs: [].into(), valid_functions: [[].into()].into(), valid_enums: [].into(), valid_traits: [].into(), valid_impls: [].into() };
-        ir.structs
-            .iter()
-            .map(|struct_| {
-                    if !validator.valid_structs.contains(struct_.0){
-                        let res = validator.validate_struct(&ir, struct_.1);
-                        if res.is_ok() {
-                            validator.valid_structs.insert(struct_.0.clone());
-                        }
-                        validator.valid_structs.insert(struct_.0.clone());
-                        res
-                    }else{
-                        Ok(())
+pub struct IRValidator<'a> {
+    ir: &'a IR,
+    /// The generics in scope, one set for each item they come from, innermost last.
+    current_generics: Vec<HashSet<String>>,
+    /// Whether `Self` means something, it does in a trait or an impl.
+    in_self: bool,
+    errors: Vec<CompileError>,
+}
+
+impl<'a> IRValidator<'a> {
+    pub fn validate(ir: &'a IR) -> Result<(), Vec<CompileError>> {
+        let mut validator = Self { ir, current_generics: vec![], in_self: false, errors: vec![] };
+        // in name order so the errors come out the same way every time
+        for (_, struct_) in ir.structs.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+            validator.with_generics(&struct_.generics, |validator| {
+                for (_, type_) in struct_.fields.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+                    validator.validate_type(type_);
+                }
+            });
+        }
+        for (_, enum_) in ir.enums.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+            validator.with_generics(&enum_.generics, |validator| {
+                for (_, fields) in enum_.varients.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+                    for (_, type_) in fields.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+                        validator.validate_type(type_);
                     }
                 }
-            )
-            .collect_ce()?;
-        Ok(())
+            });
+        }
+        validator.in_self = true;
+        for (_, trait_) in ir.traits.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+            validator.with_generics(&trait_.generics, |validator| {
+                for (_, func_tag) in trait_.func_tag.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+                    validator.validate_func_tag(func_tag);
+                }
+            });
+        }
+        for impl_ in &ir.impls {
+            validator.validate_impl(impl_);
+        }
+        validator.in_self = false;
+        for (_, function) in ir.function.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+            validator.validate_func_tag(&function.tag);
+        }
+        if validator.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(validator.errors)
+        }
     }
-    fn new_type_generics_to_check<T:FnOnce()->Result<(),CompileError>>(&mut self,type_validator:T)->Result<(),CompileError>{
-        self.current_generics.push(vec![]);
-        type_validator()?;
+    /// Runs `validate` with `generics` in scope. They all go in before any constraint is
+    /// checked, as a constraint can name a generic that comes after it.
+    fn with_generics(&mut self, generics: &[Generic], validate: impl FnOnce(&mut Self)) {
+        self.current_generics.push(generics.iter().map(|generic| generic.name.clone()).collect());
+        for generic in generics {
+            self.validate_generic(generic);
+        }
+        validate(self);
         self.current_generics.pop();
-        Ok(())
     }
-    fn add_generic(&mut self, generic: &Generic){
-        self.current_generics.last_mut().unwrap().last_mut().unwrap().insert(generic.name.clone(), generic.clone());
+    fn is_generic(&self, name: &str) -> bool {
+        self.current_generics.iter().any(|generics| generics.contains(name))
     }
-    fn validate_struct(&mut self, ir:&IR, struct_: &Struct) -> Result<(), CompileError> {
-        self.new_type_generics_to_check(||{
-            struct_
-                .generics
-                .iter()
-                .map(|generic| self.validate_generic(&ir,generic))
-                .collect_ce()?;
-            Ok(())
-        })
+    fn validate_generic(&mut self, generic: &Generic) {
+        for constraint in &generic.constraits {
+            self.validate_trait_type(constraint);
+        }
+    }
+    fn validate_func_tag(&mut self, func_tag: &FuncTag) {
+        self.with_generics(&func_tag.generics, |validator| {
+            for (_, type_) in &func_tag.inputs {
+                validator.validate_type(type_);
+            }
+            validator.validate_type(&func_tag.output);
+        });
     }
-    fn validate_generic(&mut self, ir:&IR, generic: &Generic) -> Result<(), CompileError> {
-        generic.constraits.iter().map(|constraint|{self.validate_trait_type(&ir,constraint)}).collect_ce()?;
-        self.current_generics.last_mut().unwrap().insert(generic.name.clone(), generic.clone());
-        Ok(())
+    fn validate_impl(&mut self, impl_: &Impl) {
+        self.with_generics(&impl_.generics, |validator| {
+            if let Some(trait_) = &impl_.trait_to_impl {
+                validator.validate_trait_type(&TraitType {
+                    name: trait_.name.clone(),
+                    types_in_generics: trait_.types_in_generics.clone(),
+                    span: trait_.span,
+                });
+            }
+            validator.validate_type(&impl_.type_to_impl_on);
+            for (_, function) in &impl_.funcs {
+                validator.validate_func_tag(&function.tag);
+            }
+        });
     }
-    fn validate_trait_type(&mut self, ir:&IR, trait_:&TraitType)-> Result<(),CompileError>{
-        let actual_trait = ir.traits.get(&trait_.name).ok_or(CompileError::NoTraitWithThatNameFound(trait_.name.clone()))?;
-        if actual_trait.generics.len() != trait_.types_in_generics.len(){
-            Err(CompileError::NumberOfGenericsAndTypesGivenDoNotMatchInItem(trait_.name.clone(), actual_trait.generics.len(), trait_.types_in_generics.len()))?;
+    fn validate_trait_type(&mut self, trait_: &TraitType) {
+        for type_ in &trait_.types_in_generics {
+            self.validate_type(type_);
         }
-        if !self.valid_traits.contains(&trait_.name){
-            self.validate_trait(&ir,&actual_trait)?;
+        match self.ir.traits.get(&trait_.name) {
+            Some(actual_trait) => {
+                self.check_arity(&trait_.name, actual_trait.generics.len(), trait_.types_in_generics.len(), trait_.span)
+            }
+            None => self.errors.push(CompileError::NoTraitWithThatNameFound(trait_.name.clone(), trait_.span)),
         }
-        //actual_trait.generics.iter().zip(trait_.types_in_generics.iter()).map(|(generic,type_given)|{self.validate_type_in_constraints(&ir,generic.con)}).collect_ce()?;
-        Ok(())
     }
-    fn validate_trait(&mut self, ir:&IR, trait_:&Trait)->Result<(),CompileError>{
-        self.current_generics.push(HashMap::new());
-        trait_
-            .generics
-            .iter()
-            .map(|generic| self.validate_generic(&ir,generic))
-            .collect_ce()?;
-        validatetrait_.func_tag
-        self.current_generics.pop();
-        Ok(())
+    fn validate_type(&mut self, type_: &Type) {
+        match type_ {
+            Type::Unit => {}
+            Type::Actual(actual) | Type::Array(actual) => self.validate_actual_type(actual),
+            Type::DynType(traits) => {
+                for trait_ in traits {
+                    self.validate_trait_type(trait_);
+                }
+            }
+            Type::FP(fp) => {
+                for arg in &fp.arg {
+                    self.validate_type(arg);
+                }
+                self.validate_type(&fp.out);
+            }
+            Type::Tuple(types) => {
+                for type_ in types {
+                    self.validate_type(type_);
+                }
+            }
+        }
+    }
+    fn validate_actual_type(&mut self, actual: &ActualType) {
+        for type_ in &actual.types_in_generics {
+            self.validate_type(type_);
+        }
+        let name = actual.name.as_str();
+        let generics = if self.is_generic(name) {
+            if !actual.types_in_generics.is_empty() {
+                self.errors.push(CompileError::GenericsCantHaveTypeParameters(actual.clone()));
+            }
+            return;
+        } else if let Some(struct_) = self.ir.structs.get(name) {
+            struct_.generics.len()
+        } else if let Some(enum_) = self.ir.enums.get(name) {
+            enum_.generics.len()
+        } else if (name == "Self" && self.in_self)
+            || BUILT_IN.contains(&name)
+            || name.parse::<IntSuffix>().is_ok()
+            || name.parse::<FloatSuffix>().is_ok()
+        {
+            0
+        } else {
+            self.errors.push(CompileError::NoTypeWithThatNameFound(Type::Actual(actual.clone())));
+            return;
+        };
+        self.check_arity(name, generics, actual.types_in_generics.len(), actual.span);
     }
-}
\ No newline at end of file
+    fn check_arity(&mut self, name: &str, expected: usize, given: usize, span: Span) {
+        if expected != given {
+            self.errors.push(CompileError::NumberOfGenericsAndTypesGivenDoNotMatchInItem(name.to_string(), expected, given, span));
+        }
+    }
+}
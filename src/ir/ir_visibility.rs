@@ -0,0 +1,385 @@
+//! Checks that the fields and methods a function uses are ones its module can see.
+//!
+//! Which struct a field belongs to is only known when the type of the value in front of
+//! the `.` is, there's no type checker yet, so the types of locals are followed through
+//! their annotations, constructors, fields, tuple elements, array elements, the arms of a
+//! `match` and the return types of calls and closures. Whether items can be seen is
+//! checked when the crate is linked, as that's where names are resolved.
+//!
+//! The methods of a trait impl are as visible as the trait, so only inherent ones are
+//! checked. Associated constants are checked like methods. The values constants and
+//! statics start with are checked like function bodies.
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{
+        decl::{Declaration, FunctionDecl, Program, Type as ASTType, Visibility},
+        expresions::{Closure, ConstructorType, Expresion},
+        statments::{Pattern, Statment},
+        visit::{walk_expresion, walk_statment, Visit},
+    },
+    scanner::{Span, Token, TokenType},
+};
+
+use super::ir_type_data::{ActualType, CompileError, Type, FP, IR};
+
+/// Checks every function of `program`, which `ir` was built from. `modules` is the path of
+/// the module each file is, by `file_id`.
+pub fn check_program(ir: &IR, program: &Program, modules: &[Vec<String>]) -> Result<(), CompileError> {
+    let mut checker = VisibilityChecker {
+        ir,
+        modules,
+        functions: HashMap::new(),
+        methods: HashMap::new(),
+        self_type: None,
+        locals: vec![],
+    };
+    for declaration in &program.code {
+        match declaration {
+            Declaration::FunctionDecl(function) => {
+                checker.functions.insert(&function.sig.name.lexeme, &function.sig.out);
+            }
+            Declaration::ImplDecl(impl_) if impl_.trait_to_impl.is_none() => {
+                let Some(type_name) = checker.type_name(&impl_.type_to_impl_on) else {
+                    continue;
+                };
+                for function in &impl_.funcs {
+                    let method = Method {
+                        visibility: function.visibility,
+                        span: function.sig.name.span,
+                        out: &function.sig.out,
+                    };
+                    checker.methods.insert((type_name.clone(), function.sig.name.lexeme.clone()), method);
+                }
+                for const_ in &impl_.consts {
+                    let method = Method {
+                        visibility: const_.visibility,
+                        span: const_.name.span,
+                        out: &const_.type_of,
+                    };
+                    checker.methods.insert((type_name.clone(), const_.name.lexeme.clone()), method);
+                }
+            }
+            _ => {}
+        }
+    }
+    for declaration in &program.code {
+        match declaration {
+            Declaration::FunctionDecl(function) => checker.function(function, None)?,
+            Declaration::ConstDecl(const_) => checker.initializer(&const_.value, None)?,
+            Declaration::StaticDecl(static_) => checker.initializer(&static_.value, None)?,
+            Declaration::ImplDecl(impl_) => {
+                let self_type = checker.type_name(&impl_.type_to_impl_on);
+                for function in &impl_.funcs {
+                    checker.function(function, self_type.clone())?;
+                }
+                for const_ in &impl_.consts {
+                    checker.initializer(&const_.value, self_type.clone())?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+struct Method<'a> {
+    visibility: Visibility,
+    span: Span,
+    out: &'a ASTType,
+}
+
+struct VisibilityChecker<'a> {
+    ir: &'a IR,
+    modules: &'a [Vec<String>],
+    /// The return type of every free function.
+    functions: HashMap<&'a str, &'a ASTType>,
+    /// Every inherent method and associated constant, by the name of its type and its own.
+    methods: HashMap<(String, String), Method<'a>>,
+    self_type: Option<String>,
+    /// The type of every local in scope, when it's known.
+    locals: Vec<HashMap<String, Option<Type>>>,
+}
+
+impl<'a> VisibilityChecker<'a> {
+    fn module(&self, span: Span) -> &[String] {
+        self.modules.get(span.file_id).map_or(&[], Vec::as_slice)
+    }
+    fn type_name(&self, type_: &ASTType) -> Option<String> {
+        match type_ {
+            ASTType::PhysicalType(solid) if solid.name.lexeme == "Self" => self.self_type.clone(),
+            ASTType::PhysicalType(solid) => Some(solid.name.lexeme.clone()),
+            _ => None,
+        }
+    }
+    /// The struct or enum `type_` is, with `Self` standing for the type of the impl.
+    fn named(&self, type_: &Type) -> Option<String> {
+        match type_ {
+            Type::Actual(actual) if actual.name == "Self" => self.self_type.clone(),
+            Type::Actual(actual) => Some(actual.name.clone()),
+            _ => None,
+        }
+    }
+    fn bind_as(&mut self, name: &Token, type_: Option<Type>) {
+        self.locals
+            .last_mut()
+            .expect("names are only bound inside a scope")
+            .insert(name.lexeme.clone(), type_);
+    }
+    fn local(&self, name: &Token) -> Option<&Option<Type>> {
+        self.locals.iter().rev().find_map(|locals| locals.get(&name.lexeme))
+    }
+    /// Runs `inside` in a scope of its own with `Self` standing for `self_type`.
+    fn within(
+        &mut self,
+        self_type: Option<String>,
+        inside: impl FnOnce(&mut Self) -> Result<(), CompileError>,
+    ) -> Result<(), CompileError> {
+        let outer = std::mem::replace(&mut self.self_type, self_type);
+        let result = self.scoped(inside);
+        self.self_type = outer;
+        result
+    }
+    fn function(&mut self, function: &FunctionDecl, self_type: Option<String>) -> Result<(), CompileError> {
+        self.within(self_type, |checker| {
+            for (name, type_) in &function.sig.parameters {
+                checker.bind_as(name, Some(Type::from(type_)));
+            }
+            function
+                .body
+                .statments
+                .iter()
+                .try_for_each(|statment| checker.visit_statment(statment))
+        })
+    }
+    /// Checks `value`, what a constant or static starts with.
+    fn initializer(&mut self, value: &Expresion, self_type: Option<String>) -> Result<(), CompileError> {
+        self.within(self_type, |checker| checker.visit_expresion(value))
+    }
+    /// Checks that `field` of the struct `type_name` can be seen where it's used. Anything
+    /// that isn't a field of a known struct is left alone.
+    fn field(&self, type_name: &str, field: &Token) -> Result<(), CompileError> {
+        let Some(declared) = self
+            .ir
+            .structs
+            .get(type_name)
+            .and_then(|struct_| struct_.visibility.get(&field.lexeme))
+        else {
+            return Ok(());
+        };
+        if declared.visibility.allows(self.module(declared.span), self.module(field.span)) {
+            Ok(())
+        } else {
+            Err(CompileError::PrivateField(type_name.to_string(), field.lexeme.clone(), field.span, declared.span))
+        }
+    }
+    fn method(&self, type_name: &str, name: &Token) -> Result<(), CompileError> {
+        let Some(method) = self.methods.get(&(type_name.to_string(), name.lexeme.clone())) else {
+            return Ok(());
+        };
+        if method.visibility.allows(self.module(method.span), self.module(name.span)) {
+            Ok(())
+        } else {
+            Err(CompileError::PrivateItem(format!("{type_name}::{}", name.lexeme), name.span, method.span))
+        }
+    }
+    /// Checks the fields `pattern` names and binds the names in it, `type_` is the type of
+    /// the value it's matched against when that's known.
+    fn pattern(&mut self, pattern: &Pattern, type_: Option<Type>) -> Result<(), CompileError> {
+        match pattern {
+            Pattern::Wildcard(_) | Pattern::Literal(_) => Ok(()),
+            Pattern::Binding(name) => {
+                self.bind_as(name, type_);
+                Ok(())
+            }
+            Pattern::At(at) => {
+                self.bind_as(&at.name, type_.clone());
+                self.pattern(&at.pattern, type_)
+            }
+            Pattern::Destructure(destructure) => {
+                let type_name = self.type_name(&destructure.type_of);
+                for (field, pattern) in &destructure.constructor {
+                    let field_type = match (&type_name, &destructure.varient) {
+                        (Some(type_name), None) => {
+                            self.field(type_name, field)?;
+                            self.field_type(type_name, &field.lexeme)
+                        }
+                        _ => None,
+                    };
+                    match pattern {
+                        Some(pattern) => self.pattern(pattern, field_type)?,
+                        None => self.bind_as(field, field_type),
+                    }
+                }
+                Ok(())
+            }
+            Pattern::Tuple(tuple) => {
+                let types = match type_ {
+                    Some(Type::Tuple(types)) => types,
+                    _ => vec![],
+                };
+                for (index, element) in tuple.elements.iter().enumerate() {
+                    self.pattern(element, types.get(index).cloned())?;
+                }
+                Ok(())
+            }
+            Pattern::Or(alternatives) => alternatives
+                .iter()
+                .try_for_each(|alternative| self.pattern(alternative, type_.clone())),
+        }
+    }
+    fn field_type(&self, type_name: &str, field: &str) -> Option<Type> {
+        self.ir.structs.get(type_name)?.fields.get(field).cloned()
+    }
+    /// The type of `expresion`, when it's plain to see.
+    fn type_of(&self, expresion: &Expresion) -> Option<Type> {
+        match expresion {
+            Expresion::VarAccess(name) => self.local(name)?.clone(),
+            Expresion::Parens(parens) => self.type_of(&parens.expr),
+            Expresion::FieldAcess(access) if access.field.token_type == TokenType::Int => {
+                let Type::Tuple(types) = self.type_of(&access.expr)? else {
+                    return None;
+                };
+                types.get(access.field.lexeme.parse::<usize>().ok()?).cloned()
+            }
+            Expresion::FieldAcess(access) => {
+                self.field_type(&self.named(&self.type_of(&access.expr)?)?, &access.field.lexeme)
+            }
+            Expresion::Index(index) => match self.type_of(&index.expr)? {
+                Type::Array(element) => Some(Type::Actual(element)),
+                _ => None,
+            },
+            // an element whose type isn't known is `()`, which has no fields to check
+            Expresion::Tuple(tuple) => Some(Type::Tuple(
+                tuple.elements.iter().map(|element| self.type_of(element).unwrap_or(Type::Unit)).collect(),
+            )),
+            Expresion::Constructor(constructor) => match &constructor.object_to_construct {
+                ConstructorType::StructConstructor(struct_) => Some(actual(&struct_.struct_name)),
+                ConstructorType::EnumConstructor(enum_) => Some(actual(&enum_.enum_name)),
+                ConstructorType::ArrayConstructor(elements) => match self.type_of(elements.first()?)? {
+                    Type::Actual(element) => Some(Type::Array(element)),
+                    _ => None,
+                },
+            },
+            Expresion::Match(match_) => match_.arms.iter().find_map(|arm| self.type_of(&arm.value)),
+            Expresion::Closure(closure) => Some(self.closure_type(closure)),
+            Expresion::FunctionCall(call) => match &*call.function {
+                Expresion::VarAccess(name) if self.local(name).is_none() => {
+                    Some(Type::from(*self.functions.get(name.lexeme.as_str())?))
+                }
+                Expresion::TypeAssocatedFunction(function) => {
+                    let type_name = match function.type_.lexeme.as_str() {
+                        "Self" => self.self_type.clone()?,
+                        type_name => type_name.to_string(),
+                    };
+                    self.method_type(type_name, &function.func_name.lexeme)
+                }
+                Expresion::FieldAcess(access) => {
+                    self.method_type(self.named(&self.type_of(&access.expr)?)?, &access.field.lexeme)
+                }
+                function => match self.type_of(function)? {
+                    Type::FP(fp) => Some(*fp.out),
+                    _ => None,
+                },
+            },
+            _ => None,
+        }
+    }
+    /// The function pointer `closure` is, what its body gives back stands in for a missing
+    /// return type.
+    fn closure_type(&self, closure: &Closure) -> Type {
+        let out = match &closure.out {
+            Some(out) => Type::from(out),
+            None => self.type_of(&closure.body).unwrap_or(Type::Unit),
+        };
+        Type::FP(FP {
+            arg: closure.parameters.iter().map(|(_, type_)| Type::from(type_)).collect(),
+            out: Box::new(out),
+        })
+    }
+    fn method_type(&self, type_name: String, method: &str) -> Option<Type> {
+        let out = self.methods.get(&(type_name.clone(), method.to_string()))?.out;
+        Some(match out {
+            ASTType::PhysicalType(solid) if solid.name.lexeme == "Self" => {
+                actual(&Token { lexeme: type_name, ..solid.name.clone() })
+            }
+            out => Type::from(out),
+        })
+    }
+}
+
+impl Visit for VisibilityChecker<'_> {
+    type Error = CompileError;
+    fn visit_statment(&mut self, statment: &Statment) -> Result<(), CompileError> {
+        let Statment::VarCreation(var_creation) = statment else {
+            return walk_statment(self, statment);
+        };
+        self.visit_statment(&var_creation.value)?;
+        let type_ = match (&var_creation.type_of, &*var_creation.value) {
+            (Some(type_), _) => Some(Type::from(type_)),
+            (None, Statment::Expresion(value)) => self.type_of(value),
+            _ => None,
+        };
+        self.pattern(&var_creation.pattern, type_)
+    }
+    fn visit_pattern(&mut self, pattern: &Pattern) -> Result<(), CompileError> {
+        self.pattern(pattern, None)
+    }
+    fn visit_matched(&mut self, pattern: &Pattern, value: &Expresion) -> Result<(), CompileError> {
+        let type_ = self.type_of(value);
+        self.pattern(pattern, type_)
+    }
+    fn visit_function(&mut self, function: &FunctionDecl) -> Result<(), CompileError> {
+        self.function(function, self.self_type.clone())
+    }
+    fn visit_expresion(&mut self, expresion: &Expresion) -> Result<(), CompileError> {
+        match expresion {
+            Expresion::FieldAcess(access) => {
+                if let Some(type_name) = self.type_of(&access.expr).and_then(|type_| self.named(&type_)) {
+                    self.field(&type_name, &access.field)?;
+                    self.method(&type_name, &access.field)?;
+                }
+            }
+            Expresion::TypeAssocatedFunction(function) => match function.type_.lexeme.as_str() {
+                "Self" => {
+                    if let Some(self_type) = self.self_type.clone() {
+                        self.method(&self_type, &function.func_name)?;
+                    }
+                }
+                type_name => self.method(type_name, &function.func_name)?,
+            },
+            Expresion::Constructor(constructor) => {
+                if let ConstructorType::StructConstructor(struct_) = &constructor.object_to_construct {
+                    for field in &struct_.fields {
+                        self.field(&struct_.struct_name.lexeme, &field.name)?;
+                    }
+                }
+            }
+            Expresion::Closure(closure) => {
+                return self.scoped(|checker| {
+                    for (name, type_) in &closure.parameters {
+                        checker.bind_as(name, Some(Type::from(type_)));
+                    }
+                    checker.visit_expresion(&closure.body)
+                })
+            }
+            _ => {}
+        }
+        walk_expresion(self, expresion)
+    }
+    fn scoped(&mut self, inside: impl FnOnce(&mut Self) -> Result<(), CompileError>) -> Result<(), CompileError> {
+        self.locals.push(HashMap::new());
+        let result = inside(self);
+        self.locals.pop();
+        result
+    }
+    fn bind(&mut self, name: &Token) {
+        self.bind_as(name, None);
+    }
+}
+
+/// The type of the struct or enum `name`, written as it is in an expression.
+fn actual(name: &Token) -> Type {
+    Type::Actual(ActualType { name: name.lexeme.clone(), types_in_generics: vec![], span: name.span })
+}
@@ -1,3 +1,10 @@
 mod ir_validator;
+pub mod ir_alias;
 pub mod ir_chunk;
-pub mod ir_type_data;
\ No newline at end of file
+pub mod ir_closure;
+pub mod ir_const;
+pub mod ir_lowering;
+pub mod ir_match_check;
+pub mod ir_pattern;
+pub mod ir_type_data;
+pub mod ir_visibility;
\ No newline at end of file
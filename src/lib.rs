@@ -1,3 +1,5 @@
 pub mod ir;
 pub mod scanner;
 pub mod ast;
+pub mod diagnostic;
+pub mod module;
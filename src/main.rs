@@ -1,6 +1,4 @@
-use std::{fmt::Debug, marker::PhantomData};
-
-use calcify::{ast::{decl::Program, parser::Parsable}, ir::ir_type_data::IR, scanner::Scanner};
+use calcify::{ast::{decl::Program, parser::TokenStream}, diagnostic::Diagnostic, ir::ir_type_data::IR, module::Crate, scanner::Scanner};
 
 // trait Bar<T>{}
 
@@ -20,14 +18,30 @@ use calcify::{ast::{decl::Program, parser::Parsable}, ir::ir_type_data::IR, scan
 
 
 fn main() {
-    let tokens = Scanner::scan(
-    r#"
+    if let Some(root) = std::env::args().nth(1) {
+        let (crate_, diagnostics) = match Crate::load(root.as_ref()) {
+            Ok(loaded) => loaded,
+            Err(err) => return eprintln!("can't read `{root}`: {err}"),
+        };
+        for diagnostic in &diagnostics {
+            eprint!("{}", crate_.render(diagnostic));
+        }
+        if diagnostics.is_empty() {
+            dbg!(&IR::try_from(&crate_));
+        }
+        return;
+    }
+    let source = r#"
     struct Foo<T<U>,U:(Foo<T> + Bar)>{}
-    "#.to_string());
+    "#;
+    let tokens = Scanner::scan(source);
     dbg!(&tokens);
-    let program = Program::parse(&mut tokens.iter().peekable());
+    let (program, errors) = Program::parse(&mut TokenStream::new(&tokens));
     dbg!(&program);
-    if let Ok(program) = &program{
-        dbg!(IR::try_from(program));
+    for err in &errors{
+        eprint!("{}", Diagnostic::from(err).render("main.cal", source));
+    }
+    if errors.is_empty(){
+        dbg!(&IR::try_from(&program));
     }
 }
\ No newline at end of file
@@ -0,0 +1,129 @@
+//! Programs spread over several files. The root file and every file a `mod name;` points to
+//! are parsed into a `Module` each, and linking the modules gives back one `Program` with
+//! every item named by its full path, `shapes::Circle` rather than `Circle`.
+//!
+//! The files of the modules a file declares sit in the directory beside it, `mod shapes;`
+//! in `src/main.cal` is `src/shapes.cal` or else `src/shapes/mod.cal`. A module's own
+//! modules go in a directory named after it, so `mod circle;` in either of those files is
+//! `src/shapes/circle.cal` or `src/shapes/circle/mod.cal`.
+
+mod resolve;
+
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use pub_fields::pub_fields;
+
+use crate::{
+    ast::{
+        decl::{Declaration, Program},
+        parser::TokenStream,
+    },
+    diagnostic::Diagnostic,
+    scanner::Scanner,
+};
+
+/// The extension every source file has.
+pub const EXTENSION: &str = "cal";
+
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct SourceFile {
+    path: PathBuf,
+    source: String,
+}
+
+/// The items of one file. `path` is the modules from the root down to it, empty for the root.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct Module {
+    path: Vec<String>,
+    file_id: usize,
+    program: Program,
+}
+
+/// Every file of a program, the `file_id` of a span is its index in `files`.
+#[derive(Debug, Clone)]
+#[pub_fields]
+pub struct Crate {
+    files: Vec<SourceFile>,
+    modules: Vec<Module>,
+}
+
+impl Crate {
+    /// Loads the program whose root file is at `root`. Everything that goes wrong in the files
+    /// is reported rather than stopping the load, only the root not being readable does.
+    pub fn load(root: &Path) -> io::Result<(Self, Vec<Diagnostic>)> {
+        Self::load_with(root, |path| fs::read(path))
+    }
+    /// `load`, with the files read by `read` instead of from disk. A module's file that `read`
+    /// says isn't found is looked for at the next place it could be, any other error is
+    /// reported.
+    pub fn load_with(root: &Path, mut read: impl FnMut(&Path) -> io::Result<Vec<u8>>) -> io::Result<(Self, Vec<Diagnostic>)> {
+        let source = read(root)?;
+        let dir = root.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut crate_ = Self { files: vec![], modules: vec![] };
+        let mut diagnostics = vec![];
+        // each module with the directory its own modules are in
+        let mut pending = VecDeque::from([(vec![], root.to_path_buf(), source, dir)]);
+        while let Some((path, file, source, dir)) = pending.pop_front() {
+            let file_id = crate_.files.len();
+            let (tokens, lex_errors) = Scanner::scan_bytes(&source, file_id);
+            diagnostics.extend(lex_errors.iter().map(Diagnostic::from));
+            let (program, errors) = Program::parse(&mut TokenStream::new(&tokens));
+            diagnostics.extend(errors.iter().map(Diagnostic::from));
+            for declaration in &program.code {
+                let Declaration::ModDecl(mod_) = declaration else {
+                    continue;
+                };
+                let name = &mod_.name.lexeme;
+                let candidates = [
+                    dir.join(format!("{name}.{EXTENSION}")),
+                    dir.join(name).join(format!("mod.{EXTENSION}")),
+                ];
+                let found = candidates.iter().find_map(|candidate| match read(candidate) {
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                    result => Some((candidate.clone(), result)),
+                });
+                match found {
+                    Some((file, Ok(source))) => {
+                        let module_path = [path.clone(), vec![name.clone()]].concat();
+                        pending.push_back((module_path, file, source, dir.join(name)));
+                    }
+                    Some((file, Err(err))) => diagnostics.push(
+                        Diagnostic::new(format!("can't read the file for module `{name}`"), mod_.name.span)
+                            .with_note(format!("reading `{}` failed: {err}", file.display())),
+                    ),
+                    None => diagnostics.push(
+                        Diagnostic::new(format!("can't find the file for module `{name}`"), mod_.name.span)
+                            .with_note(format!(
+                                "looked for `{}` and `{}`",
+                                candidates[0].display(),
+                                candidates[1].display()
+                            )),
+                    ),
+                }
+            }
+            crate_.files.push(SourceFile { path: file, source: text(&source) });
+            crate_.modules.push(Module { path, file_id, program });
+        }
+        Ok((crate_, diagnostics))
+    }
+    /// Renders `diagnostic` against the file its span points into.
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let file = &self.files[diagnostic.span.file_id];
+        diagnostic.render(&file.path.display().to_string(), &file.source)
+    }
+}
+
+/// `bytes` as the text `Scanner::scan_bytes` scans, a space for each byte that isn't UTF-8 so
+/// spans line up with the bytes.
+fn text(bytes: &[u8]) -> String {
+    bytes
+        .utf8_chunks()
+        .map(|chunk| format!("{}{}", chunk.valid(), " ".repeat(chunk.invalid().len())))
+        .collect()
+}
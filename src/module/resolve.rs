@@ -0,0 +1,472 @@
+//! Linking: every name an item is declared or used by is swapped for its full path, so the
+//! items of all the modules fit in one `Program` without clashing.
+//!
+//! A name on its own is looked up in the module it is used in, among the items declared
+//! there and the ones a `use` brought in. A path is looked up from there too, and from the
+//! root when that finds nothing, so `shapes::Circle` works anywhere, and the root's own
+//! items are `crate::Circle` from inside a module. Modules, structs, enums, traits
+//! and type aliases share one namespace and functions and constants have another, so a function
+//! can be named after a type. Names that find nothing are left as they are, the IR reports the ones that
+//! aren't generics or built in.
+
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::{
+    ast::{
+        decl::{ConstDecl, Declaration, FuncSig, FunctionDecl, GenericDecl, Program, SolidType, TraitType, Type, UseDecl, Visibility},
+        expresions::{ConstructorType, Expresion, StructConstructor},
+        visit::{walk_expresion_mut, VisitMut},
+    },
+    ir::ir_type_data::CompileError,
+    scanner::{Span, Token},
+};
+
+use super::{Crate, Module};
+
+impl Crate {
+    /// Puts the items of every module in one `Program`, each named by its full path.
+    pub fn link(&self) -> Result<Program, CompileError> {
+        let mut names = Names::declare(&self.modules)?;
+        names.import(&self.modules)?;
+        let mut code = vec![];
+        for module in &self.modules {
+            let mut resolver = Resolver { names: &names, module: &module.path, generics: vec![], locals: vec![] };
+            for declaration in &module.program.code {
+                let mut declaration = declaration.clone();
+                if resolver.declaration(&mut declaration)? {
+                    code.push(declaration);
+                }
+            }
+        }
+        Ok(Program {
+            docs: self.modules.first().map(|root| root.program.docs.clone()).unwrap_or_default(),
+            code,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Module,
+    Type,
+    /// A function or a constant.
+    Value,
+}
+
+/// What a name stands for, the full path of that and who can see it.
+#[derive(Debug, Clone)]
+struct Item {
+    kind: Kind,
+    path: Vec<String>,
+    visibility: Visibility,
+    span: Span,
+}
+
+impl Item {
+    /// Checks that code in the module at `from` can see the item, `used` is where it does.
+    fn visible_from(&self, from: &[String], used: Span) -> Result<(), CompileError> {
+        let home = &self.path[..self.path.len() - 1];
+        if self.visibility.allows(home, from) {
+            Ok(())
+        } else {
+            Err(CompileError::PrivateItem(self.path.join("::"), used, self.span))
+        }
+    }
+}
+
+/// The names a module can use on their own.
+#[derive(Debug, Default)]
+struct Scope {
+    types: HashMap<String, Item>,
+    values: HashMap<String, Item>,
+}
+
+impl Scope {
+    fn namespace(&mut self, kind: Kind) -> &mut HashMap<String, Item> {
+        match kind {
+            Kind::Module | Kind::Type => &mut self.types,
+            Kind::Value => &mut self.values,
+        }
+    }
+}
+
+/// The scope of every module, by its path.
+struct Names {
+    scopes: HashMap<Vec<String>, Scope>,
+}
+
+impl Names {
+    fn declare(modules: &[Module]) -> Result<Self, CompileError> {
+        let mut scopes = HashMap::new();
+        for module in modules {
+            let mut scope = Scope::default();
+            let mut duplicates = vec![];
+            for declaration in &module.program.code {
+                let (kind, name, visibility) = match declaration {
+                    Declaration::StructDecl(struct_) => (Kind::Type, &struct_.name, struct_.visibility),
+                    Declaration::EnumDecl(enum_) => (Kind::Type, &enum_.name, enum_.visibility),
+                    Declaration::TraitDecl(trait_) => (Kind::Type, &trait_.name, trait_.visibility),
+                    Declaration::TypeAliasDecl(alias) => (Kind::Type, &alias.name, alias.visibility),
+                    // a module can be named from anywhere, whether what's in it can is up to that
+                    Declaration::ModDecl(mod_) => (Kind::Module, &mod_.name, Visibility::Public),
+                    Declaration::FunctionDecl(function) => (Kind::Value, &function.sig.name, function.visibility),
+                    Declaration::ConstDecl(const_) => (Kind::Value, &const_.name, const_.visibility),
+                    Declaration::StaticDecl(static_) => (Kind::Value, &static_.name, static_.visibility),
+                    Declaration::ImplDecl(_) | Declaration::UseDecl(_) => continue,
+                };
+                let item = Item {
+                    kind,
+                    path: full_path(&module.path, &name.lexeme),
+                    visibility,
+                    span: name.span,
+                };
+                if scope.namespace(kind).insert(name.lexeme.clone(), item).is_some() {
+                    duplicates.push(name.lexeme.clone());
+                }
+            }
+            if !duplicates.is_empty() {
+                return Err(CompileError::DuplicateNames(module.path.join("::"), duplicates));
+            }
+            scopes.insert(module.path.clone(), scope);
+        }
+        Ok(Self { scopes })
+    }
+    /// Adds what every `use` brings in. An import can go through another, so they are
+    /// retried for as long as that finds something new.
+    fn import(&mut self, modules: &[Module]) -> Result<(), CompileError> {
+        let mut pending = modules
+            .iter()
+            .flat_map(|module| {
+                module.program.code.iter().filter_map(move |declaration| match declaration {
+                    Declaration::UseDecl(use_) => Some((&module.path, use_)),
+                    _ => None,
+                })
+            })
+            .collect::<Vec<(&Vec<String>, &UseDecl)>>();
+        while !pending.is_empty() {
+            let before = pending.len();
+            let mut unresolved = vec![];
+            for (module, use_) in pending {
+                let segments = use_.path.iter().map(|token| token.lexeme.clone()).collect_vec();
+                let (type_, value) = self.lookup(module, &segments);
+                if type_.is_none() && value.is_none() {
+                    unresolved.push((module, use_));
+                    continue;
+                }
+                let name = segments.last().expect("a `use` has a name").clone();
+                let scope = self.scopes.get_mut(module).expect("every module has a scope");
+                for item in type_.into_iter().chain(value) {
+                    item.visible_from(module, path_span(&use_.path))?;
+                    if scope.namespace(item.kind).insert(name.clone(), item).is_some() {
+                        return Err(CompileError::DuplicateNames(module.join("::"), vec![name]));
+                    }
+                }
+            }
+            if unresolved.len() == before {
+                let (_, use_) = unresolved[0];
+                return Err(CompileError::UnresolvedPath(
+                    use_.path.iter().map(|token| &token.lexeme).join("::"),
+                    path_span(&use_.path),
+                ));
+            }
+            pending = unresolved;
+        }
+        Ok(())
+    }
+    /// What `segments` names from inside `module`, in each namespace. A path of more than
+    /// one name is tried from the root when the module itself has nothing for it, and one
+    /// starting with `crate` is only tried from there.
+    fn lookup(&self, module: &[String], segments: &[String]) -> (Option<Item>, Option<Item>) {
+        if let [first, rest @ ..] = segments {
+            if first == "crate" && !rest.is_empty() {
+                return self.lookup_from(&[], rest);
+            }
+        }
+        let found = self.lookup_from(module, segments);
+        if found.0.is_none() && found.1.is_none() && segments.len() > 1 {
+            return self.lookup_from(&[], segments);
+        }
+        found
+    }
+    fn lookup_from(&self, module: &[String], segments: &[String]) -> (Option<Item>, Option<Item>) {
+        let Some((name, modules)) = segments.split_last() else {
+            return (None, None);
+        };
+        let mut module = module.to_vec();
+        for segment in modules {
+            match self.scopes.get(&module).and_then(|scope| scope.types.get(segment)) {
+                Some(Item { kind: Kind::Module, path, .. }) => module = path.clone(),
+                _ => return (None, None),
+            }
+        }
+        match self.scopes.get(&module) {
+            Some(scope) => (scope.types.get(name).cloned(), scope.values.get(name).cloned()),
+            None => (None, None),
+        }
+    }
+}
+
+struct Resolver<'a> {
+    names: &'a Names,
+    module: &'a [String],
+    generics: Vec<HashSet<String>>,
+    locals: Vec<HashSet<String>>,
+}
+
+impl Resolver<'_> {
+    /// Resolves the names in `declaration`, gives back whether it goes in the linked program.
+    fn declaration(&mut self, declaration: &mut Declaration) -> Result<bool, CompileError> {
+        match declaration {
+            Declaration::StructDecl(struct_) => {
+                self.rename(&mut struct_.name);
+                self.with_generics(&mut struct_.generics, |resolver| {
+                    struct_.fields.iter_mut().try_for_each(|field| resolver.type_(&mut field.type_of))
+                })?;
+            }
+            Declaration::EnumDecl(enum_) => {
+                self.rename(&mut enum_.name);
+                self.with_generics(&mut enum_.generics, |resolver| {
+                    enum_
+                        .varients
+                        .iter_mut()
+                        .flat_map(|varient| &mut varient.fields)
+                        .try_for_each(|field| resolver.type_(&mut field.type_of))
+                })?;
+            }
+            Declaration::TraitDecl(trait_) => {
+                self.rename(&mut trait_.name);
+                self.with_generics(&mut trait_.generics, |resolver| {
+                    trait_.funcs.iter_mut().try_for_each(|sig| resolver.sig(sig, |_| Ok(())))
+                })?;
+            }
+            Declaration::ImplDecl(impl_) => {
+                self.with_generics(&mut impl_.generics, |resolver| {
+                    if let Some(trait_) = &mut impl_.trait_to_impl {
+                        resolver.type_(trait_)?;
+                    }
+                    resolver.type_(&mut impl_.type_to_impl_on)?;
+                    impl_.consts.iter_mut().try_for_each(|const_| resolver.const_(const_))?;
+                    impl_.funcs.iter_mut().try_for_each(|function| resolver.function(function))
+                })?;
+            }
+            Declaration::ConstDecl(const_) => {
+                self.rename(&mut const_.name);
+                self.const_(const_)?;
+            }
+            Declaration::StaticDecl(static_) => {
+                self.rename(&mut static_.name);
+                self.type_(&mut static_.type_of)?;
+                self.scoped(|resolver| resolver.visit_expresion(&mut static_.value))?;
+            }
+            Declaration::TypeAliasDecl(alias) => {
+                self.rename(&mut alias.name);
+                self.with_generics(&mut alias.generics, |resolver| resolver.type_(&mut alias.type_of))?;
+            }
+            Declaration::FunctionDecl(function) => {
+                self.rename(&mut function.sig.name);
+                self.function(function)?;
+            }
+            Declaration::ModDecl(_) | Declaration::UseDecl(_) => return Ok(false),
+        }
+        Ok(true)
+    }
+    /// Gives an item declared in this module its full path.
+    fn rename(&self, name: &mut Token) {
+        name.lexeme = full_path(self.module, &name.lexeme).join("::");
+    }
+    fn with_generics<T>(
+        &mut self,
+        generics: &mut [GenericDecl],
+        inside: impl FnOnce(&mut Self) -> Result<T, CompileError>,
+    ) -> Result<T, CompileError> {
+        self.generics.push(generics.iter().map(|generic| generic.name.lexeme.clone()).collect());
+        let result = generics
+            .iter_mut()
+            .flat_map(|generic| &mut generic.constraints)
+            .try_for_each(|constraint| self.trait_type(constraint))
+            .and_then(|_| inside(self));
+        self.generics.pop();
+        result
+    }
+    /// Resolves the types in `sig`, then whatever `body` does with its generics in scope.
+    fn sig(
+        &mut self,
+        sig: &mut FuncSig,
+        body: impl FnOnce(&mut Self) -> Result<(), CompileError>,
+    ) -> Result<(), CompileError> {
+        self.with_generics(&mut sig.generics, |resolver| {
+            sig.parameters.iter_mut().try_for_each(|(_, type_)| resolver.type_(type_))?;
+            resolver.type_(&mut sig.out)?;
+            body(resolver)
+        })
+    }
+    fn const_(&mut self, const_: &mut ConstDecl) -> Result<(), CompileError> {
+        self.type_(&mut const_.type_of)?;
+        self.scoped(|resolver| resolver.visit_expresion(&mut const_.value))
+    }
+    fn function(&mut self, function: &mut FunctionDecl) -> Result<(), CompileError> {
+        let parameters = function.sig.parameters.iter().map(|(name, _)| name.clone()).collect_vec();
+        let body = &mut function.body;
+        self.sig(&mut function.sig, |resolver| {
+            resolver.scoped(|resolver| {
+                parameters.iter().for_each(|name| resolver.bind(name));
+                body.statments.iter_mut().try_for_each(|statment| resolver.visit_statment(statment))
+            })
+        })
+    }
+    fn type_(&mut self, type_: &mut Type) -> Result<(), CompileError> {
+        match type_ {
+            Type::PhysicalType(solid) | Type::Array(solid, _) => self.solid_type(solid),
+            Type::Unit(_) => Ok(()),
+            Type::FP(fp) => {
+                fp.args.iter_mut().try_for_each(|arg| self.type_(arg))?;
+                self.type_(&mut fp.out)
+            }
+            Type::DynamicType(traits, _) => traits.iter_mut().try_for_each(|trait_| self.trait_type(trait_)),
+            Type::Tuple(types, _) => types.iter_mut().try_for_each(|type_| self.type_(type_)),
+        }
+    }
+    fn solid_type(&mut self, solid: &mut SolidType) -> Result<(), CompileError> {
+        solid.generics.iter_mut().try_for_each(|generic| self.type_(generic))?;
+        self.type_name(&mut solid.path, &mut solid.name)
+    }
+    fn trait_type(&mut self, trait_: &mut TraitType) -> Result<(), CompileError> {
+        trait_.generics.iter_mut().try_for_each(|generic| self.type_(generic))?;
+        self.type_name(&mut trait_.path, &mut trait_.name)
+    }
+    /// Swaps the type at `path::name` for its full path, leaving `Self` and generics alone.
+    fn type_name(&self, path: &mut Vec<Token>, name: &mut Token) -> Result<(), CompileError> {
+        if path.is_empty() && (name.lexeme == "Self" || self.generics.iter().any(|generics| generics.contains(&name.lexeme))) {
+            return Ok(());
+        }
+        let segments = segments(path, name);
+        match self.names.lookup(self.module, &segments).0 {
+            Some(item @ Item { kind: Kind::Type, .. }) => {
+                item.visible_from(self.module, path.first().map_or(name.span, |first| first.span.to(name.span)))?;
+                name.lexeme = item.path.join("::");
+                path.clear();
+                Ok(())
+            }
+            _ => self.not_found(path, name, Kind::Type),
+        }
+    }
+    /// What to do about `path::name` not naming anything: a lone name is left for the IR
+    /// unless it names something in the root that wasn't brought into this module.
+    fn not_found(&self, path: &[Token], name: &Token, kind: Kind) -> Result<(), CompileError> {
+        let segments = segments(path, name);
+        let (type_, value) = self.names.lookup_from(&[], &segments);
+        let in_root = match kind {
+            Kind::Value => value.is_some(),
+            _ => type_.is_some(),
+        };
+        if path.is_empty() && (!in_root || self.module.is_empty()) {
+            return Ok(());
+        }
+        let span = path.first().map_or(name.span, |first| first.span.to(name.span));
+        Err(CompileError::UnresolvedPath(segments.join("::"), span))
+    }
+    fn is_local(&self, name: &Token) -> bool {
+        self.locals.iter().any(|locals| locals.contains(&name.lexeme))
+    }
+}
+
+impl VisitMut for Resolver<'_> {
+    type Error = CompileError;
+    fn visit_expresion(&mut self, expresion: &mut Expresion) -> Result<(), CompileError> {
+        match expresion {
+            Expresion::VarAccess(name) => {
+                if self.is_local(name) {
+                    return Ok(());
+                }
+                match self.names.lookup(self.module, std::slice::from_ref(&name.lexeme)).1 {
+                    Some(function) => {
+                        function.visible_from(self.module, name.span)?;
+                        name.lexeme = function.path.join("::");
+                    }
+                    None => self.not_found(&[], name, Kind::Value)?,
+                }
+            }
+            Expresion::TypeAssocatedFunction(function) => {
+                let segments = segments(&function.path, &function.type_);
+                match self.names.lookup(self.module, &segments).0 {
+                    // `module::function` rather than `Type::function`
+                    Some(Item { kind: Kind::Module, .. }) => {
+                        let full = [segments, vec![function.func_name.lexeme.clone()]].concat();
+                        let Some(found) = self.names.lookup(self.module, &full).1 else {
+                            return Err(CompileError::UnresolvedPath(full.join("::"), function.span));
+                        };
+                        found.visible_from(self.module, function.span)?;
+                        let name = Token {
+                            lexeme: found.path.join("::"),
+                            span: function.span,
+                            ..function.func_name.clone()
+                        };
+                        *expresion = Expresion::VarAccess(name);
+                    }
+                    _ => self.type_name(&mut function.path, &mut function.type_)?,
+                }
+            }
+            Expresion::Constructor(constructor) => {
+                let object = &mut constructor.object_to_construct;
+                match object {
+                    ConstructorType::StructConstructor(struct_) => self.type_name(&mut vec![], &mut struct_.struct_name)?,
+                    ConstructorType::EnumConstructor(enum_) => {
+                        let segments = segments(&enum_.path, &enum_.enum_name);
+                        match self.names.lookup(self.module, &segments).0 {
+                            // `module::Struct{..}` rather than `Enum::Varient{..}`
+                            Some(Item { kind: Kind::Module, .. }) => {
+                                let mut path = [enum_.path.clone(), vec![enum_.enum_name.clone()]].concat();
+                                let mut struct_name = enum_.varient_name.clone();
+                                self.type_name(&mut path, &mut struct_name)?;
+                                *object = ConstructorType::StructConstructor(StructConstructor {
+                                    struct_name,
+                                    fields: std::mem::take(&mut enum_.fields),
+                                    span: enum_.span,
+                                });
+                            }
+                            _ => self.type_name(&mut enum_.path, &mut enum_.enum_name)?,
+                        }
+                    }
+                    ConstructorType::ArrayConstructor(_) => {}
+                }
+            }
+            _ => {}
+        }
+        walk_expresion_mut(self, expresion)
+    }
+    fn visit_function(&mut self, function: &mut FunctionDecl) -> Result<(), CompileError> {
+        self.function(function)
+    }
+    fn visit_type(&mut self, type_: &mut Type) -> Result<(), CompileError> {
+        self.type_(type_)
+    }
+    fn visit_trait(&mut self, trait_: &mut SolidType) -> Result<(), CompileError> {
+        self.solid_type(trait_)
+    }
+    fn scoped(&mut self, inside: impl FnOnce(&mut Self) -> Result<(), CompileError>) -> Result<(), CompileError> {
+        self.locals.push(HashSet::new());
+        let result = inside(self);
+        self.locals.pop();
+        result
+    }
+    fn bind(&mut self, name: &Token) {
+        self.locals
+            .last_mut()
+            .expect("names are only bound inside a scope")
+            .insert(name.lexeme.clone());
+    }
+}
+
+fn full_path(module: &[String], name: &str) -> Vec<String> {
+    [module, &[name.to_string()]].concat()
+}
+
+fn segments(path: &[Token], name: &Token) -> Vec<String> {
+    path.iter().chain([name]).map(|token| token.lexeme.clone()).collect()
+}
+
+fn path_span(path: &[Token]) -> Span {
+    path[0].span.to(path[path.len() - 1].span)
+}
@@ -1,3 +1,7 @@
+use std::{fmt::Display, ops::Range, str::FromStr};
+
+use strum_macros::{Display, EnumString};
+
 macro_rules! either {
     ($test:expr => $true_expr:expr; $false_expr:expr) => {
         if $test {
@@ -38,15 +42,25 @@ pub enum TokenType {
     LessOrEqual,
     MoreOrEqual,
     SmallArrow,
+    FatArrow,
     EqualEqual,
     BangEqual,
     PlusEqual,
     MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    AmpersandEqual,
+    PipeEqual,
     DoubleColon,
     SHL,
+    SHLEqual,
+    DotDot,
+    DotDotEqual,
     //idents
     Ident,
     String,
+    Char,
     Int,
     Float,
     //Keywords
@@ -70,25 +84,551 @@ pub enum TokenType {
     Continue,
     Break,
     Panic,
+    In,
+    Match,
+    Use,
+    Mod,
+    Pub,
+    Const,
+    Static,
+    Type,
+    //reserved keywords
+    Loop,
+    As,
+    Where,
     //other stuff
     Error,
     EOF,
     Comment,
+    Whitespace, // only produced by `Scanner::scan_lossless`
+    DocComment,      // `///`, documents the item after it
+    InnerDocComment, // `//!`, documents the file it is in
+}
+
+impl TokenType {
+    /// How the token is referred to in an error message, "`;`" or "a name".
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TokenType::Plus => "`+`",
+            TokenType::Minus => "`-`",
+            TokenType::Slash => "`/`",
+            TokenType::Comma => "`,`",
+            TokenType::SemiColon => "`;`",
+            TokenType::Colon => "`:`",
+            TokenType::Dot => "`.`",
+            TokenType::Bang => "`!`",
+            TokenType::At => "`@`",
+            TokenType::Equal => "`=`",
+            TokenType::Pipe => "`|`",
+            TokenType::Star => "`*`",
+            TokenType::Percent => "`%`",
+            TokenType::Ampersand => "`&`",
+            TokenType::LBrace => "`{`",
+            TokenType::RBrace => "`}`",
+            TokenType::LParen => "`(`",
+            TokenType::RParen => "`)`",
+            TokenType::LBrack => "`[`",
+            TokenType::RBrack => "`]`",
+            TokenType::LArrow => "`<`",
+            TokenType::RArrow => "`>`",
+            TokenType::LessOrEqual => "`<=`",
+            TokenType::MoreOrEqual => "`>=`",
+            TokenType::SmallArrow => "`->`",
+            TokenType::FatArrow => "`=>`",
+            TokenType::EqualEqual => "`==`",
+            TokenType::BangEqual => "`!=`",
+            TokenType::PlusEqual => "`+=`",
+            TokenType::MinusEqual => "`-=`",
+            TokenType::StarEqual => "`*=`",
+            TokenType::SlashEqual => "`/=`",
+            TokenType::PercentEqual => "`%=`",
+            TokenType::AmpersandEqual => "`&=`",
+            TokenType::PipeEqual => "`|=`",
+            TokenType::DoubleColon => "`::`",
+            TokenType::SHL => "`<<`",
+            TokenType::SHLEqual => "`<<=`",
+            TokenType::DotDot => "`..`",
+            TokenType::DotDotEqual => "`..=`",
+            TokenType::Ident => "a name",
+            TokenType::String => "a string",
+            TokenType::Char => "a character",
+            TokenType::Int => "an integer",
+            TokenType::Float => "a float",
+            TokenType::Fn => "`fn`",
+            TokenType::Struct => "`struct`",
+            TokenType::Enum => "`enum`",
+            TokenType::While => "`while`",
+            TokenType::Let => "`let`",
+            TokenType::Return => "`return`",
+            TokenType::Self_ => "`Self`",
+            TokenType::If => "`if`",
+            TokenType::Impl => "`impl`",
+            TokenType::Else => "`else`",
+            TokenType::True => "`true`",
+            TokenType::False => "`false`",
+            TokenType::Trait => "`trait`",
+            TokenType::And => "`and`",
+            TokenType::Or => "`or`",
+            TokenType::Mut => "`mut`",
+            TokenType::For => "`for`",
+            TokenType::Continue => "`continue`",
+            TokenType::Break => "`break`",
+            TokenType::Panic => "`panic`",
+            TokenType::Match => "`match`",
+            TokenType::Loop => "`loop`",
+            TokenType::Const => "`const`",
+            TokenType::Static => "`static`",
+            TokenType::Use => "`use`",
+            TokenType::Mod => "`mod`",
+            TokenType::Pub => "`pub`",
+            TokenType::As => "`as`",
+            TokenType::In => "`in`",
+            TokenType::Type => "`type`",
+            TokenType::Where => "`where`",
+            TokenType::Error => "an unknown character",
+            TokenType::EOF => "the end of the file",
+            TokenType::Comment => "a comment",
+            TokenType::Whitespace => "whitespace",
+            TokenType::DocComment | TokenType::InnerDocComment => "a doc comment",
+        }
+    }
+}
+
+/// A byte range into one source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub file_id: usize,
+    pub byte_start: usize,
+    pub byte_end: usize,
+}
+
+impl Span {
+    pub fn new(file_id: usize, byte_start: usize, byte_end: usize) -> Self {
+        Self {
+            file_id,
+            byte_start,
+            byte_end,
+        }
+    }
+    /// An empty span sitting right before this one.
+    pub fn start_point(&self) -> Self {
+        Self::new(self.file_id, self.byte_start, self.byte_start)
+    }
+    /// An empty span sitting right after this one.
+    pub fn end_point(&self) -> Self {
+        Self::new(self.file_id, self.byte_end, self.byte_end)
+    }
+    /// The span covering everything from the start of `self` to the end of `end`.
+    pub fn to(&self, end: Span) -> Self {
+        Self::new(
+            self.file_id,
+            self.byte_start,
+            end.byte_end.max(self.byte_start),
+        )
+    }
+    pub fn len(&self) -> usize {
+        self.byte_end - self.byte_start
+    }
+    pub fn is_empty(&self) -> bool {
+        self.byte_end == self.byte_start
+    }
+}
+
+/// Every word the scanner turns into its own token instead of an `Ident`.
+///
+/// The reserved ones have no meaning yet but can't be used as names, so giving
+/// them one later won't break anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Keyword {
+    Fn,
+    Struct,
+    Enum,
+    While,
+    Let,
+    Return,
+    #[strum(serialize = "Self")]
+    SelfType,
+    If,
+    Impl,
+    Else,
+    True,
+    False,
+    Trait,
+    And,
+    Or,
+    Mut,
+    For,
+    Continue,
+    Break,
+    Panic,
+    In,
+    Match,
+    Use,
+    Mod,
+    Pub,
+    Const,
+    Static,
+    Type,
+    //reserved
+    Loop,
+    As,
+    Where,
+}
+
+impl Keyword {
+    pub fn token_type(self) -> TokenType {
+        match self {
+            Keyword::Fn => TokenType::Fn,
+            Keyword::Struct => TokenType::Struct,
+            Keyword::Enum => TokenType::Enum,
+            Keyword::While => TokenType::While,
+            Keyword::Let => TokenType::Let,
+            Keyword::Return => TokenType::Return,
+            Keyword::SelfType => TokenType::Self_,
+            Keyword::If => TokenType::If,
+            Keyword::Impl => TokenType::Impl,
+            Keyword::Else => TokenType::Else,
+            Keyword::True => TokenType::True,
+            Keyword::False => TokenType::False,
+            Keyword::Trait => TokenType::Trait,
+            Keyword::And => TokenType::And,
+            Keyword::Or => TokenType::Or,
+            Keyword::Mut => TokenType::Mut,
+            Keyword::For => TokenType::For,
+            Keyword::Continue => TokenType::Continue,
+            Keyword::Break => TokenType::Break,
+            Keyword::Panic => TokenType::Panic,
+            Keyword::Match => TokenType::Match,
+            Keyword::Loop => TokenType::Loop,
+            Keyword::Const => TokenType::Const,
+            Keyword::Static => TokenType::Static,
+            Keyword::Use => TokenType::Use,
+            Keyword::Mod => TokenType::Mod,
+            Keyword::Pub => TokenType::Pub,
+            Keyword::As => TokenType::As,
+            Keyword::In => TokenType::In,
+            Keyword::Type => TokenType::Type,
+            Keyword::Where => TokenType::Where,
+        }
+    }
+}
+
+/// Words that only mean something in one spot, like `module` in `pub(module)`.
+/// They are scanned as an `Ident` so they stay usable as names everywhere else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ContextualKeyword {
+    Module,
+    New,
+}
+
+/// The type suffix on an integer literal, as in `10u8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum IntSuffix {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+}
+
+impl IntSuffix {
+    pub fn bits(&self) -> u32 {
+        match self {
+            IntSuffix::U8 | IntSuffix::I8 => 8,
+            IntSuffix::U16 | IntSuffix::I16 => 16,
+            IntSuffix::U32 | IntSuffix::I32 => 32,
+            IntSuffix::U64 | IntSuffix::I64 => 64,
+        }
+    }
+    pub fn is_signed(&self) -> bool {
+        matches!(self, IntSuffix::I8 | IntSuffix::I16 | IntSuffix::I32 | IntSuffix::I64)
+    }
+    /// The largest magnitude a literal of this type can have, negative literals of signed
+    /// types get one more than positive ones.
+    pub fn max_magnitude(&self, negative: bool) -> u64 {
+        match (self.is_signed(), negative) {
+            (false, _) => u64::MAX >> (64 - self.bits()),
+            (true, false) => u64::MAX >> (65 - self.bits()),
+            (true, true) => 1 << (self.bits() - 1),
+        }
+    }
+}
+
+/// The type suffix on a float literal, as in `3.0f32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum FloatSuffix {
+    F32,
+    F64,
+}
+
+/// What the scanner worked out about a token beyond its raw text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenValue {
+    Str(String), //String with its escapes decoded
+    Char(char),
+    Int(u64, Option<IntSuffix>),
+    Float(f64, Option<FloatSuffix>),
 }
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub line: usize,
-    pub line_offset:usize,
+    pub span: Span,
     pub lexeme: String,
+    pub value: Option<TokenValue>,
+}
+
+impl Token {
+    /// The text of a `///` or `//!` comment without its marker or the first space after it.
+    pub fn doc_text(&self) -> Option<&str> {
+        match self.token_type {
+            TokenType::DocComment | TokenType::InnerDocComment => {
+                let text = &self.lexeme[3..];
+                Some(text.strip_prefix(' ').unwrap_or(text))
+            }
+            _ => None,
+        }
+    }
+    /// Whether this is an `Ident` spelling `keyword`.
+    pub fn is_contextual(&self, keyword: ContextualKeyword) -> bool {
+        self.token_type == TokenType::Ident && ContextualKeyword::from_str(&self.lexeme) == Ok(keyword)
+    }
+    /// The keyword this token was scanned from, if it was one.
+    pub fn keyword(&self) -> Option<Keyword> {
+        Keyword::from_str(&self.lexeme)
+            .ok()
+            .filter(|keyword| keyword.token_type() == self.token_type)
+    }
+    /// Whitespace, plain comments and characters the scanner could not make sense of,
+    /// none of which mean anything to the parser.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.token_type, TokenType::Whitespace | TokenType::Comment | TokenType::Error)
+    }
+}
+
+/// Something wrong with the text itself, found while scanning.
+///
+/// The scanner always recovers, a bad string or number still becomes a `String` or `Int`
+/// token and a stray character is dropped, so the parser only sees the errors it would
+/// have seen had the mistake been fixed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexError {
+    UnexpectedCharacter { found: char, span: Span },
+    UnterminatedString { closing: String, span: Span }, //`closing` is what was missing
+    UnterminatedComment { span: Span },
+    InvalidEscape { escape: String, span: Span },
+    MalformedChar { text: String, span: Span },
+    MalformedNumber { problem: NumberProblem, span: Span },
+    InvalidUtf8 { span: Span },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumberProblem {
+    InvalidDigit { digit: char, radix: u32 },
+    MissingDigits { radix: u32 },
+    UnknownSuffix(String),
+    IntSuffixOnFloat(IntSuffix),
+    FloatInBase(u32),
+    OutOfRange(String), //the type it did not fit in
 }
 
-pub struct Scanner {
-    source_code: String,
+/// A change that would fix a `LexError`, worded so it can follow "help: ".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub message: String,
+    pub edit: TextEdit,
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnexpectedCharacter { span, .. }
+            | LexError::UnterminatedString { span, .. }
+            | LexError::UnterminatedComment { span }
+            | LexError::InvalidEscape { span, .. }
+            | LexError::MalformedChar { span, .. }
+            | LexError::MalformedNumber { span, .. }
+            | LexError::InvalidUtf8 { span } => *span,
+        }
+    }
+    pub fn suggestion(&self) -> Option<Suggestion> {
+        let span = self.span();
+        let suggest = |message: String, range: Range<usize>, replacement: &str| {
+            Some(Suggestion {
+                message,
+                edit: TextEdit::new(range, replacement),
+            })
+        };
+        match self {
+            LexError::UnexpectedCharacter { found, .. } => match lookalike(*found) {
+                Some(ascii) => suggest(
+                    format!("`{found}` looks like `{ascii}`, use that instead"),
+                    span.byte_start..span.byte_end,
+                    &ascii.to_string(),
+                ),
+                None => suggest("remove it".to_string(), span.byte_start..span.byte_end, ""),
+            },
+            LexError::UnterminatedString { closing, .. } => suggest(
+                format!("add a closing `{closing}`"),
+                span.byte_end..span.byte_end,
+                closing,
+            ),
+            LexError::UnterminatedComment { .. } => {
+                suggest("add a closing `*/`".to_string(), span.byte_end..span.byte_end, "*/")
+            }
+            LexError::InvalidEscape { escape, .. } if escape.len() == 2 => suggest(
+                format!("to write a `\\` followed by `{}` escape the backslash", &escape[1..]),
+                span.byte_start..span.byte_start,
+                "\\",
+            ),
+            LexError::InvalidEscape { .. } => None,
+            LexError::MalformedChar { text, .. } => {
+                let inner = text.strip_prefix('\'').unwrap_or(text);
+                let inner = inner.strip_suffix('\'').unwrap_or(inner);
+                (!inner.is_empty()).then(|| Suggestion {
+                    message: "use a string instead".to_string(),
+                    edit: TextEdit::new(span.byte_start..span.byte_end, format!("\"{inner}\"")),
+                })
+            }
+            LexError::MalformedNumber {
+                problem: NumberProblem::IntSuffixOnFloat(suffix),
+                ..
+            } => {
+                let suffix_len = suffix.to_string().len();
+                suggest(
+                    "use a float suffix".to_string(),
+                    span.byte_end - suffix_len..span.byte_end,
+                    "f64",
+                )
+            }
+            LexError::MalformedNumber { .. } => None,
+            LexError::InvalidUtf8 { .. } => suggest(
+                "remove these bytes or save the file as UTF-8".to_string(),
+                span.byte_start..span.byte_end,
+                "",
+            ),
+        }
+    }
+}
+
+/// Characters that usually sneak in from word processors in place of the ascii one.
+fn lookalike(char: char) -> Option<char> {
+    Some(match char {
+        '\u{201C}' | '\u{201D}' | '\u{201E}' => '"',
+        '\u{2018}' | '\u{2019}' => '\'',
+        '\u{2212}' | '\u{2013}' | '\u{2014}' => '-',
+        '\u{00D7}' => '*',
+        '\u{00F7}' => '/',
+        '\u{037E}' => ';',
+        '\u{FF08}' => '(',
+        '\u{FF09}' => ')',
+        '\u{00A0}' | '\u{3000}' => ' ',
+        _ => None?,
+    })
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnexpectedCharacter { found, .. } => {
+                write!(f, "unexpected character `{}`", found.escape_default())
+            }
+            LexError::UnterminatedString { closing, .. } => {
+                write!(f, "unterminated literal, expected a closing `{closing}`")
+            }
+            LexError::UnterminatedComment { .. } => {
+                write!(f, "unterminated block comment, expected a closing `*/`")
+            }
+            LexError::InvalidEscape { escape, .. } if escape.starts_with("\\u") => write!(
+                f,
+                "invalid unicode escape `{escape}`, it needs to be `\\u{{...}}` with 1 to 6 hex digits of a unicode scalar value"
+            ),
+            LexError::InvalidEscape { escape, .. } if escape.len() == 1 => {
+                write!(f, "unterminated escape at the end of the file")
+            }
+            LexError::InvalidEscape { escape, .. } => write!(f, "unknown escape `{escape}`"),
+            LexError::MalformedChar { text, .. } if text == "''" => write!(f, "empty character literal"),
+            LexError::MalformedChar { .. } => {
+                write!(f, "character literals hold exactly one character")
+            }
+            LexError::MalformedNumber { problem, .. } => problem.fmt(f),
+            LexError::InvalidUtf8 { .. } => write!(f, "the file is not valid UTF-8 here"),
+        }
+    }
+}
+
+impl Display for NumberProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumberProblem::InvalidDigit { digit, radix } => {
+                write!(f, "invalid digit `{digit}` in a base {radix} literal")
+            }
+            NumberProblem::MissingDigits { radix } => {
+                write!(f, "missing digits after the base {radix} prefix")
+            }
+            NumberProblem::UnknownSuffix(suffix) => {
+                write!(f, "unknown suffix `{suffix}` on a number literal")
+            }
+            NumberProblem::IntSuffixOnFloat(suffix) => {
+                write!(f, "a float literal cannot have the integer suffix `{suffix}`")
+            }
+            NumberProblem::FloatInBase(radix) => write!(f, "base {radix} literals cannot be floats"),
+            NumberProblem::OutOfRange(type_) => write!(f, "number literal is out of range for `{type_}`"),
+        }
+    }
+}
+
+/// A change made to a source file, `range` is in bytes of the text before the change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, replacement: impl Into<String>) -> Self {
+        Self {
+            range,
+            replacement: replacement.into(),
+        }
+    }
+    /// The text after the edit.
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = String::with_capacity(source.len() - self.range.len() + self.replacement.len());
+        edited.push_str(&source[..self.range.start]);
+        edited.push_str(&self.replacement);
+        edited.push_str(&source[self.range.end..]);
+        edited
+    }
+    /// Where the replacement ends in the text after the edit.
+    fn new_end(&self) -> usize {
+        self.range.start + self.replacement.len()
+    }
+}
+
+/// Walks the source with a byte cursor, `start` and `current` are always on char boundaries.
+///
+/// It is an iterator handing out one token at a time up to and including the `EOF`, the
+/// `scan` functions are shorthands that collect it.
+pub struct Scanner<'a> {
+    source_code: &'a str,
+    file_id: usize,
     line: usize,
+    start_line: usize,
     start: usize,
     current: usize,
+    value: Option<TokenValue>,
+    lossless: bool,
+    finished: bool,
+    errors: Vec<LexError>,
 }
 
 trait NewAlphanumric {
@@ -106,45 +646,126 @@ impl NewAlphanumric for char {
     }
 }
 
-impl Scanner {
-    pub fn scan(source: String) -> Vec<Token> {
-        let mut tokens = vec![];
-        let mut scanner: Scanner = Self {
-            start: 0,
-            current: 0,
+impl<'a> Scanner<'a> {
+    pub fn new(source: &'a str, file_id: usize) -> Self {
+        Self {
             source_code: source,
+            file_id,
             line: 0,
-        };
-        while TokenType::EOF
-            != tokens
-                .last()
-                .and_then(|token: &Token| Some(token.token_type))
-                .unwrap_or(TokenType::SemiColon)
-        {
-            tokens.push(
-                scanner
-                    .scan_token(tokens.last().and_then(|token|Some(token.token_type == TokenType::Int || token.token_type == TokenType::Float)).unwrap_or(false))
-                    .unwrap_or(scanner.gen_token(TokenType::EOF)),
-            );
+            start_line: 0,
+            start: 0,
+            current: 0,
+            value: None,
+            lossless: false,
+            finished: false,
+            errors: vec![],
+        }
+    }
+    /// Also hand out whitespace and comments, so the lexemes joined back together are
+    /// exactly the source.
+    pub fn lossless(mut self) -> Self {
+        self.lossless = true;
+        self
+    }
+    pub fn scan(source: &str) -> Vec<Token> {
+        Self::scan_file(source, 0)
+    }
+    pub fn scan_file(source: &str, file_id: usize) -> Vec<Token> {
+        Scanner::new(source, file_id).collect()
+    }
+    /// Like `scan_file` but keeps what went wrong, in the order it was found.
+    pub fn scan_with_errors(source: &str, file_id: usize) -> (Vec<Token>, Vec<LexError>) {
+        let mut scanner = Scanner::new(source, file_id);
+        let tokens = scanner.by_ref().collect();
+        (tokens, scanner.errors)
+    }
+    /// Scans a file straight from its bytes, each byte that is not valid UTF-8 is reported
+    /// and read as a space so spans still line up with the file.
+    pub fn scan_bytes(source: &[u8], file_id: usize) -> (Vec<Token>, Vec<LexError>) {
+        let mut text = String::with_capacity(source.len());
+        let mut utf8_errors = vec![];
+        for chunk in source.utf8_chunks() {
+            text.push_str(chunk.valid());
+            if !chunk.invalid().is_empty() {
+                let span = Span::new(file_id, text.len(), text.len() + chunk.invalid().len());
+                // a run of bad bytes comes in one chunk per byte sequence, report it once
+                match utf8_errors.last_mut() {
+                    Some(LexError::InvalidUtf8 { span: last }) if last.byte_end == span.byte_start => {
+                        *last = last.to(span)
+                    }
+                    _ => utf8_errors.push(LexError::InvalidUtf8 { span }),
+                }
+                text.extend(std::iter::repeat_n(' ', chunk.invalid().len()));
+            }
+        }
+        let (tokens, mut errors) = Self::scan_with_errors(&text, file_id);
+        errors.extend(utf8_errors);
+        errors.sort_by_key(|error| error.span().byte_start);
+        (tokens, errors)
+    }
+    /// What went wrong in the part of the source scanned so far.
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+    pub fn scan_lossless(source: &str, file_id: usize) -> Vec<Token> {
+        Scanner::new(source, file_id).lossless().collect()
+    }
+    /// Brings `tokens`, what this scanner's source scanned to before `edit`, up to date by
+    /// scanning from just before the edit until the tokens line up with the old ones again.
+    ///
+    /// The old tokens have to come from a scanner in the same mode, that is lossless or not.
+    pub fn relex(mut self, mut tokens: Vec<Token>, edit: &TextEdit) -> Vec<Token> {
+        // the first token the edit touches, a token ending right where the edit starts
+        // counts since the edit might extend it
+        let touched = tokens.partition_point(|token| token.span.byte_end < edit.range.start);
+        // one more back, as scanning a token looks a char or two past its end
+        let restart = touched.saturating_sub(1);
+        // with nothing before the edit there might still be comments in front of it
+        if let Some(token) = tokens.get(restart).filter(|_| touched > 0) {
+            self.current = token.span.byte_start;
+            self.line = token.line;
         }
-        let mut i  = 0;
-        while i<tokens.len(){
-            if tokens[i].token_type == TokenType::Comment{
-                tokens.remove(i);
-                break;
+        let shift = |position: usize| position + edit.new_end() - edit.range.end;
+        let mut relexed = vec![];
+        let mut resume = restart;
+        for token in self.by_ref() {
+            while tokens.get(resume).is_some_and(|old| {
+                old.span.byte_start < edit.range.end || shift(old.span.byte_start) < token.span.byte_start
+            }) {
+                resume += 1;
+            }
+            // the scanner carries nothing from one token to the next, so once a token starts
+            // where an old one did past the edit the rest comes out the same as before
+            if token.span.byte_start >= edit.new_end()
+                && tokens.get(resume).is_some_and(|old| shift(old.span.byte_start) == token.span.byte_start)
+            {
+                let lines = token.line as isize - tokens[resume].line as isize;
+                for old in &mut tokens[resume..] {
+                    old.span.byte_start = shift(old.span.byte_start);
+                    old.span.byte_end = shift(old.span.byte_end);
+                    old.line = (old.line as isize + lines) as usize;
+                }
+                tokens.splice(restart..resume, relexed);
+                return tokens;
             }
-            i+=1;
+            relexed.push(token);
         }
+        tokens.truncate(restart);
+        tokens.append(&mut relexed);
         tokens
     }
-    fn scan_token(&mut self, is_last_numeric:bool) -> Option<Token> {
-        loop {
-            self.start = self.current;
-            let p_whitespace = self.peek(0)?;
-            if !p_whitespace.is_whitespace() {
-                break;
+    fn scan_token(&mut self) -> Option<Token> {
+        self.start = self.current;
+        self.start_line = self.line;
+        while self.peek(0).is_some_and(char::is_whitespace) {
+            self.advance();
+        }
+        if self.current > self.start {
+            if self.lossless {
+                return Some(self.gen_token(TokenType::Whitespace));
             }
-            let _ = self.advance()?;
+            self.start = self.current;
+            self.start_line = self.line;
         }
         let token_type = match self.advance()? {
             ';' => TokenType::SemiColon,
@@ -155,95 +776,222 @@ impl Scanner {
             '{' => TokenType::LBrace,
             '}' => TokenType::RBrace,
             '@' => TokenType::At,
-            '%' => TokenType::Percent,
-            '.' => TokenType::Dot,
-            '*' => TokenType::Star,
+            '%' => either!(self.check('=') => TokenType::PercentEqual; TokenType::Percent),
+            '.' => either!(self.check('.') => either!(self.check('=') => TokenType::DotDotEqual; TokenType::DotDot); TokenType::Dot),
+            '*' => either!(self.check('=') => TokenType::StarEqual; TokenType::Star),
             ',' => TokenType::Comma,
-            '|' => TokenType::Pipe,
-            '&' => TokenType::Ampersand,
+            '|' => either!(self.check('=') => TokenType::PipeEqual; TokenType::Pipe),
+            '&' => either!(self.check('=') => TokenType::AmpersandEqual; TokenType::Ampersand),
             '>' => TokenType::RArrow,
             ':' => either!(self.check(':') => TokenType::DoubleColon; TokenType::Colon),
             '+' => either!(self.check('=') => TokenType::PlusEqual;   TokenType::Plus),
-            '=' => either!(self.check('=') => TokenType::EqualEqual;  TokenType::Equal),
-            '<' => either!(self.check('=') => TokenType::LessOrEqual; either!(self.check('<') =>TokenType::SHL; TokenType::LArrow)),
+            '=' => either!(self.check('=') => TokenType::EqualEqual;  either!(self.check('>') => TokenType::FatArrow; TokenType::Equal)),
+            '<' => either!(self.check('=') => TokenType::LessOrEqual; either!(self.check('<') => either!(self.check('=') => TokenType::SHLEqual; TokenType::SHL); TokenType::LArrow)),
             '!' => either!(self.check('=') => TokenType::BangEqual;   TokenType::Bang),
-            '-' => {
-                if !is_last_numeric && self.peek(0).unwrap_or(' ').is_numeric(){
-                    self.extract_numeric()
+            '-' => either!(self.check('=') => TokenType::MinusEqual;  either!(self.check('>') => TokenType::SmallArrow; TokenType::Minus)),
+            'r' if self.is_raw_string_start() => self.extract_raw_string(),
+            a if a.is_ascii_digit() => self.extract_numeric(),
+            a if a.is_new_alpha() => self.extract_word(),
+            '"' => self.extract_string(),
+            '\'' => self.extract_char(),
+            '/' => {
+                if self.check('/') {
+                    let token_type = if self.peek(0) == Some('/') && self.peek(1) != Some('/') {
+                        TokenType::DocComment
+                    } else if self.peek(0) == Some('!') {
+                        TokenType::InnerDocComment
+                    } else {
+                        TokenType::Comment
+                    };
+                    while self.peek(0).is_some_and(|char| char != '\n'){self.advance();}
+                    token_type
+                } else if self.check('*') {
+                    self.extract_block_comment()
+                } else if self.check('=') {
+                    TokenType::SlashEqual
                 } else {
-                    either!(self.check('=') => TokenType::MinusEqual;  either!(self.check('>') => TokenType::SmallArrow; TokenType::Minus))
+                    TokenType::Slash
                 }
             }
-            'b' => self.check_keyword("reak", TokenType::Break)?,
-            'c' => self.check_keyword("ontinue", TokenType::Continue)?,
-            'l' => self.check_keyword("et", TokenType::Let)?,
-            'r' => self.check_keyword("eturn", TokenType::Return)?,
-            'w' => self.check_keyword("hile", TokenType::While)?,
-            'i' => match self.advance()? {
-                'f' => self.check_keyword("", TokenType::If)?,
-                'm' => self.check_keyword("pl", TokenType::Impl)?,
-                _ => self.extract_ident(),
-            },
-            'e' => match self.advance()? {
-                'l' => self.check_keyword("se", TokenType::Else)?,
-                'n' => self.check_keyword("um", TokenType::Enum)?,
-                _ => self.extract_ident(),
-            },
-            'a' => self.check_keyword("nd", TokenType::And)?,
-            'o' => self.check_keyword("r", TokenType::Or)?,
-            'f' => match self.advance()? {
-                'a' => self.check_keyword("lse", TokenType::False)?,
-                'n' => self.check_keyword("", TokenType::Fn)?,
-                'o' => self.check_keyword("r", TokenType::For)?,
-                _ => self.extract_ident(),
-            },
-            'S' => self.check_keyword("elf", TokenType::Self_)?,
-            's' => self.check_keyword("truct", TokenType::Struct)?,
-            'T' => self.check_keyword("rait", TokenType::Trait)?,
-            't' => self
-                .advance()
-                .and_then(|char| {
-                    if char == 'r' {
-                        Some(match self.advance()? {
-                            'u' => self.check_keyword("e", TokenType::True)?,
-                            'a' => self.check_keyword("it", TokenType::Trait)?,
-                            _ => {None?;unreachable!()},
-                        })
-                    } else {
-                        None
+            found => {
+                self.report(LexError::UnexpectedCharacter {
+                    found,
+                    span: self.span_from(self.start),
+                });
+                TokenType::Error
+            }
+        };
+        Some(self.gen_token(token_type))
+    }
+    fn extract_string(&mut self) -> TokenType {
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => break,
+                Some('\\') => {
+                    let escape_start = self.current - 1;
+                    match self.extract_escape() {
+                        Some(char) => value.push(char),
+                        None => {
+                            value.push_str(&self.source_code[escape_start..self.current]);
+                            self.report(LexError::InvalidEscape {
+                                escape: self.source_code[escape_start..self.current].to_string(),
+                                span: self.span_from(escape_start),
+                            });
+                        }
                     }
-                })
-                .unwrap_or(self.extract_ident()),
-            'm' => self.check_keyword("ut", TokenType::Mut)?,
-            'p' => self.check_keyword("anic", TokenType::Panic)?,
-            a if a.is_numeric() => self.extract_numeric(),
-            a if a.is_new_alpha() => self.extract_ident(),
-            '"' => loop {
-                if let Some(char) = self.advance() {
-                    if char == '\\' {
-                        continue;
+                }
+                Some(char) => value.push(char),
+                None => {
+                    self.report(LexError::UnterminatedString {
+                        closing: "\"".to_string(),
+                        span: self.span_from(self.start),
+                    });
+                    break;
+                }
+            }
+        }
+        self.value = Some(TokenValue::Str(value));
+        TokenType::String
+    }
+    /// Whether the `r` just consumed starts a raw string rather than a name.
+    fn is_raw_string_start(&self) -> bool {
+        self.source_code[self.current..].trim_start_matches('#').starts_with('"')
+    }
+    /// `r"..."` or `r#"..."#` with any number of `#`, the leading `r` is already consumed.
+    fn extract_raw_string(&mut self) -> TokenType {
+        let mut hashes = 0;
+        while self.check('#') {
+            hashes += 1;
+        }
+        self.check('"');
+        let content_start = self.current;
+        let content_end = loop {
+            match self.advance() {
+                Some('"') => {
+                    let content_end = self.current - 1;
+                    let mut closing = 0;
+                    while closing < hashes && self.check('#') {
+                        closing += 1;
                     }
-                    if char != '"' {
-                        continue;
-                    } else {
-                        break TokenType::String;
+                    if closing == hashes {
+                        break content_end;
                     }
                 }
-                break TokenType::Error;
-            },
-            '/' => {
-                either!(
-                    self.check('/')=> {
-                        let start_line = self.line.clone();
-                        while start_line == self.line{self.advance();}
-                        TokenType::Comment
-                    };
-                    TokenType::Slash
-                )
+                Some(_) => {}
+                None => {
+                    self.report(LexError::UnterminatedString {
+                        closing: format!("\"{}", "#".repeat(hashes)),
+                        span: self.span_from(self.start),
+                    });
+                    break self.current;
+                }
             }
-            _ => TokenType::Error,
         };
-        Some(self.gen_token(token_type))
+        let value = self.source_code[content_start..content_end].to_string();
+        self.value = Some(TokenValue::Str(value));
+        TokenType::String
+    }
+    fn extract_char(&mut self) -> TokenType {
+        let value = match self.advance() {
+            Some('\\') => {
+                let escape_start = self.current - 1;
+                let escape = self.extract_escape();
+                if escape.is_none() {
+                    self.report(LexError::InvalidEscape {
+                        escape: self.source_code[escape_start..self.current].to_string(),
+                        span: self.span_from(escape_start),
+                    });
+                }
+                escape.unwrap_or_default()
+            }
+            Some('\'') => {
+                self.report(LexError::MalformedChar {
+                    text: "''".to_string(),
+                    span: self.span_from(self.start),
+                });
+                self.value = Some(TokenValue::Char('\0'));
+                return TokenType::Char;
+            }
+            Some('\n') | None => {
+                self.report(LexError::UnterminatedString {
+                    closing: "'".to_string(),
+                    span: self.span_from(self.start),
+                });
+                self.value = Some(TokenValue::Char('\0'));
+                return TokenType::Char;
+            }
+            Some(char) => char,
+        };
+        if !self.check('\'') {
+            while self.peek(0).is_some_and(|char| char != '\'' && char != '\n') {
+                self.advance();
+            }
+            if self.check('\'') {
+                self.report(LexError::MalformedChar {
+                    text: self.source_code[self.start..self.current].to_string(),
+                    span: self.span_from(self.start),
+                });
+            } else {
+                self.report(LexError::UnterminatedString {
+                    closing: "'".to_string(),
+                    span: self.span_from(self.start),
+                });
+            }
+        }
+        self.value = Some(TokenValue::Char(value));
+        TokenType::Char
+    }
+    /// Decodes the escape after a `\\`, the backslash is already consumed.
+    fn extract_escape(&mut self) -> Option<char> {
+        match self.advance()? {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '\'' => Some('\''),
+            'u' => {
+                if !self.check('{') {
+                    return None;
+                }
+                let mut digits = String::new();
+                while let Some(char) = self.peek(0).filter(char::is_ascii_hexdigit) {
+                    digits.push(char);
+                    self.advance();
+                }
+                if !self.check('}') || digits.is_empty() || digits.len() > 6 {
+                    return None;
+                }
+                u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+            }
+            _ => None,
+        }
+    }
+    fn report(&mut self, error: LexError) {
+        self.errors.push(error);
+    }
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(self.file_id, start, self.current)
+    }
+    /// Block comments nest, so `/* a /* b */ c */` is a single comment.
+    fn extract_block_comment(&mut self) -> TokenType {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance() {
+                Some('*') if self.check('/') => depth -= 1,
+                Some('/') if self.check('*') => depth += 1,
+                Some(_) => {}
+                None => {
+                    self.report(LexError::UnterminatedComment {
+                        span: self.span_from(self.start),
+                    });
+                    break;
+                }
+            }
+        }
+        TokenType::Comment
     }
     fn extract_ident(&mut self) -> TokenType {
         while self
@@ -261,57 +1009,132 @@ impl Scanner {
         }
         TokenType::Ident
     }
-    fn check_keyword(&mut self, to_match: &str, token_type: TokenType) -> Option<TokenType> {
-        let mut chars = to_match.chars();
-        loop {
-            let char = chars.next();
-            match char {
-                Some(char) => {
-                    let actual_char = self.peek(0)?;
-                    if actual_char != char {
-                        break;
-                    }
+    /// An identifier, or the keyword it spells.
+    fn extract_word(&mut self) -> TokenType {
+        self.extract_ident();
+        Keyword::from_str(&self.source_code[self.start..self.current])
+            .map_or(TokenType::Ident, Keyword::token_type)
+    }
+    /// Reads a whole number literal, `0x`/`0o`/`0b` prefixes, `_` separators, fractions,
+    /// exponents and a type suffix, checking that the value fits the type it ends up with.
+    ///
+    /// Literals are never negative here, the parser folds a leading `-` in and does the final
+    /// range check, so `128i8` is let through for the sake of `-128i8`.
+    fn extract_numeric(&mut self) -> TokenType {
+        self.current = self.start;
+        let radix = match (self.peek(0), self.peek(1)) {
+            (Some('0'), Some('x')) => 16,
+            (Some('0'), Some('o')) => 8,
+            (Some('0'), Some('b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.advance();
+            self.advance();
+        }
+        let mut digits = String::new();
+        let mut is_float = false;
+        self.extract_digits(radix, &mut digits);
+        if radix == 10 {
+            if self.peek(0) == Some('.') && self.peek(1).is_some_and(|char| char.is_ascii_digit()) {
+                is_float = true;
+                digits.push('.');
+                self.advance();
+                self.extract_digits(10, &mut digits);
+            }
+            let exponent_digit = match self.peek(1) {
+                Some('+' | '-') => self.peek(2),
+                other => other,
+            };
+            if matches!(self.peek(0), Some('e' | 'E')) && exponent_digit.is_some_and(|char| char.is_ascii_digit()) {
+                is_float = true;
+                digits.push('e');
+                self.advance();
+                if let Some(sign) = self.peek(0).filter(|char| matches!(char, '+' | '-')) {
+                    digits.push(sign);
                     self.advance();
                 }
-                None => {
-                    return either!(!self.peek(0).unwrap_or(' ').is_new_alphanumeric() => Some(token_type); Some(self.extract_ident()))
-                }
+                self.extract_digits(10, &mut digits);
             }
         }
-        Some(self.extract_ident())
-    }
-    fn extract_numeric(&mut self) -> TokenType {
-        loop {
-            let p_char = self.peek(0);
-            match p_char {
-                Some(char) => {
-                    if char.is_numeric() {
-                        self.advance();
-                        continue;
-                    }
-                    if char == '.' {
-                        if self.peek(1).unwrap_or(' ').is_numeric(){
-                            self.advance();
-                            break;
-                        }else{
-                            return TokenType::Int;
-                        }
-                    }
-                    return TokenType::Int;
-                }
-                None => {
-                    return TokenType::Int;
-                }
+        if let Some(invalid) = self.peek(0).filter(|char| char.is_ascii_digit()) {
+            while self.peek(0).is_some_and(|char| char.is_new_alphanumeric()) {
+                self.advance();
             }
+            return self.malformed_number(NumberProblem::InvalidDigit { digit: invalid, radix }, false);
         }
-        while self
-            .peek(0)
-            .and_then(|char| if char.is_numeric() { Some(()) } else { None })
-            .is_some()
-        {
+        if digits.is_empty() {
+            while self.peek(0).is_some_and(|char| char.is_new_alphanumeric()) {
+                self.advance();
+            }
+            return self.malformed_number(NumberProblem::MissingDigits { radix }, false);
+        }
+        let suffix_start = self.current;
+        while self.peek(0).is_some_and(|char| char.is_new_alphanumeric()) {
+            self.advance();
+        }
+        let suffix = &self.source_code[suffix_start..self.current];
+        let float_suffix = suffix.parse::<FloatSuffix>().ok();
+        let int_suffix = suffix.parse::<IntSuffix>().ok();
+        if !suffix.is_empty() && float_suffix.is_none() && int_suffix.is_none() {
+            return self.malformed_number(NumberProblem::UnknownSuffix(suffix.to_string()), is_float);
+        }
+        if is_float || float_suffix.is_some() {
+            if let Some(suffix) = int_suffix {
+                return self.malformed_number(NumberProblem::IntSuffixOnFloat(suffix), true);
+            }
+            if radix != 10 {
+                return self.malformed_number(NumberProblem::FloatInBase(radix), true);
+            }
+            let value: f64 = digits.parse().unwrap_or(f64::INFINITY);
+            let fits = match float_suffix {
+                Some(FloatSuffix::F32) => (value as f32).is_finite(),
+                _ => value.is_finite(),
+            };
+            if !fits {
+                let type_ = float_suffix.unwrap_or(FloatSuffix::F64).to_string();
+                return self.malformed_number(NumberProblem::OutOfRange(type_), true);
+            }
+            self.value = Some(TokenValue::Float(value, float_suffix));
+            return TokenType::Float;
+        }
+        let max = match int_suffix {
+            Some(suffix) => suffix.max_magnitude(suffix.is_signed()),
+            None => u64::MAX,
+        };
+        match u64::from_str_radix(&digits, radix) {
+            Ok(value) if value <= max => {
+                self.value = Some(TokenValue::Int(value, int_suffix));
+                TokenType::Int
+            }
+            _ => {
+                let type_ = int_suffix.map(|suffix| suffix.to_string()).unwrap_or("u64".to_string());
+                self.malformed_number(NumberProblem::OutOfRange(type_), false)
+            }
+        }
+    }
+    /// Reports the number literal just scanned and stands a zero in for it.
+    fn malformed_number(&mut self, problem: NumberProblem, is_float: bool) -> TokenType {
+        self.report(LexError::MalformedNumber {
+            problem,
+            span: self.span_from(self.start),
+        });
+        if is_float {
+            self.value = Some(TokenValue::Float(0.0, None));
+            TokenType::Float
+        } else {
+            self.value = Some(TokenValue::Int(0, None));
+            TokenType::Int
+        }
+    }
+    /// Pushes the digits valid in `radix` onto `digits`, skipping over `_` separators.
+    fn extract_digits(&mut self, radix: u32, digits: &mut String) {
+        while let Some(char) = self.peek(0).filter(|char| char.is_digit(radix) || *char == '_') {
+            if char != '_' {
+                digits.push(char);
+            }
             self.advance();
         }
-        TokenType::Float
     }
     fn check(&mut self, char: char) -> bool {
         match self.peek(0) {
@@ -325,12 +1148,13 @@ impl Scanner {
             None => false,
         }
     }
+    /// Looks `i` chars past the cursor, only ever called with a small `i` so this stays constant time.
     fn peek(&self, i: usize) -> Option<char> {
-        self.source_code.chars().nth(self.current + i)
+        self.source_code[self.current..].chars().nth(i)
     }
     fn advance(&mut self) -> Option<char> {
         let res = self.peek(0)?;
-        self.current += 1;
+        self.current += res.len_utf8();
         if res == '\n' {
             self.line += 1;
         }
@@ -339,9 +1163,28 @@ impl Scanner {
     fn gen_token(&mut self, token_type: TokenType) -> Token {
         Token {
             token_type,
-            line: self.line,
-            line_offset:self.start,
+            line: self.start_line,
+            span: Span::new(self.file_id, self.start, self.current),
             lexeme: self.source_code[self.start..self.current].to_string(),
+            value: self.value.take(),
+        }
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = Token;
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            if self.finished {
+                return None;
+            }
+            let token = self
+                .scan_token()
+                .unwrap_or_else(|| self.gen_token(TokenType::EOF));
+            self.finished = token.token_type == TokenType::EOF;
+            if self.lossless || !token.is_trivia() {
+                return Some(token);
+            }
         }
     }
 }
@@ -0,0 +1,224 @@
+//! `type` aliases: how they parse, and the types they're swapped for when the IR is made.
+
+mod common;
+
+use calcify::{
+    ast::decl::{Declaration, Type as ASTType, Visibility},
+    ir::{
+        ir_alias::expand,
+        ir_type_data::{CompileError, Type, IR},
+    },
+};
+
+use common::{load_clean, parse, parse_errors};
+
+/// Writes an IR type out the way it would be in the source.
+fn show(type_: &Type) -> String {
+    let generics = |types: &[Type]| match types {
+        [] => String::new(),
+        types => format!("<{}>", types.iter().map(show).collect::<Vec<_>>().join(", ")),
+    };
+    match type_ {
+        Type::Unit => "()".to_string(),
+        Type::Actual(actual) => format!("{}{}", actual.name, generics(&actual.types_in_generics)),
+        Type::Array(actual) => format!("[{}{}]", actual.name, generics(&actual.types_in_generics)),
+        Type::DynType(traits) => traits.iter().map(|trait_| trait_.name.clone()).collect::<Vec<_>>().join(" + "),
+        Type::FP(fp) => format!("|{}|: {}", fp.arg.iter().map(show).collect::<Vec<_>>().join(", "), show(&fp.out)),
+        Type::Tuple(types) => format!("({})", types.iter().map(show).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+/// The name of an AST type, `[T]` for an array of one.
+fn show_ast(type_: &ASTType) -> String {
+    match type_ {
+        ASTType::PhysicalType(solid) => solid.name.lexeme.clone(),
+        ASTType::Array(solid, _) => format!("[{}]", solid.name.lexeme),
+        other => format!("{other:?}"),
+    }
+}
+
+fn fields(ir: &IR, struct_: &str) -> Vec<(String, String)> {
+    let mut fields = ir.structs[struct_]
+        .fields
+        .iter()
+        .map(|(name, type_)| (name.clone(), show(type_)))
+        .collect::<Vec<_>>();
+    fields.sort();
+    fields
+}
+
+#[test]
+fn aliases_parse() {
+    let program = parse("/// two of them\npub type Pair<T: Add> = Both<T, T>;\ntype Id = Int;");
+    let Declaration::TypeAliasDecl(pair) = &program.code[0] else { panic!() };
+    assert_eq!(pair.name.lexeme, "Pair");
+    assert_eq!((pair.visibility, pair.docs.len(), pair.generics.len()), (Visibility::Public, 1, 1));
+    assert!(matches!(&pair.type_of, ASTType::PhysicalType(both) if both.name.lexeme == "Both"));
+    let Declaration::TypeAliasDecl(id) = &program.code[1] else { panic!() };
+    assert!(id.generics.is_empty());
+    assert!(!pair.new && !id.new);
+    for source in ["type = Int;", "type A Int;", "type A = ;"] {
+        assert!(!parse_errors(source).is_empty(), "{source:?}");
+    }
+}
+
+#[test]
+fn aliases_are_swapped_for_what_they_name() {
+    let ir = IR::try_from(&parse(
+        "struct Both<A, B>{a: A, b: B}
+type Pair<T> = Both<T, T>;
+type Grid = Pair<Pair<Int>>;
+type Callback<T> = |T, Id|: Pair<T>;
+type Id = u32;
+struct S<T>{
+    pair: Pair<Float>,
+    grid: Grid,
+    ids: [Id],
+    call: Callback<Bool>,
+    own: Pair<T>
+}",
+    ))
+    .unwrap();
+    assert_eq!(
+        fields(&ir, "S"),
+        [
+            ("call".to_string(), "|Bool, u32|: Both<Bool, Bool>".to_string()),
+            ("grid".to_string(), "Both<Both<Int, Int>, Both<Int, Int>>".to_string()),
+            ("ids".to_string(), "[u32]".to_string()),
+            ("own".to_string(), "Both<T, T>".to_string()),
+            ("pair".to_string(), "Both<Float, Float>".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn generics_and_bodies_are_looked_through() {
+    let program = expand(&parse(
+        "type Id = Int;
+struct S<Id>{x: Id}
+fn f(x: Id) -> Id { let y: Id = x; let g = |z: Id|: Id z; y }",
+    ))
+    .unwrap();
+    assert_eq!(program.code.len(), 2, "the alias itself is taken out");
+    // a generic of the same name hides the alias
+    let Declaration::StructDecl(s) = &program.code[0] else { panic!() };
+    assert!(matches!(&s.fields[0].type_of, ASTType::PhysicalType(id) if id.name.lexeme == "Id"));
+    let Declaration::FunctionDecl(f) = &program.code[1] else { panic!() };
+    assert!(matches!(&f.sig.parameters[0].1, ASTType::PhysicalType(int) if int.name.lexeme == "Int"));
+    assert!(matches!(&f.sig.out, ASTType::PhysicalType(int) if int.name.lexeme == "Int"));
+    assert!(!format!("{:?}", f.body).contains("\"Id\""), "{:?}", f.body);
+}
+
+#[test]
+fn newtypes_parse() {
+    let new = |source: &str| {
+        let Declaration::TypeAliasDecl(alias) = &parse(source).code[0] else { panic!() };
+        (alias.new, show_ast(&alias.type_of))
+    };
+    assert_eq!(new("type Meters = new Float;"), (true, "Float".to_string()));
+    assert_eq!(new("type Ids<T> = new [T];"), (true, "[T]".to_string()));
+    // with nothing after it `new` is just a name
+    assert_eq!(new("type A = new;"), (false, "new".to_string()));
+    assert_eq!(new("type A = new<Int>;"), (false, "new".to_string()));
+}
+
+#[test]
+fn newtypes_are_structs_of_their_own() {
+    let ir = IR::try_from(&parse(
+        "type Meters = new Float;
+type Wrap<T> = new |T|: T;
+type Length = Meters;
+struct S{m: Meters, l: Length, w: Wrap<Int>}
+fn f(x: Float) -> Float { let m = Meters{value: x}; m.value }",
+    ))
+    .unwrap();
+    assert_eq!(fields(&ir, "Meters"), [("value".to_string(), "Float".to_string())]);
+    assert_eq!(fields(&ir, "Wrap"), [("value".to_string(), "|T|: T".to_string())]);
+    assert_eq!(
+        fields(&ir, "S"),
+        [
+            ("l".to_string(), "Meters".to_string()),
+            ("m".to_string(), "Meters".to_string()),
+            ("w".to_string(), "Wrap<Int>".to_string()),
+        ]
+    );
+    assert!(matches!(
+        IR::try_from(&parse("type A = new Int; type A = Float;")),
+        Err(CompileError::DuplicateTypeAliases(names)) if names == ["A"]
+    ));
+    let crate_ = load_clean(&[
+        ("main.cal", "mod geo; fn f(m: geo::Meters) -> Float { m.value }"),
+        ("geo.cal", "pub type Meters = new Float;"),
+    ]);
+    assert!(IR::try_from(&crate_).is_ok());
+}
+
+#[test]
+fn constructors_and_paths_through_aliases() {
+    let program = expand(&parse(
+        "struct Point{x: Int}
+enum Shape{Circle{r: Int}}
+type P = Point;
+type Shapes = Shape;
+type List<T> = [T];
+struct S{ps: List<P>}
+fn f() { let p = P{x: 1}; let s = Shapes::Circle{r: 2}; let q = P::new(); }",
+    ))
+    .unwrap();
+    let Declaration::StructDecl(s) = &program.code[2] else { panic!() };
+    assert!(matches!(&s.fields[0].type_of, ASTType::Array(point, _) if point.name.lexeme == "Point"));
+    let Declaration::FunctionDecl(f) = &program.code[3] else { panic!() };
+    let body = format!("{:?}", f.body);
+    for alias in ["\"P\"", "\"Shapes\""] {
+        assert!(!body.contains(alias), "{alias} is left in {body}");
+    }
+    assert_eq!(body.matches("\"Point\"").count(), 2, "{body}");
+    assert_eq!(body.matches("\"Shape\"").count(), 1, "{body}");
+}
+
+#[test]
+fn aliases_that_cant_be_worked_out() {
+    for (source, check) in [
+        ("type A = A;", (|err| matches!(err, CompileError::CyclicTypeAlias(name, _) if name == "A")) as fn(&CompileError) -> bool),
+        ("type A = B<Int>; type B<T> = C<T>; type C<T> = A;", |err| matches!(err, CompileError::CyclicTypeAlias(..))),
+        ("type L = Node<L>; struct Node<T>{next: T}", |err| matches!(err, CompileError::CyclicTypeAlias(..))),
+        ("type Pair<T> = Both<T, T>; struct S{p: Pair<Int, Int>}", |err| {
+            matches!(err, CompileError::NumberOfGenericsAndTypesGivenDoNotMatchInItem(name, 1, 2, _) if name == "Pair")
+        }),
+        ("type Id = Int; struct S{p: Id<Int>}", |err| {
+            matches!(err, CompileError::NumberOfGenericsAndTypesGivenDoNotMatchInItem(name, 0, 1, _) if name == "Id")
+        }),
+        ("type A = Int; type A = Float;", |err| matches!(err, CompileError::DuplicateTypeAliases(names) if names == &["A"])),
+        ("struct A{} type A = Int;", |err| matches!(err, CompileError::AliasesNamedLikeTypes(names) if names == &["A"])),
+        ("type B = new Int; enum B{C} type A = B;", |err| {
+            matches!(err, CompileError::AliasesNamedLikeTypes(names) if names == &["B"])
+        }),
+        ("type F = |Int|: Int; struct S{fs: [F]}", |err| matches!(err, CompileError::AliasInArrayIsNotNamedType(name, _) if name == "F")),
+        ("type List<T> = [T]; struct S{ts: List<(Int, Int)>}", |err| {
+            matches!(err, CompileError::AliasInArrayIsNotNamedType(name, _) if name == "List")
+        }),
+        ("type T = (Int, Int); fn f() { let t = T{}; }", |err| matches!(err, CompileError::AliasIsNotNamedType(name, _) if name == "T")),
+        ("type F = |Int|: Int; fn f() { F::new(); }", |err| matches!(err, CompileError::AliasIsNotNamedType(name, _) if name == "F")),
+    ] {
+        match IR::try_from(&parse(source)) {
+            Err(err) => assert!(check(&err), "{source:?} gave {err:?}"),
+            Ok(_) => panic!("{source:?} should fail"),
+        }
+    }
+}
+
+#[test]
+fn aliases_through_modules() {
+    let crate_ = load_clean(&[
+        ("main.cal", "mod geo; use geo::Pair; struct S{p: Pair<Int>, q: geo::Wide}"),
+        ("geo.cal", "pub struct Both<A, B>{a: A, b: B} pub type Pair<T> = Both<T, T>; pub type Wide = Pair<Float>;"),
+    ]);
+    let ir = IR::try_from(&crate_).unwrap();
+    assert_eq!(
+        fields(&ir, "S"),
+        [
+            ("p".to_string(), "geo::Both<Int, Int>".to_string()),
+            ("q".to_string(), "geo::Both<Float, Float>".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,63 @@
+//! `=` and the compound assignments like `+=`: what they can store to, and what they lower to.
+
+mod common;
+
+use calcify::ir::{
+    ir_chunk::{Calc, IRChunk, IRCode, ValueAccess},
+    ir_type_data::CompileError,
+};
+
+/// Lowers `fn go(x: Int) { <body> }`, so `x` is slot 0.
+fn lower(body: &str) -> Result<IRChunk, CompileError> {
+    common::lower(&format!("fn go(x: Int) {{ {body} }}"))
+}
+
+fn var(slot: u16) -> ValueAccess {
+    ValueAccess::VarAccess(slot)
+}
+
+#[test]
+fn compound_assignments_store_back_into_the_local() {
+    let chunk = lower("let mut t = 1; t += x; t <<= 2; t %= t;").unwrap();
+    match &chunk.code[..] {
+        [IRCode::VarCreate(1, Calc::Clone(ValueAccess::IntConst(1, None))), IRCode::VarAssign(1, Calc::Add(lhs, rhs)), IRCode::VarAssign(1, Calc::SHL(shifted, ValueAccess::IntConst(2, None))), IRCode::VarAssign(1, Calc::Mod(left, right))]
+            if *lhs == var(1) && *rhs == var(0) && *shifted == var(1) && *left == var(1) && *right == var(1) => {}
+        other => panic!("{other:#?}"),
+    }
+}
+
+#[test]
+fn only_mutable_places_can_be_assigned() {
+    for body in ["let t = 1; t += 1;", "x -= 1;", "let t = (1, 2); t.0 = 3;", "let t = ((1, 2), 3); (t.0).1 *= 2;"] {
+        assert!(matches!(lower(body), Err(CompileError::AssignToImmutable(..))), "{body:?}: {:?}", lower(body));
+    }
+    for body in ["1 += 2;", "(x + 1) = 2;", "(1, 2).0 = 3;"] {
+        assert!(matches!(lower(body), Err(CompileError::InvalidAssignTarget(_))), "{body:?}: {:?}", lower(body));
+    }
+    assert!(lower("let mut n = 0; let f = || n >>= 1;").is_ok());
+    assert!(matches!(lower("let n = 0; let f = || n |= 1;"), Err(CompileError::AssignToImmutable(name, _)) if name == "n"));
+}
+
+#[test]
+fn the_place_is_only_worked_out_once() {
+    let chunk = lower("let mut t = ((1, 2), 3); t.0.1 *= 5;").unwrap();
+    let reads = chunk.code.iter().filter(|code| matches!(code, IRCode::VarCreate(_, Calc::FieldAccess(_, 0)))).count();
+    assert_eq!(reads, 1, "{:#?}", chunk.code);
+    // the two tuples are slots 1 and 2, `t` is 3
+    match &chunk.code[chunk.code.len() - 3..] {
+        [IRCode::VarCreate(inner, Calc::FieldAccess(t, 0)), IRCode::VarCreate(current, Calc::FieldAccess(from, 1)), IRCode::FieldAssign(into, 1, Calc::Multiply(old, ValueAccess::IntConst(5, None)))]
+            if *t == var(3) && *from == var(*inner) && into == inner && *old == var(*current) => {}
+        other => panic!("{other:#?}"),
+    }
+}
+
+#[test]
+fn captured_locals_are_assigned_through_their_reference() {
+    let chunk = lower("let mut n = 0; let f = || n -= 1;").unwrap();
+    let Some(IRCode::VarCreate(_, Calc::Closure(name, _))) = chunk.code.iter().rev().nth(1) else { panic!("{:#?}", chunk.code) };
+    match &chunk.functions[name].body.code[..] {
+        [IRCode::VarCreate(current, Calc::Deref(reference)), IRCode::DerefAssign(0, Calc::Subtract(old, ValueAccess::IntConst(1, None))), IRCode::Return(ValueAccess::Unit)]
+            if *reference == var(0) && *old == var(*current) => {}
+        other => panic!("{other:#?}"),
+    }
+}
@@ -0,0 +1,149 @@
+//! Closures: how they parse, what they capture, what they lower to and how they are checked
+//! against function pointer types.
+
+mod common;
+
+use calcify::{
+    ast::{
+        expresions::{Closure, Expresion},
+        statments::Statment,
+    },
+    ir::{
+        ir_chunk::{Calc, IRChunk, IRCode, ValueAccess},
+        ir_closure::captures,
+        ir_type_data::{CompileError, IR},
+    },
+};
+
+use common::{function, parse, parse_errors};
+
+/// The closure the `let` at the start of `body` is given.
+fn closure(body: &str) -> Closure {
+    let program = parse(&format!("fn go() {{ {body} }}"));
+    let Statment::VarCreation(var_creation) = &function(&program).body.statments[0] else { panic!("{body:?}") };
+    let Statment::Expresion(Expresion::Closure(closure)) = &*var_creation.value else { panic!("{body:?}") };
+    closure.clone()
+}
+
+fn lower(body: &str) -> Result<IRChunk, CompileError> {
+    common::lower(&format!("fn go(a: Int) {{ {body} }}"))
+}
+
+#[test]
+fn closures_parse() {
+    let add = closure("let add = |x: Int, y: Int|: Int x + y;");
+    assert_eq!(add.parameters.len(), 2);
+    assert!(add.out.is_some());
+    assert!(matches!(*add.body, Expresion::Binary(_)));
+    let nothing = closure("let nothing = || {};");
+    assert!(nothing.parameters.is_empty() && nothing.out.is_none());
+    let curried = closure("let add = |x: Int|: |Int|: Int |y: Int|: Int x + y;");
+    assert!(matches!(*curried.body, Expresion::Closure(_)));
+    // a closure is an argument like any other expression
+    parse("fn go() { each(xs, |x: Int| total = total + x); }");
+}
+
+#[test]
+fn parameters_need_types() {
+    let errors = parse_errors("fn go() { let f = |x| x; }");
+    assert!(errors[0].note.as_deref().is_some_and(|note| note.contains("add a `:` after `x`")), "{errors:?}");
+}
+
+#[test]
+fn captures_are_the_names_used_but_not_bound() {
+    let names = |body: &str| captures(&closure(body)).into_iter().map(|token| token.lexeme).collect::<Vec<_>>();
+    assert_eq!(names("let f = |x: Int| x + y * y + z;"), vec!["y", "z"]);
+    assert_eq!(names("let f = || { let y = 1; y + w };"), vec!["w"]);
+    assert_eq!(names("let f = || { w; let w = 1; w };"), vec!["w"]);
+    assert_eq!(names("let f = || { for i in 0..n { total = total + i; } };"), vec!["n", "total"]);
+    assert_eq!(names("let f = || match p { Point{x, y: b} => x + b + c, other => other.x };"), vec!["p", "c"]);
+    // what a nested closure needs has to be captured on its way in
+    assert_eq!(names("let f = |x: Int| |y: Int| x + y + z;"), vec!["z"]);
+}
+
+#[test]
+fn immutable_locals_are_copied_in_and_mutable_ones_shared() {
+    let chunk = lower("let b = 2; let mut total = 0; let add = |x: Int| total = total + x + a + b;").unwrap();
+    // `a` is slot 0, `b` 1 and `total` 2
+    // the closure is made just before `add` is given it
+    let Some(IRCode::VarCreate(_, Calc::Closure(name, captured))) = chunk.code.iter().rev().nth(1) else { panic!("{:#?}", chunk.code) };
+    let Some(IRCode::VarCreate(reference, Calc::Ref(2))) = chunk.code.iter().rev().nth(2) else { panic!("{:#?}", chunk.code) };
+    assert_eq!(captured, &vec![ValueAccess::VarAccess(*reference), ValueAccess::VarAccess(0), ValueAccess::VarAccess(1)]);
+    // inside, `total` is slot 0, `a` 1, `b` 2 and `x` 3
+    let body = &chunk.functions[name].body.code;
+    assert!(matches!(body[0], IRCode::VarCreate(_, Calc::Deref(ValueAccess::VarAccess(0)))), "{body:#?}");
+    assert!(body.iter().any(|code| matches!(code, IRCode::DerefAssign(0, _))), "{body:#?}");
+    assert!(matches!(body.last(), Some(IRCode::Return(ValueAccess::Unit))), "{body:#?}");
+}
+
+#[test]
+fn nested_closures_pass_references_along() {
+    let chunk = lower("let mut n = 0; let outer = || || n = n + 1;").unwrap();
+    let Some(IRCode::VarCreate(_, Calc::Closure(outer, _))) = chunk.code.iter().rev().nth(1) else { panic!("{:#?}", chunk.code) };
+    let outer = &chunk.functions[outer].body;
+    // the reference `outer` got is given to the inner closure as it is
+    let Some(IRCode::VarCreate(inner, Calc::Closure(_, captured))) = outer.code.iter().rev().nth(1) else { panic!("{:#?}", outer.code) };
+    assert_eq!(captured, &vec![ValueAccess::VarAccess(0)]);
+    assert!(matches!(outer.code.last(), Some(IRCode::Return(ValueAccess::VarAccess(slot))) if slot == inner));
+}
+
+#[test]
+fn captured_immutable_locals_cant_be_assigned() {
+    assert!(matches!(lower("let n = 0; let f = || n = 1;"), Err(CompileError::AssignToImmutable(name, _)) if name == "n"));
+    assert!(matches!(lower("let f = |x: Int| x = 1;"), Err(CompileError::AssignToImmutable(name, _)) if name == "x"));
+    assert!(lower("let mut n = 0; let f = || n = 1;").is_ok());
+}
+
+#[test]
+fn closures_have_to_fit_the_type_they_are_given() {
+    for body in [
+        "let f: |Int, Int|: Int = |x: Int, y: Int|: Int x + y;",
+        "let f: ||: () = || {};",
+        "let f: |Int|: |Int|: Int = |x: Int|: |Int|: Int |y: Int|: Int x + y;",
+    ] {
+        assert!(lower(body).is_ok(), "{body:?}: {:?}", lower(body));
+    }
+    let mismatch = |body: &str| match lower(body) {
+        Err(CompileError::MismatchedClosureType(expected, found, _)) => (expected, found),
+        other => panic!("{body:?} gave {other:?}"),
+    };
+    assert_eq!(mismatch("let f: |Int|: Int = |x: Int, y: Int|: Int x;"), ("|Int|: Int".to_string(), "|Int, Int|: Int".to_string()));
+    assert_eq!(mismatch("let f: |Int|: Int = |x: Float| 1;"), ("|Int|: Int".to_string(), "|Float|: ()".to_string()));
+    assert_eq!(
+        mismatch("let f: |Int|: |Int|: Int = |x: Int|: |Int|: Int |y: [Int]|: Int x;"),
+        ("|Int|: Int".to_string(), "|[Int]|: Int".to_string())
+    );
+}
+
+#[test]
+fn closures_passed_and_returned_are_checked_too() {
+    let check = |source: &str| match IR::try_from(&parse(source)) {
+        Ok(_) => None,
+        Err(CompileError::MismatchedClosureType(expected, found, _)) => Some((expected, found)),
+        Err(other) => panic!("{source:?} gave {other:?}"),
+    };
+    let apply = "fn apply(f: |Int|: Int, x: Int) -> Int { f(x) }\n";
+    assert_eq!(check(&format!("{apply}fn go() -> Int {{ apply(|x: Int|: Int x + 1, 2) }}")), None);
+    assert_eq!(
+        check(&format!("{apply}fn go() -> Int {{ apply(|x: Int, y: Int|: Int x + y, 2) }}")),
+        Some(("|Int|: Int".to_string(), "|Int, Int|: Int".to_string()))
+    );
+    assert_eq!(
+        check("struct S{} impl S { fn apply(self: Self, f: |Int|: Int) -> Int { f(1) } }
+fn go() -> Int { let s = S{}; s.apply(|x: Int|: Float 1.0) }"),
+        Some(("|Int|: Int".to_string(), "|Int|: Float".to_string()))
+    );
+    assert_eq!(
+        check("struct S{} impl S { fn apply(f: |Int|: Int) -> Int { f(1) } } fn go() -> Int { S::apply(|| 1) }"),
+        Some(("|Int|: Int".to_string(), "||: ()".to_string()))
+    );
+    assert_eq!(check("fn adder(n: Int) -> |Int|: Int { |x: Int|: Int x + n }"), None);
+    assert_eq!(
+        check("fn adder(n: Int) -> |Int|: Int { |x: Int, y: Int|: Int x + n }"),
+        Some(("|Int|: Int".to_string(), "|Int, Int|: Int".to_string()))
+    );
+    assert_eq!(
+        check("fn adder(n: Int) -> |Int|: Int { return |x: Int|: Float 1.0; }"),
+        Some(("|Int|: Int".to_string(), "|Int|: Float".to_string()))
+    );
+}
@@ -0,0 +1,79 @@
+//! Helpers the integration tests share, each test file only uses some of them.
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use calcify::{
+    ast::{
+        decl::{Declaration, FunctionDecl, Program},
+        parser::{ParseError, TokenStream},
+    },
+    ir::{
+        ir_chunk::IRChunk,
+        ir_lowering::lower_function,
+        ir_type_data::{CompileError, IR},
+    },
+    diagnostic::Diagnostic,
+    module::Crate,
+    scanner::Scanner,
+};
+
+/// Parses `source`, which has to parse without any errors.
+pub fn parse(source: &str) -> Program {
+    let (program, errors) = parse_with_errors(source);
+    assert!(errors.is_empty(), "{source:?} failed to parse: {errors:?}");
+    program
+}
+
+pub fn parse_with_errors(source: &str) -> (Program, Vec<ParseError>) {
+    let tokens = Scanner::scan(source);
+    Program::parse(&mut TokenStream::new(&tokens))
+}
+
+/// The syntax errors in `source`.
+pub fn parse_errors(source: &str) -> Vec<ParseError> {
+    parse_with_errors(source).1
+}
+
+/// The last function in `program`, the one a test wrote after the types it needs.
+pub fn function(program: &Program) -> &FunctionDecl {
+    program
+        .code
+        .iter()
+        .rev()
+        .find_map(|declaration| match declaration {
+            Declaration::FunctionDecl(function) => Some(function),
+            _ => None,
+        })
+        .expect("there is a function")
+}
+
+/// Lowers the last function in `source` with nothing else declared around it.
+pub fn lower(source: &str) -> Result<IRChunk, CompileError> {
+    let ir = IR::try_from(&Program { docs: vec![], code: vec![] }).expect("nothing to get wrong");
+    lower_function(&ir, function(&parse(source)))
+}
+
+/// Loads the crate made of `files`, the first of them being the root.
+pub fn load(files: &[(&str, &str)]) -> (Crate, Vec<Diagnostic>) {
+    let root = PathBuf::from(files[0].0);
+    let files = files
+        .iter()
+        .map(|(path, source)| (PathBuf::from(path), source.as_bytes().to_vec()))
+        .collect::<HashMap<_, _>>();
+    Crate::load_with(&root, |path: &Path| {
+        files.get(path).cloned().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    })
+    .expect("the root is there")
+}
+
+/// Loads the crate made of `files`, which has to load without any errors.
+pub fn load_clean(files: &[(&str, &str)]) -> Crate {
+    let (crate_, diagnostics) = load(files);
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    crate_
+}
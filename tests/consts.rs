@@ -0,0 +1,192 @@
+//! `const` items, top level and in impls: how they parse, what the evaluator makes of their
+//! values, and uses of them being swapped for those values when a function is lowered. Also
+//! `static` items, which start with a value worked out the same way and are read and
+//! assigned by name.
+
+mod common;
+
+use std::collections::HashMap;
+
+use calcify::{
+    ast::decl::{Declaration, Program, Visibility},
+    ir::{
+        ir_chunk::{Calc, IRCode, ValueAccess},
+        ir_lowering::lower_function,
+        ir_type_data::{CompileError, IR},
+    },
+    scanner::IntSuffix,
+};
+
+use common::{parse, parse_errors};
+
+/// Works out the constants of `source`, which has nothing else that needs lowering.
+fn consts(source: &str) -> Result<HashMap<String, ValueAccess>, CompileError> {
+    let ir = IR::try_from(&parse(source))?;
+    Ok(ir.consts.into_iter().map(|(name, const_)| (name, const_.value)).collect())
+}
+
+fn int(value: i64, suffix: Option<IntSuffix>) -> ValueAccess {
+    ValueAccess::IntConst(value as u64, suffix)
+}
+
+#[test]
+fn top_level_and_associated_consts_parse() {
+    let program = parse("/// how many\npub const MAX: u8 = 200;\nimpl Point { pub(module) const ORIGIN: Int = 0; fn f() {} }");
+    let Declaration::ConstDecl(max) = &program.code[0] else { panic!() };
+    assert_eq!((max.name.lexeme.as_str(), max.visibility, max.docs.len()), ("MAX", Visibility::Public, 1));
+    let Declaration::ImplDecl(impl_) = &program.code[1] else { panic!() };
+    assert_eq!(impl_.consts[0].name.lexeme, "ORIGIN");
+    assert_eq!(impl_.consts[0].visibility, Visibility::Module);
+    assert_eq!(impl_.funcs.len(), 1);
+    let errors = parse_errors("const A = 1;");
+    assert!(!errors.is_empty());
+}
+
+#[test]
+fn values_are_worked_out() {
+    let consts = consts(
+        "const A: Int = 2 + 3 * 4;
+const B: Int = (A - 20) / 3 % 5;
+const MASK: u8 = 1 << 3 | 1;
+const HALF: Float = 1.0 / 2.0;
+const BIG: Bool = A > 10 and !(B == 0);
+const NAME: String = \"calc\" + \"ify\";
+const FIRST: Char = 'c';
+struct Point{}
+impl Point { const ZERO: i16 = -A + Self::ONE; const ONE: i16 = 1; }",
+    )
+    .unwrap();
+    assert_eq!(consts["A"], int(14, None));
+    assert_eq!(consts["B"], int(-2, None));
+    assert_eq!(consts["MASK"], int(9, Some(IntSuffix::U8)));
+    assert!(matches!(consts["HALF"], ValueAccess::FloatConst(half, None) if half == 0.5));
+    assert_eq!(consts["BIG"], ValueAccess::True);
+    assert_eq!(consts["NAME"], ValueAccess::StringConst("calcify".into()));
+    assert_eq!(consts["FIRST"], ValueAccess::CharConst('c'));
+    assert_eq!(consts["Point::ZERO"], int(-13, Some(IntSuffix::I16)));
+}
+
+#[test]
+fn values_that_cant_be_worked_out() {
+    for (source, check) in [
+        ("const A: u8 = 300;", (|err| matches!(err, CompileError::ConstOverflow(_))) as fn(&CompileError) -> bool),
+        ("const A: u8 = 0 - 1;", |err| matches!(err, CompileError::ConstOverflow(_))),
+        ("const A: Int = 9223372036854775807 + 1;", |err| matches!(err, CompileError::ConstOverflow(_))),
+        ("const A: Int = 1 / (2 - 2);", |err| matches!(err, CompileError::ConstDivideByZero(_))),
+        ("const A: Int = \"a\";", |err| matches!(err, CompileError::MismatchedConstType(expected, found, _) if expected == "Int" && found == "string")),
+        ("const A: Int = 1 + 1.0;", |err| matches!(err, CompileError::MismatchedConstType(..))),
+        ("const A: Int = B; const B: Int = A;", |err| matches!(err, CompileError::CyclicConst(..))),
+        ("const A: Int = f();", |err| matches!(err, CompileError::NotConstant(_))),
+        ("const A: Int = NOPE;", |err| matches!(err, CompileError::UnknownVariable(name, _) if name == "NOPE")),
+        ("const A: Int = 1; const A: Int = 2;", |err| matches!(err, CompileError::DuplicateConsts(names) if names == &["A"])),
+    ] {
+        match consts(source) {
+            Err(err) => assert!(check(&err), "{source:?} gave {err:?}"),
+            Ok(consts) => panic!("{source:?} gave {consts:?}"),
+        }
+    }
+}
+
+#[test]
+fn uses_are_swapped_for_the_value() {
+    let program = parse(
+        "const LIMIT: u32 = 4 * 4;
+const SCALE: Float = 2.5;
+const GREETING: String = \"hi\";
+struct Point{}
+impl Point { const ORIGIN: Int = LIMIT - 16; }
+fn f(x: Int) -> u32 { let s = SCALE; let g = GREETING; let o = Point::ORIGIN; x + LIMIT }",
+    );
+    let (functions, rest): (Vec<_>, Vec<_>) =
+        program.code.into_iter().partition(|declaration| matches!(declaration, Declaration::FunctionDecl(_)));
+    let ir = IR::try_from(&Program { docs: vec![], code: rest }).unwrap();
+    let [Declaration::FunctionDecl(f)] = &functions[..] else { panic!() };
+    let chunk = lower_function(&ir, f).unwrap();
+    let values = chunk
+        .code
+        .iter()
+        .filter_map(|code| match code {
+            IRCode::VarCreate(_, Calc::Clone(value)) | IRCode::VarAssign(_, Calc::Clone(value)) => Some(value.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(values.contains(&ValueAccess::FloatConst(2.5, None)), "{values:?}");
+    assert!(values.contains(&ValueAccess::StringConst("hi".into())), "{values:?}");
+    assert!(values.contains(&int(0, None)), "{values:?}");
+    assert!(
+        chunk.code.iter().any(|code| matches!(code, IRCode::VarCreate(_, Calc::Add(_, ValueAccess::IntConst(16, Some(IntSuffix::U32)))))),
+        "{:?}",
+        chunk.code
+    );
+    // a local of the same name is used over the constant
+    let program = parse("const X: Int = 1; fn f(X: Int) -> Int { X }");
+    let [Declaration::ConstDecl(_), Declaration::FunctionDecl(f)] = &program.code[..] else { panic!() };
+    let ir = IR::try_from(&Program { docs: vec![], code: program.code[..1].to_vec() }).unwrap();
+    let chunk = lower_function(&ir, f).unwrap();
+    assert!(!format!("{:?}", chunk.code).contains("IntConst"), "{:?}", chunk.code);
+}
+
+#[test]
+fn self_makes_the_struct_of_its_impl() {
+    let ir = IR::try_from(&parse(
+        "struct Point{x: Int}
+impl Point {
+    const X: Int = 1;
+    fn new() -> Self { Self{x: Self::X} }
+    fn named() -> Self { Point{x: Self::X} }
+}",
+    ))
+    .unwrap();
+    let body = |name: &str| {
+        let (_, function) = ir.impls[0].funcs.iter().find(|(function, _)| function == name).unwrap();
+        format!("{:?}", function.body.code)
+    };
+    assert_eq!(body("new"), body("named"));
+    // a `Self` not followed by `::` or `{` is still an error
+    assert!(!parse_errors("struct P{} impl P { fn f() -> Int { Self } }").is_empty());
+    assert!(!parse_errors("struct P{} impl P { fn f() { if Self {} } }").is_empty());
+}
+
+#[test]
+fn statics_parse_and_start_with_their_value() {
+    let program = parse("/// how many so far\npub static mut COUNT: u8 = 2 * LIMIT; static NAME: String = \"calc\"; const LIMIT: u8 = 3;");
+    let Declaration::StaticDecl(count) = &program.code[0] else { panic!() };
+    assert_eq!((count.name.lexeme.as_str(), count.visibility, count.mutable, count.docs.len()), ("COUNT", Visibility::Public, true, 1));
+    let Declaration::StaticDecl(name) = &program.code[1] else { panic!() };
+    assert!(!name.mutable);
+    let ir = IR::try_from(&program).unwrap();
+    assert_eq!(ir.statics["COUNT"].value, int(6, Some(IntSuffix::U8)));
+    assert_eq!(ir.statics["NAME"].value, ValueAccess::StringConst("calc".into()));
+    assert!(!parse_errors("static mut A = 1;").is_empty());
+    for (source, check) in [
+        ("static A: Int = 1; static B: Int = A;", (|err| matches!(err, CompileError::NotConstant(_))) as fn(&CompileError) -> bool),
+        ("static A: Int = 1; const B: Int = A;", |err| matches!(err, CompileError::NotConstant(_))),
+        ("static A: u8 = 256;", |err| matches!(err, CompileError::ConstOverflow(_))),
+        ("static A: Int = 1; const A: Int = 2;", |err| matches!(err, CompileError::DuplicateStatics(names) if names == &["A"])),
+    ] {
+        match IR::try_from(&parse(source)) {
+            Err(err) => assert!(check(&err), "{source:?} gave {err:?}"),
+            Ok(ir) => panic!("{source:?} gave {:?}", ir.statics),
+        }
+    }
+}
+
+#[test]
+fn statics_are_read_and_assigned_by_name() {
+    let statics = parse("static mut COUNT: Int = 0; static LIMIT: Int = 3;");
+    let ir = IR::try_from(&statics).unwrap();
+    let lower = |body: &str| {
+        let program = parse(&format!("fn f(x: Int) {{ {body} }}"));
+        let [Declaration::FunctionDecl(f)] = &program.code[..] else { panic!() };
+        lower_function(&ir, f)
+    };
+    let chunk = lower("COUNT += LIMIT; COUNT = x; let add = || COUNT += 1;").unwrap();
+    match &chunk.code[..3] {
+        [IRCode::StaticAssign(count, Calc::Add(ValueAccess::Static(current), ValueAccess::Static(limit))), IRCode::StaticAssign(again, Calc::Clone(ValueAccess::VarAccess(0))), IRCode::VarCreate(_, Calc::Closure(_, captured))]
+            if [count, current, again] == ["COUNT"; 3] && limit == "LIMIT" && captured.is_empty() => {}
+        other => panic!("{other:#?}"),
+    }
+    assert!(matches!(lower("LIMIT = 1;"), Err(CompileError::AssignToImmutable(name, _)) if name == "LIMIT"));
+    // a local of the same name is used over the static
+    assert!(lower("let mut COUNT = 1; COUNT = 2;").is_ok());
+}
@@ -0,0 +1,47 @@
+//! The lossless tree has to give back exactly the text it was built from, trivia and all.
+
+use calcify::ast::cst::{parse_lossless, SyntaxKind, SyntaxNode};
+
+const SOURCE: &str = "//! a file doc\n\n/// a point\nstruct Point<X> {\n    x: X, // the x\n    y: X\n}\n\nfn go() -> i32 {\n    /* nested /* block */ comment */\n    let mut z: i32 = -3 + 4 * (2 - 1);\n    z = z + Point{x: 1, y: 2}.x;\n    if z > 2 { return z; }\n    \"str\\n\";\n    z\n}\n\t\n";
+
+fn kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+    node.descendants().iter().map(SyntaxNode::kind).collect()
+}
+
+#[test]
+fn printing_the_tree_gives_back_the_source() {
+    let parsed = parse_lossless(SOURCE, 0);
+    assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+    assert_eq!(parsed.cst.to_string(), SOURCE);
+    assert_eq!(parsed.cst.text_range(), (0, SOURCE.len()));
+}
+
+#[test]
+fn text_that_does_not_parse_is_still_kept() {
+    for source in ["fn go() { let = ; }", "struct { ~ }", "  ", "", "fn go() {\n    \"unterminated\n"] {
+        assert_eq!(parse_lossless(source, 0).cst.to_string(), source);
+    }
+}
+
+#[test]
+fn nodes_cover_what_they_were_parsed_from() {
+    let parsed = parse_lossless(SOURCE, 0);
+    let root = &parsed.cst;
+    assert_eq!(root.kind(), SyntaxKind::SourceFile);
+    let items = root.children().iter().map(SyntaxNode::kind).collect::<Vec<_>>();
+    assert_eq!(items, vec![SyntaxKind::StructDecl, SyntaxKind::FunctionDecl]);
+
+    let all = kinds(root);
+    for kind in [SyntaxKind::FieldDecl, SyntaxKind::VarCreation, SyntaxKind::Binary, SyntaxKind::Parens, SyntaxKind::If, SyntaxKind::Return, SyntaxKind::ImReturn, SyntaxKind::FieldAccess, SyntaxKind::StructConstructor] {
+        assert!(all.contains(&kind), "no {kind:?} in {all:?}");
+    }
+
+    let field = root.covering_node(SOURCE.find("x: X").unwrap());
+    assert_eq!(field.kind(), SyntaxKind::FieldDecl);
+    assert_eq!(field.to_string(), "x: X");
+    assert_eq!(field.parent().map(SyntaxNode::kind), Some(SyntaxKind::StructDecl));
+
+    let literal = root.covering_node(SOURCE.find("-3").unwrap());
+    assert_eq!(literal.kind(), SyntaxKind::IntLitteral);
+    assert_eq!(literal.to_string(), "-3");
+}
@@ -0,0 +1,106 @@
+//! Rendered diagnostics, compared against the exact text a user would see.
+
+use calcify::{
+    ast::{decl::Program, parser::TokenStream},
+    diagnostic::{line_column, Diagnostic},
+    scanner::Scanner,
+};
+
+fn render(source: &str) -> String {
+    let (tokens, lex_errors) = Scanner::scan_with_errors(source, 0);
+    let (_, parse_errors) = Program::parse(&mut TokenStream::new(&tokens));
+    lex_errors
+        .iter()
+        .map(Diagnostic::from)
+        .chain(parse_errors.iter().map(Diagnostic::from))
+        .map(|diagnostic| diagnostic.render("test.cal", source))
+        .collect()
+}
+
+#[test]
+fn a_missing_semicolon_points_at_what_came_instead() {
+    let source = "fn go() {\n    let x = 1\n    let y = 2;\n}\n";
+    assert_eq!(
+        render(source),
+        "error: expected `;`, found `let`
+ --> test.cal:3:5
+  |
+3 |     let y = 2;
+  |     ^^^
+"
+    );
+}
+
+#[test]
+fn expectations_are_deduplicated_and_summed_up() {
+    let source = "fn go() {\n    let x = 1 + ;\n}\n";
+    assert_eq!(
+        render(source),
+        "error: expected an expression, found `;`
+ --> test.cal:2:17
+  |
+2 |     let x = 1 + ;
+  |                 ^
+"
+    );
+    assert!(render("fn go() { let x = ; }").starts_with("error: expected a statement, found `;`"));
+}
+
+#[test]
+fn the_end_of_the_file_is_named() {
+    assert_eq!(
+        render("struct Point {x: Int"),
+        "error: expected `,` or `}`, found the end of the file
+ --> test.cal:1:21
+  |
+1 | struct Point {x: Int
+  |                     ^
+"
+    );
+}
+
+#[test]
+fn a_return_type_without_an_arrow_gets_a_note() {
+    assert_eq!(
+        render("fn area() Float {}"),
+        "error: expected `->` or `{`, found `Float`
+ --> test.cal:1:11
+  |
+1 | fn area() Float {}
+  |           ^^^^^
+  = note: a return type needs a `->` in front of it, as in `fn area(..) -> Float`
+"
+    );
+    assert!(render("fn area(): Float {}").contains("= note: the return type goes after a `->`, not a `:`"));
+}
+
+#[test]
+fn a_field_without_a_colon_gets_a_note() {
+    assert_eq!(
+        render("struct Point {\n\tx Int\n}"),
+        "error: expected `:`, found `Int`
+ --> test.cal:2:4
+  |
+2 | \tx Int
+  | \t  ^^^
+  = note: fields are written `name: Type`, add a `:` after `x`
+"
+    );
+    assert!(render("fn go(x Int) {}").contains("= note: parameters are written `name: Type`, add a `:` after `x`"));
+}
+
+#[test]
+fn lex_errors_carry_their_suggestion() {
+    let rendered = render("fn go() { let s = \u{201C}hi\u{201D}; }");
+    assert!(rendered.starts_with("error: "), "{rendered}");
+    assert!(rendered.contains("= help: "), "{rendered}");
+}
+
+#[test]
+fn columns_count_characters() {
+    let source = "let é = 1;\nlet ü = 2;";
+    assert_eq!(line_column(source, 0), (1, 1));
+    assert_eq!(line_column(source, source.find('=').unwrap()), (1, 7));
+    assert_eq!(line_column(source, source.rfind('ü').unwrap()), (2, 5));
+    assert_eq!(line_column(source, source.len()), (2, 11));
+}
@@ -0,0 +1,200 @@
+//! Expressions that used to come out of the parser with the wrong shape, each one is
+//! rendered back as an s-expression so the grouping is easy to read at a glance.
+
+use calcify::{
+    ast::{
+        expresions::{BinaryOp, Expresion, UnaryOp},
+        parser::{Parsable, TokenStream},
+    },
+    scanner::{Scanner, TokenType},
+};
+
+fn parse(source: &str) -> Expresion {
+    let tokens = Scanner::scan(source);
+    let mut stream = TokenStream::new(&tokens);
+    let expr = Expresion::parse(&mut stream).unwrap_or_else(|err| panic!("{source:?} failed to parse: {err:?}"));
+    assert_eq!(
+        stream.peek().map(|token| token.token_type),
+        Some(TokenType::EOF),
+        "{source:?} was not fully parsed"
+    );
+    expr
+}
+
+fn op(binary_op: &BinaryOp) -> &'static str {
+    match binary_op {
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::Mult => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Mod => "%",
+        BinaryOp::Lessthan => "<",
+        BinaryOp::Greaterthan => ">",
+        BinaryOp::GE => ">=",
+        BinaryOp::LE => "<=",
+        BinaryOp::Equal => "==",
+        BinaryOp::NE => "!=",
+        BinaryOp::LogicalAnd => "and",
+        BinaryOp::LogicalOr => "or",
+        BinaryOp::Assign => "=",
+        BinaryOp::AddAssign => "+=",
+        BinaryOp::SubtractAssign => "-=",
+        BinaryOp::MultAssign => "*=",
+        BinaryOp::DivAssign => "/=",
+        BinaryOp::ModAssign => "%=",
+        BinaryOp::BitwiseAndAssign => "&=",
+        BinaryOp::BitwiseOrAssign => "|=",
+        BinaryOp::SHLAssign => "<<=",
+        BinaryOp::SHRAssign => ">>=",
+        BinaryOp::BitwiseOr => "|",
+        BinaryOp::BitwiseAnd => "&",
+        BinaryOp::SHL => "<<",
+        BinaryOp::SHR => ">>",
+        BinaryOp::Range => "..",
+        BinaryOp::RangeInclusive => "..=",
+    }
+}
+
+fn sexpr(expr: &Expresion) -> String {
+    match expr {
+        Expresion::IntLitteral(int) => (int.value as i64).to_string(),
+        Expresion::FloatLitteral(float) => float.value.to_string(),
+        Expresion::VarAccess(name) => name.lexeme.clone(),
+        Expresion::FunctionCall(call) => format!(
+            "(call {}{})",
+            sexpr(&call.function),
+            call.arguments.iter().map(|arg| format!(" {}", sexpr(arg))).collect::<String>()
+        ),
+        Expresion::FieldAcess(access) => format!("(. {} {})", sexpr(&access.expr), access.field.lexeme),
+        Expresion::Index(index) => format!("(index {} {})", sexpr(&index.expr), sexpr(&index.index)),
+        Expresion::Parens(parens) => sexpr(&parens.expr),
+        Expresion::Unary(unary) => format!(
+            "({} {})",
+            match unary.unary_op {
+                UnaryOp::Negate => "neg",
+                UnaryOp::Not => "!",
+            },
+            sexpr(&unary.expr)
+        ),
+        Expresion::Binary(binary) => format!(
+            "({} {} {})",
+            op(&binary.binary_op),
+            sexpr(&binary.lhs),
+            sexpr(&binary.rhs)
+        ),
+        other => panic!("no s-expression for {other:?}"),
+    }
+}
+
+fn check(cases: &[(&str, &str)]) {
+    for (source, expected) in cases {
+        assert_eq!(sexpr(&parse(source)), *expected, "while parsing {source:?}");
+    }
+}
+
+#[test]
+fn minus_after_an_operand_is_subtraction() {
+    check(&[
+        ("x-1", "(- x 1)"),
+        ("x -1", "(- x 1)"),
+        ("g()-1", "(- (call g) 1)"),
+        ("v[k]-1", "(- (index v k) 1)"),
+        ("x.y-1", "(- (. x y) 1)"),
+        ("(x)-1", "(- x 1)"),
+        ("1-1", "(- 1 1)"),
+        ("2.5-1.5", "(- 2.5 1.5)"),
+        ("1-2-3", "(- (- 1 2) 3)"),
+    ]);
+}
+
+#[test]
+fn minus_before_a_number_is_a_negative_literal() {
+    check(&[
+        ("-1", "-1"),
+        ("-2.5", "-2.5"),
+        ("x - -1", "(- x -1)"),
+        ("x*-1", "(* x -1)"),
+        ("g(-1, -2)", "(call g -1 -2)"),
+        ("- -1", "(neg -1)"),
+        ("-x", "(neg x)"),
+        ("-128i8", "-128"),
+        ("-9223372036854775808", "-9223372036854775808"),
+    ]);
+    for (source, negative) in [("-1", true), ("1", false), ("-0", true), ("18446744073709551615", false)] {
+        let tokens = Scanner::scan_with_errors(source, 0).0;
+        match Expresion::parse(&mut TokenStream::new(&tokens)) {
+            Ok(Expresion::IntLitteral(int)) => assert_eq!(int.negative, negative, "{source:?}"),
+            other => panic!("{source:?} gave {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn out_of_range_literals_are_rejected() {
+    for source in ["128i8", "-129i8", "-1u8", "-9223372036854775809"] {
+        // too big for the type at all is caught by the scanner, too big once negated by the parser
+        let (tokens, errors) = Scanner::scan_with_errors(source, 0);
+        assert!(
+            !errors.is_empty() || Expresion::parse(&mut TokenStream::new(&tokens)).is_err(),
+            "{source:?} should not parse"
+        );
+    }
+}
+
+#[test]
+fn comparison_and_bitwise_operators() {
+    check(&[
+        ("x > y", "(> x y)"),
+        ("x >> y", "(>> x y)"),
+        ("x < y", "(< x y)"),
+        ("x << y", "(<< x y)"),
+        ("x & y | z", "(| (& x y) z)"),
+        ("x + y > z >> 1", "(> (+ x y) (>> z 1))"),
+        ("x < y and y > z", "(and (< x y) (> y z))"),
+        ("g<Int>(x) < y", "(< (call g x) y)"),
+        ("a..b + 1", "(.. a (+ b 1))"),
+        ("0..=n or m", "(..= 0 (or n m))"),
+    ]);
+}
+
+#[test]
+fn names_that_start_like_keywords() {
+    check(&[
+        ("a > b", "(> a b)"),
+        ("f()-1", "(- (call f) 1)"),
+        ("format(t, tr)", "(call format t tr)"),
+        ("iffy + elsewhere", "(+ iffy elsewhere)"),
+        ("forty * truth", "(* forty truth)"),
+        ("Traits.mutable", "(. Traits mutable)"),
+        ("x + letter", "(+ x letter)"),
+    ]);
+}
+
+#[test]
+fn compound_assignments_group_like_assignment() {
+    check(&[
+        ("x += 1", "(+= x 1)"),
+        ("x -= -1", "(-= x -1)"),
+        ("x *= y + 2", "(*= x (+ y 2))"),
+        ("x /= y %= 3", "(/= x (%= y 3))"),
+        ("x = y &= z | 1", "(= x (&= y (| z 1)))"),
+        ("x.0 |= y", "(|= (. x 0) y)"),
+        ("x <<= y << 1", "(<<= x (<< y 1))"),
+        ("x >>= y >> 1", "(>>= x (>> y 1))"),
+    ]);
+}
+
+#[test]
+fn operators_scanned_a_greater_than_at_a_time() {
+    check(&[
+        ("a >= b", "(>= a b)"),
+        ("a>=b+1", "(>= a (+ b 1))"),
+        ("a >= b and c <= d", "(and (>= a b) (<= c d))"),
+        ("a >> b > c", "(> (>> a b) c)"),
+    ]);
+    // the parts of one operator can't have anything between them
+    for source in ["a > > b", "a > = b", "a >> = b", "a > >= b"] {
+        let tokens = Scanner::scan(source);
+        assert!(Expresion::parse(&mut TokenStream::new(&tokens)).is_err(), "{source:?} should not parse");
+    }
+}
@@ -0,0 +1,195 @@
+//! `for` loops, parsed and then lowered, the lowered code is run by a small interpreter so
+//! the tests check what the loops do rather than the exact instructions they became.
+
+mod common;
+
+use std::collections::HashMap;
+
+use calcify::{
+    ast::{
+        expresions::{BinaryOp, Expresion},
+        statments::Statment,
+    },
+    ir::{
+        ir_chunk::{Calc, IRChunk, IRCode, ValueAccess},
+        ir_type_data::CompileError,
+    },
+};
+
+use common::{function, lower, parse, parse_errors};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Unit,
+    Int(i64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+fn int(value: &Value) -> i64 {
+    match value {
+        Value::Int(int) => *int,
+        other => panic!("{other:?} is not an int"),
+    }
+}
+
+/// Runs `chunk` with `args` in the first slots and gives back every slot at the end.
+fn run(chunk: &IRChunk, args: Vec<Value>) -> HashMap<u16, Value> {
+    let mut slots = args.into_iter().enumerate().map(|(slot, value)| (slot as u16, value)).collect::<HashMap<_, _>>();
+    let get = |slots: &HashMap<u16, Value>, access: &ValueAccess| match access {
+        ValueAccess::Unit => Value::Unit,
+        ValueAccess::VarAccess(slot) => slots[slot].clone(),
+        ValueAccess::IntConst(int, _) => Value::Int(*int as i64),
+        ValueAccess::True => Value::Bool(true),
+        ValueAccess::False => Value::Bool(false),
+        other => panic!("the tests don't use {other:?}"),
+    };
+    let calc = |slots: &HashMap<u16, Value>, calc: &Calc| {
+        let int = |access| int(&get(slots, access));
+        match calc {
+            Calc::Clone(value) => get(slots, value),
+            Calc::ArrayAcess(array, index) => match get(slots, array) {
+                Value::Array(elements) => elements[int(index) as usize].clone(),
+                other => panic!("{other:?} is not an array"),
+            },
+            Calc::ArrayLen(array) => match get(slots, array) {
+                Value::Array(elements) => Value::Int(elements.len() as i64),
+                other => panic!("{other:?} is not an array"),
+            },
+            Calc::Add(lhs, rhs) => Value::Int(int(lhs) + int(rhs)),
+            Calc::Subtract(lhs, rhs) => Value::Int(int(lhs) - int(rhs)),
+            Calc::Multiply(lhs, rhs) => Value::Int(int(lhs) * int(rhs)),
+            Calc::Div(lhs, rhs) => Value::Int(int(lhs) / int(rhs)),
+            Calc::Mod(lhs, rhs) => Value::Int(int(lhs) % int(rhs)),
+            Calc::EQ(lhs, rhs) => Value::Bool(get(slots, lhs) == get(slots, rhs)),
+            Calc::NE(lhs, rhs) => Value::Bool(get(slots, lhs) != get(slots, rhs)),
+            Calc::LT(lhs, rhs) => Value::Bool(int(lhs) < int(rhs)),
+            Calc::GT(lhs, rhs) => Value::Bool(int(lhs) > int(rhs)),
+            Calc::LE(lhs, rhs) => Value::Bool(int(lhs) <= int(rhs)),
+            Calc::GE(lhs, rhs) => Value::Bool(int(lhs) >= int(rhs)),
+            Calc::Not(value) => Value::Bool(get(slots, value) == Value::Bool(false)),
+            other => panic!("the tests don't use {other:?}"),
+        }
+    };
+    let mut at = 0;
+    for _ in 0..100_000 {
+        let Some(code) = chunk.code.get(at) else {
+            return slots;
+        };
+        at += 1;
+        match code {
+            IRCode::VarCreate(slot, value) | IRCode::VarAssign(slot, value) => {
+                let value = calc(&slots, value);
+                slots.insert(*slot, value);
+            }
+            IRCode::Jmp(to) => at = *to,
+            IRCode::JNE(lhs, rhs) => {
+                if get(&slots, lhs) == get(&slots, rhs) {
+                    at += 1;
+                }
+            }
+            other => panic!("the tests don't use {other:?}"),
+        }
+    }
+    panic!("the code never finished: {:#?}", chunk.code);
+}
+
+/// The value `total` ends up with, it has to be the first local after the parameters.
+fn total(source: &str, args: Vec<Value>) -> i64 {
+    let slot = args.len() as u16;
+    let chunk = lower(source).unwrap_or_else(|err| panic!("{source:?} failed to lower: {err:?}"));
+    int(&run(&chunk, args)[&slot])
+}
+
+#[test]
+fn ranges_parse_below_everything_but_assignment() {
+    let program = parse("fn go() { for i in 1 + 2..n * 3 {} }");
+    let function = function(&program);
+    let Statment::For(for_) = &function.body.statments[0] else { panic!("{:?}", function.body.statments) };
+    assert_eq!(for_.variable.lexeme, "i");
+    let Expresion::Binary(range) = &for_.iterable else { panic!("{:?}", for_.iterable) };
+    assert_eq!(range.binary_op, BinaryOp::Range);
+    assert!(matches!(&*range.lhs, Expresion::Binary(add) if add.binary_op == BinaryOp::Add));
+    assert!(matches!(&*range.rhs, Expresion::Binary(mult) if mult.binary_op == BinaryOp::Mult));
+    assert!(matches!(&*for_.statment, Statment::Block(_)));
+}
+
+#[test]
+fn a_name_before_the_body_is_not_a_struct_constructor() {
+    for source in [
+        "fn go() { for x in xs {} }",
+        "fn go() { for x in 0..=n { x; } }",
+        "fn go() { while going { go(); } }",
+        "fn go() { if ready {} else if set {} }",
+        "fn go() { for x in (Point{x: 1}).x..3 {} }",
+        "fn go() { for x in xs[Point{x: 1}.x] {} }",
+    ] {
+        let errors = parse_errors(source);
+        assert!(errors.is_empty(), "{source:?} failed to parse: {errors:?}");
+    }
+}
+
+#[test]
+fn for_over_a_range() {
+    assert_eq!(total("fn go() { let mut total = 0; for i in 0..5 { total = total + i; } }", vec![]), 10);
+    assert_eq!(total("fn go() { let mut total = 0; for i in 0..=5 { total = total + i; } }", vec![]), 15);
+    assert_eq!(total("fn go() { let mut total = 0; for i in 5..0 { total = total + 1; } }", vec![]), 0);
+    assert_eq!(total("fn go() { let mut total = 0; for i in 5..=5 { total = total + 1; } }", vec![]), 1);
+    assert_eq!(total("fn go() { let mut total = 0; for i in 6..=5 { total = total + 1; } }", vec![]), 0);
+}
+
+#[test]
+fn the_end_of_a_range_is_only_worked_out_once() {
+    let source = "fn go() { let mut total = 0; let mut end = 3; for i in 0..end { end = end + 1; total = total + 1; } }";
+    assert_eq!(total(source, vec![]), 3);
+}
+
+#[test]
+fn for_over_an_array() {
+    let source = "fn go(xs: [Int]) { let mut total = 0; for x in xs { total = total + x; } }";
+    let xs = |elements: &[i64]| Value::Array(elements.iter().copied().map(Value::Int).collect());
+    assert_eq!(total(source, vec![xs(&[1, 2, 3, 4])]), 10);
+    assert_eq!(total(source, vec![xs(&[])]), 0);
+}
+
+#[test]
+fn break_and_continue() {
+    assert_eq!(total("fn go() { let mut total = 0; for i in 0..10 { total = total + 1; break; } }", vec![]), 1);
+    assert_eq!(
+        total("fn go() { let mut total = 0; for i in 0..=9 { total = total + i; continue; total = 100; } }", vec![]),
+        45
+    );
+    assert_eq!(
+        total("fn go() { let mut total = 0; for i in 0..3 { for j in 0..10 { total = total + 1; break; } } }", vec![]),
+        3
+    );
+    assert_eq!(
+        total("fn go() { let mut total = 0; while total < 10 { for j in 0..2 { total = total + 1; } } }", vec![]),
+        10
+    );
+}
+
+#[test]
+fn and_or_only_work_out_the_right_side_when_they_need_to() {
+    // `1 / 0` would stop the interpreter if it was run
+    assert_eq!(total("fn go() { let mut total = 0; let b = false and 1 / 0 == 1; for i in 0..3 { total = total + 1; } }", vec![]), 3);
+    let chunk = lower("fn go() { let a = true or 1 / 0 == 1; let b = false or 2 > 1; let c = true and 1 > 2; }").unwrap();
+    let slots = run(&chunk, vec![]);
+    let values = chunk
+        .code
+        .iter()
+        .filter_map(|code| match code {
+            IRCode::VarCreate(slot, Calc::Clone(ValueAccess::VarAccess(_))) => Some(slots[slot].clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(values, vec![Value::Bool(true), Value::Bool(true), Value::Bool(false)]);
+}
+
+#[test]
+fn loops_that_cant_be_lowered() {
+    assert!(matches!(lower("fn go() { break; }"), Err(CompileError::BreakOutsideLoop(_))));
+    assert!(matches!(lower("fn go() { continue; }"), Err(CompileError::ContinueOutsideLoop(_))));
+    assert!(matches!(lower("fn go() { let r = 0..3; }"), Err(CompileError::RangeOutsideForLoop(_))));
+    assert!(matches!(lower("fn go() { for i in 0..3 {} i; }"), Err(CompileError::UnknownVariable(name, _)) if name == "i"));
+}
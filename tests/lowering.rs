@@ -0,0 +1,252 @@
+//! Golden tests of the code a function body lowers to, one statement kind at a time. Each
+//! body goes in `go` in a program with a few types and impls and is put through
+//! `IR::try_from`, so `p`, `s`, `n` and `xs` are slots 0 to 3.
+
+mod common;
+
+use calcify::ir::ir_type_data::{CompileError, IR};
+
+use common::parse;
+
+const ITEMS: &str = "struct Point{y: Int, x: Int}
+enum Shape{Empty, Circle{radius: Int, center: Point}}
+impl Point {
+    const ORIGIN: Int = 0;
+    fn len(self: Self) -> Int { self.x + Self::ORIGIN }
+    fn new(x: Int) -> Self { Point{x: x, y: 0} }
+}
+";
+
+fn ir(body: &str) -> Result<IR, CompileError> {
+    IR::try_from(&parse(&format!("{ITEMS}fn go(p: Point, s: Shape, n: Int, xs: [Int]) -> Int {{ {body} }}")))
+}
+
+/// One instruction a line, with its index so the jumps can be followed.
+fn render(ir: &IR, function: &str) -> String {
+    ir.function[function]
+        .body
+        .code
+        .iter()
+        .enumerate()
+        .map(|(index, code)| format!("{index}: {code:?}\n"))
+        .collect()
+}
+
+fn check(body: &str, expected: &str) {
+    let ir = ir(body).unwrap_or_else(|err| panic!("{body:?} failed to lower: {err:?}"));
+    assert_eq!(render(&ir, "go"), expected, "while lowering {body:?}");
+}
+
+#[test]
+fn let_statements() {
+    check(
+        "let mut a = n; let Point{x, ..} = p;",
+        "0: VarCreate(4, Clone(VarAccess(2)))
+1: VarCreate(5, FieldAccess(VarAccess(0), 0))
+2: VarCreate(6, Clone(VarAccess(5)))
+",
+    );
+}
+
+#[test]
+fn expression_statements_and_blocks() {
+    check("n + 1;", "0: VarCreate(4, Add(VarAccess(2), IntConst(1, None)))\n");
+    check(
+        "{ let a = 1; a }",
+        "0: VarCreate(4, Clone(IntConst(1, None)))
+1: Return(VarAccess(4))
+",
+    );
+}
+
+#[test]
+fn nested_functions_are_called_by_name() {
+    let ir = ir("fn double(x: Int) -> Int { x * 2 } double(n)").unwrap();
+    assert_eq!(
+        render(&ir, "go"),
+        "0: Call(4, Func(\"double\"), [2])
+1: Return(VarAccess(4))
+"
+    );
+    let body = &ir.function["go"].body.functions["double"].body;
+    assert_eq!(format!("{:?}", body.code), "[VarCreate(1, Multiply(VarAccess(0), IntConst(2, None))), Return(VarAccess(1))]");
+}
+
+#[test]
+fn if_and_if_let() {
+    check(
+        "if n > 0 { 1 } else if let Shape.Circle{radius, ..} s { radius } else { 0 }",
+        "0: VarCreate(4, Clone(Unit))
+1: VarCreate(5, GT(VarAccess(2), IntConst(0, None)))
+2: JNE(VarAccess(5), True)
+3: Jmp(6)
+4: VarAssign(4, Clone(IntConst(1, None)))
+5: Jmp(14)
+6: VarCreate(6, FieldAccess(VarAccess(1), 0))
+7: JNE(VarAccess(6), IntConst(0, None))
+8: Jmp(13)
+9: VarCreate(7, FieldAccess(VarAccess(1), 2))
+10: VarCreate(8, Clone(VarAccess(7)))
+11: VarAssign(4, Clone(VarAccess(8)))
+12: Jmp(14)
+13: VarAssign(4, Clone(IntConst(0, None)))
+14: Return(VarAccess(4))
+",
+    );
+}
+
+#[test]
+fn loops_with_break_and_continue() {
+    check(
+        "while n > 0 { n; }",
+        "0: VarCreate(4, GT(VarAccess(2), IntConst(0, None)))
+1: JNE(VarAccess(4), True)
+2: Jmp(4)
+3: Jmp(0)
+",
+    );
+    check(
+        "while true { break; }",
+        "0: JNE(True, True)
+1: Jmp(4)
+2: Jmp(4)
+3: Jmp(0)
+",
+    );
+    check(
+        "for x in xs { continue; }",
+        "0: VarCreate(4, Clone(IntConst(0, None)))
+1: VarCreate(5, ArrayLen(VarAccess(3)))
+2: VarCreate(6, LT(VarAccess(4), VarAccess(5)))
+3: JNE(VarAccess(6), True)
+4: Jmp(9)
+5: VarCreate(7, ArrayAcess(VarAccess(3), VarAccess(4)))
+6: VarAssign(4, Add(VarAccess(4), IntConst(1, None)))
+7: Jmp(2)
+8: Jmp(2)
+",
+    );
+}
+
+#[test]
+fn return_and_panic() {
+    check(
+        "return p.len();",
+        "0: Call(4, Func(\"Point::len\"), [0])
+1: Return(VarAccess(4))
+",
+    );
+    check("panic;", "0: Panic\n");
+}
+
+#[test]
+fn match_with_an_or_pattern_and_a_guard() {
+    check(
+        "match s { Shape.Circle{radius: 1 | 2, ..} => 1, Shape.Circle{center, ..} if center.x > 0 => 2, _ => 3 }",
+        "0: VarCreate(4, Clone(Unit))
+1: VarCreate(5, FieldAccess(VarAccess(1), 0))
+2: JNE(VarAccess(5), IntConst(0, None))
+3: Jmp(12)
+4: VarCreate(6, FieldAccess(VarAccess(1), 2))
+5: JNE(VarAccess(6), IntConst(1, None))
+6: Jmp(8)
+7: Jmp(10)
+8: JNE(VarAccess(6), IntConst(2, None))
+9: Jmp(12)
+10: VarAssign(4, Clone(IntConst(1, None)))
+11: Jmp(25)
+12: VarCreate(7, FieldAccess(VarAccess(1), 0))
+13: JNE(VarAccess(7), IntConst(0, None))
+14: Jmp(23)
+15: VarCreate(8, FieldAccess(VarAccess(1), 1))
+16: VarCreate(9, Clone(VarAccess(8)))
+17: VarCreate(10, FieldAccess(VarAccess(9), 0))
+18: VarCreate(11, GT(VarAccess(10), IntConst(0, None)))
+19: JNE(VarAccess(11), True)
+20: Jmp(23)
+21: VarAssign(4, Clone(IntConst(2, None)))
+22: Jmp(25)
+23: VarAssign(4, Clone(IntConst(3, None)))
+24: Jmp(25)
+25: Return(VarAccess(4))
+",
+    );
+}
+
+#[test]
+fn constructors_fields_and_indexes() {
+    check(
+        "let mut q = Point::new(n); q.y += 1; let mut ys = [1, 2]; ys[0] = q.y; Shape::Circle{center: q, radius: 1}; 0",
+        "0: Call(4, Func(\"Point::new\"), [2])
+1: VarCreate(5, Clone(VarAccess(4)))
+2: VarCreate(6, FieldAccess(VarAccess(5), 1))
+3: FieldAssign(5, 1, Add(VarAccess(6), IntConst(1, None)))
+4: VarCreate(7, Array([IntConst(1, None), IntConst(2, None)], None))
+5: VarCreate(8, Clone(VarAccess(7)))
+6: VarCreate(9, FieldAccess(VarAccess(5), 1))
+7: IndexAssign(8, IntConst(0, None), Clone(VarAccess(9)))
+8: VarCreate(10, Alloc(3))
+9: FieldAssign(10, 0, Clone(IntConst(0, None)))
+10: FieldAssign(10, 1, Clone(VarAccess(5)))
+11: FieldAssign(10, 2, Clone(IntConst(1, None)))
+12: Return(IntConst(0, None))
+",
+    );
+}
+
+#[test]
+fn methods_are_lowered_with_their_impl() {
+    let ir = ir("0").unwrap();
+    let len = ir.impls.iter().flat_map(|impl_| &impl_.funcs).find(|(name, _)| name == "len").expect("`len` is in the impl");
+    let code = len.1.body.code.iter().enumerate().map(|(index, code)| format!("{index}: {code:?}\n")).collect::<String>();
+    assert_eq!(
+        code,
+        "0: VarCreate(1, FieldAccess(VarAccess(0), 0))
+1: VarCreate(2, Add(VarAccess(1), IntConst(0, None)))
+2: Return(VarAccess(2))
+"
+    );
+}
+
+#[test]
+fn names_that_are_not_there() {
+    assert!(matches!(ir("p.z"), Err(CompileError::NoFieldWithThatName(..))), "{:?}", ir("p.z"));
+    assert!(matches!(ir("Point::nope(p)"), Err(CompileError::NoFunctionWithThatName(..))));
+    assert!(matches!(ir("Point{x: 1}; 0"), Err(CompileError::MissingFields(_, fields, _)) if fields == ["y"]));
+    assert!(matches!(ir("let a = n; a.x"), Err(CompileError::TypeNotKnown(_))));
+}
+
+#[test]
+fn fields_of_array_elements_and_tuple_elements() {
+    check(
+        "let a: [Point] = [p]; a[0].x",
+        "0: VarCreate(4, Array([VarAccess(0)], None))
+1: VarCreate(5, Clone(VarAccess(4)))
+2: VarCreate(6, ArrayAcess(VarAccess(5), IntConst(0, None)))
+3: VarCreate(7, FieldAccess(VarAccess(6), 0))
+4: Return(VarAccess(7))
+",
+    );
+    check(
+        "[p, p][1].y",
+        "0: VarCreate(4, Array([VarAccess(0), VarAccess(0)], None))
+1: VarCreate(5, ArrayAcess(VarAccess(4), IntConst(1, None)))
+2: VarCreate(6, FieldAccess(VarAccess(5), 1))
+3: Return(VarAccess(6))
+",
+    );
+    check(
+        "let t = (n, p); t.1.x",
+        "0: VarCreate(4, Alloc(2))
+1: FieldAssign(4, 0, Clone(VarAccess(2)))
+2: FieldAssign(4, 1, Clone(VarAccess(0)))
+3: VarCreate(5, Clone(VarAccess(4)))
+4: VarCreate(6, FieldAccess(VarAccess(5), 1))
+5: VarCreate(7, FieldAccess(VarAccess(6), 0))
+6: Return(VarAccess(7))
+",
+    );
+    // an element that isn't a struct has no fields
+    assert!(matches!(ir("xs[0].x"), Err(CompileError::TypeNotKnown(_))));
+    assert!(matches!(ir("let t = (n, 1); t.0.x"), Err(CompileError::TypeNotKnown(_))));
+}
@@ -0,0 +1,123 @@
+//! `match` expressions, how they parse and what the exhaustiveness check makes of them.
+
+mod common;
+
+use calcify::{
+    ast::{
+        expresions::Expresion,
+        statments::{Pattern, Statment},
+    },
+    ir::ir_type_data::{CompileError, IR},
+};
+
+use common::{function, parse};
+
+const TYPES: &str = "
+struct Point{x: Int, y: Int}
+enum Shape{Circle{center: Point, radius: Int}, Rect{min: Point, max: Point}, Empty}
+enum Maybe<T>{Some{value: T}, None}
+";
+
+/// Checks the matches in `body`, put in a function after the types above.
+fn check(body: &str) -> Result<(), CompileError> {
+    let types = IR::try_from(&parse(TYPES)).expect("the types are fine");
+    let program = parse(&format!("fn go(shape: Shape, maybe: Maybe<Shape>) {{ {body} }}"));
+    calcify::ir::ir_match_check::check_function(&types, function(&program))
+}
+
+#[test]
+fn arms_with_guards_blocks_and_commas() {
+    let program = parse(
+        "fn go() {
+            match shape {
+                Shape.Circle{radius: r} if r > 10 => 1,
+                Shape.Circle{center: Point{x, y: _}} => { x }
+                Shape.Rect{} => 3,
+                _ => 4,
+            }
+            let y = match maybe { Maybe.Some{value: v} => v, other => 0 };
+        }",
+    );
+    let function = function(&program);
+    let Statment::Expresion(Expresion::Match(match_)) = &function.body.statments[0] else {
+        panic!("{:?}", function.body.statments[0])
+    };
+    assert_eq!(match_.arms.len(), 4);
+    assert!(match_.arms[0].guard.is_some());
+    assert!(matches!(match_.arms[1].value, Expresion::Block(_)));
+    assert!(matches!(match_.arms[2].pattern, Pattern::Destructure(_)));
+    assert!(matches!(match_.arms[3].pattern, Pattern::Wildcard(_)));
+    assert!(matches!(function.body.statments[1], Statment::VarCreation(_)));
+}
+
+#[test]
+fn every_varient_handled() {
+    for body in [
+        "match shape { Shape.Circle{} => 1, Shape.Rect{} => 2, Shape.Empty => 3 }",
+        "match shape { Shape.Circle{} => 1, _ => 2 }",
+        "match shape { anything => 1 }",
+        "match shape { Shape{} => 1 }",
+        "match maybe { Maybe.Some{value: Shape.Circle{}} => 1, Maybe.Some{value: s} => 2, Maybe.None => 3 }",
+        "match maybe { Maybe.Some{value: Shape.Circle{}} => 1, Maybe.Some{value: Shape.Rect{}} => 2, Maybe.Some{value: Shape.Empty} => 2, Maybe.None => 3 }",
+        "return match shape { Shape.Circle{} => 1, Shape.Rect{} => 2, Shape.Empty => 3 };",
+    ] {
+        assert!(check(body).is_ok(), "{body:?}: {:?}", check(body));
+    }
+}
+
+#[test]
+fn missing_varients_are_named() {
+    let missing = |body: &str| match check(body) {
+        Err(CompileError::NonExhaustiveMatch(missing, _)) => missing,
+        other => panic!("{body:?} gave {other:?}"),
+    };
+    assert_eq!(missing("match shape { Shape.Circle{} => 1, Shape.Empty => 3 }"), vec!["Shape.Rect"]);
+    assert_eq!(missing("match shape { Shape.Circle{} => 1 }"), vec!["Shape.Empty", "Shape.Rect"]);
+    // a guard might not hold, so its arm doesn't cover anything
+    assert_eq!(
+        missing("match shape { Shape.Circle{} if true => 1, Shape.Rect{} => 2, Shape.Empty => 3 }"),
+        vec!["Shape.Circle"]
+    );
+    assert_eq!(
+        missing("match maybe { Maybe.Some{value: Shape.Circle{}} => 1, Maybe.None => 3 }"),
+        vec!["Maybe.Some"]
+    );
+    assert_eq!(missing("match shape {}"), vec!["_"]);
+    // matches are checked wherever they are
+    assert_eq!(missing("while true { let x = (match shape { Shape.Empty => 1 }); }").len(), 2);
+}
+
+#[test]
+fn arms_hidden_by_earlier_ones() {
+    for body in [
+        "match shape { _ => 1, Shape.Circle{} => 2 }",
+        "match shape { Shape.Circle{} => 1, Shape.Circle{radius: r} => 2, _ => 3 }",
+        "match shape { Shape.Circle{} => 1, Shape.Rect{} => 2, Shape.Empty => 3, other => 4 }",
+        "match maybe { Maybe.Some{value: s} => 1, Maybe.Some{value: Shape.Empty} => 2, _ => 3 }",
+    ] {
+        assert!(matches!(check(body), Err(CompileError::UnreachableMatchArm(_))), "{body:?}: {:?}", check(body));
+    }
+    // an arm with a guard doesn't hide the same pattern after it
+    assert!(check("match shape { Shape.Circle{} if false => 1, Shape.Circle{} => 2, _ => 3 }").is_ok());
+}
+
+#[test]
+fn patterns_that_name_the_wrong_thing() {
+    assert!(matches!(
+        check("match shape { Shape.Square{} => 1, _ => 2 }"),
+        Err(CompileError::NoVarientWithThatName(type_, varient, _)) if type_ == "Shape" && varient == "Square"
+    ));
+    assert!(matches!(
+        check("match shape { Shape.Circle{diameter: d} => 1, _ => 2 }"),
+        Err(CompileError::NoFieldWithThatName(_, field, _)) if field == "diameter"
+    ));
+    assert!(matches!(
+        check("match shape { Shape.Circle{} => 1, Point{} => 2 }"),
+        Err(CompileError::MismatchedPatternType(expected, found, _)) if expected == "Shape" && found == "Point"
+    ));
+    assert!(matches!(
+        check("match shape { Shape.Circle{center: Shape.Empty} => 1, _ => 2 }"),
+        Err(CompileError::MismatchedPatternType(expected, found, _)) if expected == "Point" && found == "Shape"
+    ));
+    assert!(matches!(check("match shape { Circle{} => 1 }"), Err(CompileError::NoTypeWithThatNameFound(_))));
+}
@@ -0,0 +1,266 @@
+//! Programs over several files: finding the files `mod` points to, and linking the modules
+//! into one program with every item named by its full path.
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use calcify::{
+    ast::{
+        decl::{Declaration, Program, Type},
+        expresions::{ConstructorType, Expresion},
+        statments::Statment,
+    },
+    ir::ir_type_data::{self, CompileError, IR},
+    module::Crate,
+};
+
+use common::load;
+
+fn link(files: &[(&str, &str)]) -> Result<Program, CompileError> {
+    let (crate_, diagnostics) = load(files);
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    crate_.link()
+}
+
+fn names(program: &Program) -> Vec<String> {
+    program
+        .code
+        .iter()
+        .map(|declaration| match declaration {
+            Declaration::StructDecl(struct_) => struct_.name.lexeme.clone(),
+            Declaration::EnumDecl(enum_) => enum_.name.lexeme.clone(),
+            Declaration::FunctionDecl(function) => function.sig.name.lexeme.clone(),
+            Declaration::TraitDecl(trait_) => trait_.name.lexeme.clone(),
+            Declaration::ConstDecl(const_) => const_.name.lexeme.clone(),
+            Declaration::StaticDecl(static_) => static_.name.lexeme.clone(),
+            Declaration::TypeAliasDecl(alias) => alias.name.lexeme.clone(),
+            other => panic!("{other:?} shouldn't be left after linking"),
+        })
+        .collect()
+}
+
+fn function<'a>(program: &'a Program, name: &str) -> &'a [Statment] {
+    program
+        .code
+        .iter()
+        .find_map(|declaration| match declaration {
+            Declaration::FunctionDecl(function) if function.sig.name.lexeme == name => {
+                Some(&function.body.statments[..])
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no function {name}"))
+}
+
+#[test]
+fn module_files_are_found_beside_their_parent() {
+    let (crate_, diagnostics) = load(&[
+        ("src/main.cal", "mod shapes; mod util; fn main() {}"),
+        ("src/shapes.cal", "mod circle; struct Point{x: Int}"),
+        ("src/shapes/circle.cal", "struct Circle{radius: Int}"),
+        ("src/util/mod.cal", "fn id(x: Int) -> Int { x }"),
+    ]);
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let paths = crate_.modules.iter().map(|module| module.path.join("::")).collect::<Vec<_>>();
+    assert_eq!(paths, ["", "shapes", "util", "shapes::circle"]);
+    for module in &crate_.modules {
+        assert_eq!(crate_.modules[module.file_id].path, module.path);
+    }
+    assert_eq!(crate_.files[3].path, PathBuf::from("src/shapes/circle.cal"));
+}
+
+#[test]
+fn missing_module_files_and_errors_in_them_are_reported() {
+    let (crate_, diagnostics) = load(&[
+        ("src/main.cal", "mod shapes;\nmod gone;"),
+        ("src/shapes.cal", "struct Point{x: }"),
+    ]);
+    assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+    let rendered = diagnostics.iter().map(|diagnostic| crate_.render(diagnostic)).collect::<Vec<_>>();
+    assert!(rendered.iter().any(|text| text.contains("can't find the file for module `gone`")
+        && text.contains("src/main.cal:2")
+        && text.contains("src/gone.cal")));
+    assert!(rendered.iter().any(|text| text.contains("src/shapes.cal:1")), "{rendered:?}");
+}
+
+#[test]
+fn unreadable_and_non_utf8_module_files_are_reported() {
+    let files = HashMap::from([
+        (PathBuf::from("main.cal"), b"mod locked;\nmod bytes;".to_vec()),
+        (PathBuf::from("bytes.cal"), b"struct P{x: Int}\n\xFF fn f() {}".to_vec()),
+    ]);
+    let mut tried = vec![];
+    let (crate_, diagnostics) = Crate::load_with(Path::new("main.cal"), |path: &Path| {
+        tried.push(path.to_path_buf());
+        match path.to_str() {
+            Some("locked.cal") => Err(io::Error::new(io::ErrorKind::PermissionDenied, "no you don't")),
+            _ => files.get(path).cloned().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound)),
+        }
+    })
+    .unwrap();
+    // an error that isn't the file being missing is the end of looking for it
+    assert!(!tried.contains(&PathBuf::from("locked/mod.cal")), "{tried:?}");
+    assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+    let rendered = diagnostics.iter().map(|diagnostic| crate_.render(diagnostic)).collect::<Vec<_>>();
+    assert!(rendered[0].contains("can't read the file for module `locked`") && rendered[0].contains("no you don't"), "{}", rendered[0]);
+    assert!(rendered[1].contains("not valid UTF-8") && rendered[1].contains("bytes.cal:2"), "{}", rendered[1]);
+    assert_eq!(diagnostics[1].span.file_id, 1);
+    assert_eq!(crate_.modules[1].program.code.len(), 2);
+}
+
+#[test]
+fn items_are_named_by_their_path() {
+    let program = link(&[
+        ("main.cal", "mod shapes; use shapes::Point; fn main(p: Point, c: shapes::circle::Circle) {}"),
+        ("shapes.cal", "mod circle; pub struct Point{x: Int} fn origin() -> Point { Point{x: 0} }"),
+        ("shapes/circle.cal", "use super_point::Point; mod super_point; pub struct Circle{center: Point}"),
+        ("shapes/circle/super_point.cal", "pub struct Point{y: Int}"),
+    ])
+    .unwrap();
+    assert_eq!(
+        names(&program),
+        ["main", "shapes::Point", "shapes::origin", "shapes::circle::Circle", "shapes::circle::super_point::Point"]
+    );
+    let Declaration::FunctionDecl(main) = &program.code[0] else { panic!() };
+    let types = main
+        .sig
+        .parameters
+        .iter()
+        .map(|(_, type_)| match type_ {
+            Type::PhysicalType(solid) => solid.name.lexeme.clone(),
+            other => panic!("{other:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(types, ["shapes::Point", "shapes::circle::Circle"]);
+    let Declaration::StructDecl(circle) = &program.code[3] else { panic!() };
+    let Type::PhysicalType(center) = &circle.fields[0].type_of else { panic!() };
+    assert_eq!(center.name.lexeme, "shapes::circle::super_point::Point");
+}
+
+#[test]
+fn functions_and_constructors_through_a_module() {
+    let program = link(&[
+        ("main.cal", "mod geo; fn main() { let p = geo::Point{x: 1}; geo::origin(); let s = geo::Shape::Dot{}; }"),
+        ("geo.cal", "pub struct Point{pub x: Int} pub enum Shape{Dot{}} pub fn origin() -> Point { helper() } fn helper() -> Point { Point{x: 0} }"),
+    ])
+    .unwrap();
+    let main = function(&program, "main");
+    let Statment::VarCreation(point) = &main[0] else { panic!() };
+    let Statment::Expresion(Expresion::Constructor(constructor)) = &*point.value else { panic!("{:?}", point.value) };
+    let ConstructorType::StructConstructor(struct_) = &constructor.object_to_construct else { panic!() };
+    assert_eq!(struct_.struct_name.lexeme, "geo::Point");
+    let Statment::Expresion(Expresion::FunctionCall(call)) = &main[1] else { panic!("{:?}", main[1]) };
+    assert!(matches!(&*call.function, Expresion::VarAccess(name) if name.lexeme == "geo::origin"));
+    let Statment::VarCreation(shape) = &main[2] else { panic!() };
+    let Statment::Expresion(Expresion::Constructor(constructor)) = &*shape.value else { panic!() };
+    let ConstructorType::EnumConstructor(enum_) = &constructor.object_to_construct else { panic!() };
+    assert_eq!(enum_.enum_name.lexeme, "geo::Shape");
+    assert!(enum_.path.is_empty());
+    // inside its own module a function is found by its name alone
+    let Statment::ImReturn(value) = &function(&program, "geo::origin")[0] else { panic!() };
+    let Statment::Expresion(Expresion::FunctionCall(call)) = &**value else { panic!() };
+    assert!(matches!(&*call.function, Expresion::VarAccess(name) if name.lexeme == "geo::helper"));
+}
+
+#[test]
+fn constants_through_a_module() {
+    let program = link(&[
+        ("main.cal", "mod geo; const TWICE: Int = geo::LIMIT * 2; fn main() -> Int { geo::LIMIT }"),
+        ("geo.cal", "pub const LIMIT: Int = BASE + 1; const BASE: Int = 9;"),
+    ])
+    .unwrap();
+    assert_eq!(names(&program), ["TWICE", "main", "geo::LIMIT", "geo::BASE"]);
+    let Statment::ImReturn(value) = &function(&program, "main")[0] else { panic!() };
+    assert!(matches!(&**value, Statment::Expresion(Expresion::VarAccess(name)) if name.lexeme == "geo::LIMIT"));
+    let Declaration::ConstDecl(limit) = &program.code[2] else { panic!() };
+    let Expresion::Binary(sum) = &limit.value else { panic!() };
+    assert!(matches!(&*sum.lhs, Expresion::VarAccess(name) if name.lexeme == "geo::BASE"));
+    assert!(matches!(
+        link(&[("main.cal", "mod geo; const A: Int = geo::BASE;"), ("geo.cal", "const BASE: Int = 9;")]),
+        Err(CompileError::PrivateItem(name, _, _)) if name == "geo::BASE"
+    ));
+}
+
+#[test]
+fn statics_through_a_module() {
+    let program = link(&[("main.cal", "mod stats; fn main() -> Int { stats::SEEN }"), ("stats.cal", "pub static SEEN: Int = 4;")]).unwrap();
+    assert_eq!(names(&program), ["main", "stats::SEEN"]);
+    let Statment::ImReturn(value) = &function(&program, "main")[0] else { panic!() };
+    assert!(matches!(&**value, Statment::Expresion(Expresion::VarAccess(name)) if name.lexeme == "stats::SEEN"));
+}
+
+#[test]
+fn locals_and_generics_are_left_alone() {
+    let program = link(&[
+        ("main.cal", "mod geo; use geo::make; fn main<T>(make: T) -> T { let f = make; f }"),
+        ("geo.cal", "pub fn make() {}"),
+    ])
+    .unwrap();
+    let Declaration::FunctionDecl(main) = &program.code[0] else { panic!() };
+    let Type::PhysicalType(out) = &main.sig.out else { panic!() };
+    assert_eq!(out.name.lexeme, "T");
+    let Statment::VarCreation(f) = &main.body.statments[0] else { panic!() };
+    assert!(matches!(&*f.value, Statment::Expresion(Expresion::VarAccess(name)) if name.lexeme == "make"));
+}
+
+#[test]
+fn the_same_name_in_two_modules_is_fine_in_one_it_isnt() {
+    assert!(link(&[("main.cal", "mod a; struct Point{} fn Point() {}"), ("a.cal", "struct Point{}")]).is_ok());
+    assert!(matches!(
+        link(&[("main.cal", "mod a;"), ("a.cal", "struct Point{} enum Point{}")]),
+        Err(CompileError::DuplicateNames(module, names)) if module == "a" && names == ["Point"]
+    ));
+    // what a `use` brings in clashes with what's declared there
+    assert!(matches!(
+        link(&[("main.cal", "mod a; use a::Point; struct Point{}"), ("a.cal", "pub struct Point{}")]),
+        Err(CompileError::DuplicateNames(module, names)) if module.is_empty() && names == ["Point"]
+    ));
+}
+
+#[test]
+fn paths_that_lead_nowhere() {
+    for (files, path) in [
+        (&[("main.cal", "mod a; use a::Nope;"), ("a.cal", "")][..], "a::Nope"),
+        (&[("main.cal", "use b::Point;")][..], "b::Point"),
+        (&[("main.cal", "mod a; fn main(p: a::Nope) {}"), ("a.cal", "")][..], "a::Nope"),
+        (&[("main.cal", "mod a; fn main() { a::nope(); }"), ("a.cal", "")][..], "a::nope"),
+        // a module only sees the root's items through a path or a `use`
+        (&[("main.cal", "mod a; struct Point{}"), ("a.cal", "fn f(p: Point) {}")][..], "Point"),
+    ] {
+        assert!(
+            matches!(link(files), Err(CompileError::UnresolvedPath(found, _)) if found == path),
+            "{files:?}: {:?}",
+            link(files)
+        );
+    }
+    // the root's items are there through `crate`, and imports can go through imports
+    let program = link(&[
+        ("main.cal", "mod a; mod b; pub(module) struct Point{}"),
+        ("a.cal", "use b::Point; fn f(p: Point) {}"),
+        ("b.cal", "use crate::Point;"),
+    ])
+    .unwrap();
+    let Declaration::FunctionDecl(f) = &program.code[1] else { panic!() };
+    assert!(matches!(&f.sig.parameters[0].1, Type::PhysicalType(point) if point.name.lexeme == "Point"));
+    // imports that only go through each other never find anything
+    assert!(matches!(
+        link(&[("main.cal", "mod a; mod b;"), ("a.cal", "use b::P;"), ("b.cal", "use a::P;")]),
+        Err(CompileError::UnresolvedPath(..))
+    ));
+}
+
+#[test]
+fn linked_crates_become_ir() {
+    let (crate_, _) = load(&[
+        ("main.cal", "mod shapes; use shapes::Shape; struct Drawing{shape: Shape}"),
+        ("shapes.cal", "pub enum Shape{Dot{}, Big{}} struct Shape2{inner: Shape}"),
+    ]);
+    let ir = IR::try_from(&crate_).unwrap();
+    assert!(ir.enums.contains_key("shapes::Shape"));
+    assert!(matches!(&ir.structs["Drawing"].fields["shape"], ir_type_data::Type::Actual(shape) if shape.name == "shapes::Shape"));
+}
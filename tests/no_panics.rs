@@ -0,0 +1,133 @@
+//! Property test: scanning and parsing must never panic, whatever the input.
+//!
+//! Inputs are every prefix of a valid program, random soups of the language's own tokens,
+//! which get much further into the parser than random text does, and plain random bytes.
+//! Set `CALCIFY_FUZZ_CASES` to run more than the default number of random cases.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use calcify::{
+    ast::{cst::parse_lossless, decl::Program, parser::TokenStream},
+    scanner::Scanner,
+};
+
+const PROGRAM: &str = r#"//! a file
+mod shapes;
+use shapes::circle::Circle;
+/// a point
+pub struct Point<T:Add>{pub x: T, pub(module) y: T}
+pub const LIMIT: u8 = 1 << 4 + MIN;
+type Pair<T: Add> = Point<T>;
+enum Shape{
+    Circle{center: Point<Float>, radius: Float},
+    Rect{min: Point<Float>, max: Point<Float>},
+}
+trait Area{ fn area(self: Self) -> Float }
+impl Area for Shape {
+    const SIDES: Int = Self::MIN * 2;
+    fn area(self: Self) -> Float {
+        let mut total = 0.0;
+        if let Shape.Circle{radius: r} self {
+            total = 3.14159 * r * r;
+        } else if total >= 1 { return -1.5e3; } else { panic; }
+        while total >= 100 { total = total - 1; total *= 2; t.0 <<= 1; continue; }
+        for i in 0..=3 { for x in xs { break; } }
+        let n = match self { Shape.Circle{radius: r} if r > 1 => r, _ => { 0 } };
+        let whole @ Point{x: 0 | -1, ..} = p;
+        let k = match n { 1 | 2 => 'a', "s" => true, _ => false };
+        let f: |Int, Int|: Int = geo::add;
+        let c: shapes::Circle = shapes::Circle::new(geo::Point{x: 1});
+        let g = |x: Int|: Int x + total;
+        let xs = [1, 2, 3]@arena;
+        let (a, (b,)): (Int, (Float,)) = (1, (2.0,)); t.0.1;
+        xs[0].len() + Shape::Rect{min: p, max: q}:(Area + Debug) + add<Int>(1, 'c', "s\n")
+    }
+}
+"#;
+
+const VOCABULARY: &[&str] = &[
+    "fn", "struct", "enum", "trait", "impl", "for", "let", "mut", "if", "else", "while", "return",
+    "continue", "break", "panic", "in", "..", "..=", "Self", "true", "false", "and", "or", "match", "=>", "_", "const", "static", "type", "mod", "use", "pub", "module", "x", "Foo",
+    "1", "2.5", "-", "+", "*", "/", "%", "<", ">", "<=", ">=", "==", "!=", "=", "!", "&", "|", "<<", "+=", "-=", "|=", "<<=",
+    ":", "::", ";", ",", ".", "@", "->", "(", ")", "{", "}", "[", "]", "\"s\"", "'c'", "///", "/*",
+    " ", "\n",
+];
+
+/// xorshift, good enough to spread the cases and repeatable from run to run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+fn cases() -> usize {
+    std::env::var("CALCIFY_FUZZ_CASES").ok().and_then(|cases| cases.parse().ok()).unwrap_or(2_000)
+}
+
+fn check(source: &str) {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let tokens = Scanner::scan(source);
+        Program::parse(&mut TokenStream::new(&tokens));
+        let lossless = parse_lossless(source, 0);
+        assert_eq!(lossless.cst.to_string(), source, "the tree lost text");
+    }));
+    if result.is_err() {
+        panic!("panicked on {source:?}");
+    }
+}
+
+#[test]
+fn every_prefix_of_a_program() {
+    for (end, _) in PROGRAM.char_indices() {
+        check(&PROGRAM[..end]);
+    }
+    check(PROGRAM);
+}
+
+#[test]
+fn every_program_with_one_token_dropped() {
+    let tokens = Scanner::scan(PROGRAM);
+    for token in tokens {
+        let mut source = PROGRAM.to_string();
+        source.replace_range(token.span.byte_start..token.span.byte_end, "");
+        check(&source);
+    }
+}
+
+#[test]
+fn random_token_soup() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for _ in 0..cases() {
+        let len = rng.below(60);
+        let source = (0..len)
+            .map(|_| VOCABULARY[rng.below(VOCABULARY.len())])
+            .collect::<Vec<_>>()
+            .join(" ");
+        check(&source);
+    }
+}
+
+#[test]
+fn random_bytes() {
+    let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+    for _ in 0..cases() {
+        let len = rng.below(80);
+        let bytes = (0..len).map(|_| rng.next() as u8).collect::<Vec<_>>();
+        let result = catch_unwind(|| {
+            let (tokens, _) = Scanner::scan_bytes(&bytes, 0);
+            Program::parse(&mut TokenStream::new(&tokens));
+        });
+        if result.is_err() {
+            panic!("panicked on {bytes:?}");
+        }
+        check(&String::from_utf8_lossy(&bytes));
+    }
+}
@@ -0,0 +1,171 @@
+//! The pattern language: wildcards, literals, bindings, `@`, `..` and or-patterns, in a
+//! `match`, an `if let` and a `let`.
+
+mod common;
+
+use calcify::{
+    ast::{
+        expresions::Expresion,
+        statments::{IfType, Pattern, Statment},
+    },
+    ir::{
+        ir_chunk::{Calc, IRCode, ValueAccess},
+        ir_lowering::lower_function,
+        ir_match_check::check_function,
+        ir_pattern::{resolve, IRPattern},
+        ir_type_data::{CompileError, IR},
+    },
+};
+
+use common::{function, parse, parse_errors};
+
+const TYPES: &str = "
+struct Point{x: Int, y: Int}
+enum Shape{Circle{center: Point, radius: Int}, Rect{min: Point, max: Point}, Empty}
+";
+
+/// The pattern of the `let` that `body` starts with.
+fn let_pattern(body: &str) -> Pattern {
+    let program = parse(&format!("fn go() {{ {body} }}"));
+    let Statment::VarCreation(var_creation) = &function(&program).body.statments[0] else { panic!("{body:?}") };
+    var_creation.pattern.clone()
+}
+
+fn types() -> IR {
+    IR::try_from(&parse(TYPES)).expect("the types are fine")
+}
+
+/// Checks the patterns in `body`, put in a function after the types above.
+fn check(body: &str) -> Result<(), CompileError> {
+    let program = parse(&format!("fn go(shape: Shape, point: Point, n: Int) {{ {body} }}"));
+    check_function(&types(), function(&program))
+}
+
+#[test]
+fn every_kind_of_pattern_parses() {
+    assert!(matches!(let_pattern("let _ = 1;"), Pattern::Wildcard(_)));
+    assert!(matches!(let_pattern("let mut x = 1;"), Pattern::Binding(_)));
+    assert!(matches!(let_pattern("let -3 = 1;"), Pattern::Literal(Expresion::IntLitteral(int)) if int.value as i64 == -3));
+    assert!(matches!(let_pattern("let 'a' = 1;"), Pattern::Literal(Expresion::CharLitteral(_))));
+    assert!(matches!(let_pattern("let \"hi\" = 1;"), Pattern::Literal(Expresion::StringLitteral(_))));
+    assert!(matches!(let_pattern("let true = 1;"), Pattern::Literal(Expresion::True(_))));
+    let Pattern::At(at) = let_pattern("let whole @ Point{x, ..} = p;") else { panic!() };
+    assert_eq!(at.name.lexeme, "whole");
+    let Pattern::Destructure(destructure) = *at.pattern else { panic!() };
+    assert!(destructure.rest);
+    assert_eq!(destructure.constructor.len(), 1);
+    let Pattern::Or(alternatives) = let_pattern("let 1 | 2 | n @ 3 = 1;") else { panic!() };
+    assert_eq!(alternatives.len(), 3);
+    assert!(matches!(alternatives[2], Pattern::At(_)));
+}
+
+#[test]
+fn patterns_in_if_let_and_match() {
+    let program = parse(
+        "fn go() {
+            if let Shape.Circle{radius: 0 | 1, ..} | Shape.Empty shape {}
+            match n { 0 => 1, -1 | 1 => 2, big @ _ => big };
+        }",
+    );
+    let Statment::If(if_) = &function(&program).body.statments[0] else { panic!() };
+    assert!(matches!(&if_.conditionals_and_statments[0].0, IfType::IfLet { pattern, .. } if matches!(**pattern, Pattern::Or(_))));
+    let Statment::Expresion(Expresion::Match(match_)) = &function(&program).body.statments[1] else { panic!("{:?}", function(&program).body.statments) };
+    assert!(matches!(match_.arms[1].pattern, Pattern::Or(_)));
+    assert!(matches!(match_.arms[2].pattern, Pattern::At(_)));
+}
+
+#[test]
+fn rest_has_to_come_last() {
+    let errors = parse_errors("fn go() { let Point{.., x} = p; }");
+    assert_eq!(errors[0].note.as_deref(), Some("`..` has to be the last thing in a pattern"), "{errors:?}");
+}
+
+#[test]
+fn resolved_fields_are_in_name_order() {
+    let ir = types();
+    let pattern = resolve(&ir, &let_pattern("let Point{y: 1, x} = p;"), None).unwrap();
+    assert_eq!(
+        pattern,
+        IRPattern::Struct(
+            "Point".to_string(),
+            vec![
+                IRPattern::Binding("x".to_string(), Box::new(IRPattern::Wildcard)),
+                IRPattern::Literal(ValueAccess::IntConst(1, None)),
+            ]
+        )
+    );
+    let pattern = resolve(&ir, &let_pattern("let s @ Shape.Rect{..} | s @ Shape.Empty = shape;"), None).unwrap();
+    assert_eq!(pattern.bindings(), vec!["s"]);
+    assert!(!pattern.is_irrefutable());
+}
+
+#[test]
+fn literals_and_alternatives_in_a_match() {
+    for body in [
+        "match n { 0 => 1, 1 | 2 => 2, _ => 3 }",
+        "match true { true => 1, false => 2 }",
+        "match shape { Shape.Circle{} | Shape.Rect{..} => 1, Shape.Empty => 2 }",
+        "match shape { Shape.Circle{radius: 0} => 1, Shape.Circle{radius: r} => 2, _ => 3 }",
+        "match point { Point{x: 0, ..} | Point{y: 0, ..} => 1, p @ Point{} => 2 }",
+    ] {
+        assert!(check(body).is_ok(), "{body:?}: {:?}", check(body));
+    }
+    let missing = |body: &str| match check(body) {
+        Err(CompileError::NonExhaustiveMatch(missing, _)) => missing,
+        other => panic!("{body:?} gave {other:?}"),
+    };
+    // there are always more numbers
+    assert_eq!(missing("match n { 0 => 1, 1 => 2 }"), vec!["_"]);
+    assert_eq!(missing("match true { true => 1 }"), vec!["false"]);
+    assert_eq!(missing("match shape { Shape.Circle{} | Shape.Empty => 1 }"), vec!["Shape.Rect"]);
+    assert_eq!(missing("match shape { Shape.Circle{radius: 0} => 1, Shape.Rect{} | Shape.Empty => 2 }"), vec!["Shape.Circle"]);
+    for body in [
+        "match n { 0 | 1 => 1, 1 => 2, _ => 3 }",
+        "match true { true | false => 1, _ => 2 }",
+        "match shape { Shape.Circle{} | Shape.Rect{} | Shape.Empty => 1, Shape.Empty => 2 }",
+    ] {
+        assert!(matches!(check(body), Err(CompileError::UnreachableMatchArm(_))), "{body:?}: {:?}", check(body));
+    }
+}
+
+#[test]
+fn patterns_that_dont_fit() {
+    assert!(matches!(
+        check("match shape { Shape.Empty => 1, 3 => 2, _ => 3 }"),
+        Err(CompileError::MismatchedPatternType(expected, found, _)) if expected == "Shape" && found == "integer"
+    ));
+    assert!(matches!(
+        check("match n { 1 => 1, \"one\" => 2, _ => 3 }"),
+        Err(CompileError::MismatchedPatternType(expected, found, _)) if expected == "integer" && found == "string"
+    ));
+    assert!(matches!(
+        check("match shape { Shape.Circle{radius: r} | Shape.Empty => 1, _ => 2 }"),
+        Err(CompileError::MismatchedOrBindings(_))
+    ));
+    assert!(matches!(check("if let Point{z: 1} point {}"), Err(CompileError::NoFieldWithThatName(_, field, _)) if field == "z"));
+}
+
+#[test]
+fn let_patterns_have_to_match_everything() {
+    for body in ["let Point{x, ..} = point;", "let p @ Point{x: _, y} = point;", "let _ = 3;", "let Shape{} = shape;"] {
+        assert!(check(body).is_ok(), "{body:?}: {:?}", check(body));
+    }
+    for body in ["let Shape.Empty = shape;", "let Point{x: 0, y} = point;", "let 1 | 2 = n;", "let true = n == 1;"] {
+        assert!(matches!(check(body), Err(CompileError::RefutablePatternInLet(_))), "{body:?}: {:?}", check(body));
+    }
+}
+
+#[test]
+fn let_destructuring_reads_out_the_fields() {
+    let program = parse(&format!("{TYPES} fn go(point: Point) {{ let whole @ Point{{y, ..}} = point; }}"));
+    let chunk = lower_function(&types(), function(&program)).unwrap();
+    // `point` is slot 0, the value of the `let` is read straight from it
+    let whole = match &chunk.code[..] {
+        [IRCode::VarCreate(whole, Calc::Clone(ValueAccess::VarAccess(0))), IRCode::VarCreate(y_temp, Calc::FieldAccess(ValueAccess::VarAccess(from), 1)), IRCode::VarCreate(_, Calc::Clone(ValueAccess::VarAccess(y_from)))]
+            if from == whole && y_from == y_temp => *whole,
+        other => panic!("{other:#?}"),
+    };
+    assert_eq!(whole, 1);
+    let program = parse(&format!("{TYPES} fn go(n: Int) {{ let 3 = n; }}"));
+    assert!(matches!(lower_function(&types(), function(&program)), Err(CompileError::RefutablePatternInLet(_))));
+}
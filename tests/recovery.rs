@@ -0,0 +1,97 @@
+//! The parser should get past a syntax error and report every one in the file, keeping
+//! whatever did parse.
+
+use calcify::{
+    ast::{
+        decl::{Declaration, Program},
+        parser::{ParseError, TokenStream},
+        statments::Statment,
+    },
+    scanner::{Scanner, TokenType},
+};
+
+fn parse(source: &str) -> (Program, Vec<ParseError>) {
+    let tokens = Scanner::scan(source);
+    Program::parse(&mut TokenStream::new(&tokens))
+}
+
+fn names(program: &Program) -> Vec<String> {
+    program
+        .code
+        .iter()
+        .map(|declaration| match declaration {
+            Declaration::StructDecl(struct_) => struct_.name.lexeme.clone(),
+            Declaration::EnumDecl(enum_) => enum_.name.lexeme.clone(),
+            Declaration::FunctionDecl(function) => function.sig.name.lexeme.clone(),
+            Declaration::TraitDecl(trait_) => trait_.name.lexeme.clone(),
+            Declaration::ImplDecl(_) => "impl".to_string(),
+            Declaration::ModDecl(mod_) => mod_.name.lexeme.clone(),
+            Declaration::UseDecl(_) => "use".to_string(),
+            Declaration::ConstDecl(const_) => const_.name.lexeme.clone(),
+            Declaration::StaticDecl(static_) => static_.name.lexeme.clone(),
+            Declaration::TypeAliasDecl(alias) => alias.name.lexeme.clone(),
+        })
+        .collect()
+}
+
+/// Which token each error was found at, as `line:lexeme`.
+fn found_at(errors: &[ParseError]) -> Vec<String> {
+    errors.iter().map(|err| format!("{}:{}", err.got.line, err.got.lexeme)).collect()
+}
+
+#[test]
+fn every_broken_statement_is_reported() {
+    let (program, errors) = parse(
+        "fn go() {
+    let x = ;
+    let y = 2;
+    x + * y;
+    ;
+    let = 3;
+    y
+}
+",
+    );
+    assert_eq!(found_at(&errors), vec!["1:;", "3:*", "4:;", "5:="]);
+    assert_eq!(names(&program), vec!["go"]);
+    let Declaration::FunctionDecl(go) = &program.code[0] else { panic!() };
+    // the statements in between still parse
+    assert!(matches!(go.body.statments[..], [Statment::VarCreation(_), Statment::ImReturn(_)]));
+}
+
+#[test]
+fn broken_items_are_skipped_up_to_the_next_one() {
+    let (program, errors) = parse(
+        "struct A { x: }
+struct B { x: Int }
+enum { Nope }
+fn ok() {}
+impl B {
+    fn one( {}
+    fn two() {}
+}
+trait T { fn f() }
+",
+    );
+    assert_eq!(found_at(&errors), vec!["0:}", "2:{", "5:{"]);
+    assert_eq!(names(&program), vec!["B", "ok", "impl", "T"]);
+    let Declaration::ImplDecl(impl_) = &program.code[2] else { panic!() };
+    assert_eq!(impl_.funcs.len(), 1);
+}
+
+#[test]
+fn a_missing_semicolon_does_not_eat_the_next_token() {
+    let (_, errors) = parse("fn go() { let x = 1 let y = 2; }");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].expected, vec![TokenType::SemiColon]);
+    assert_eq!(errors[0].got.token_type, TokenType::Let);
+}
+
+#[test]
+fn an_unclosed_block_ends_at_the_end_of_the_file() {
+    let (program, errors) = parse("fn go() { let x = 1;");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].expected, vec![TokenType::RBrace]);
+    assert_eq!(errors[0].got.token_type, TokenType::EOF);
+    assert!(program.code.is_empty());
+}
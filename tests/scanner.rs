@@ -0,0 +1,223 @@
+//! Which words come out of the scanner as keywords and which stay identifiers.
+
+use calcify::{
+    ast::{
+        decl::Program,
+        parser::{TokenExt, TokenStream},
+    },
+    scanner::{ContextualKeyword, IntSuffix, Keyword, LexError, NumberProblem, Scanner, Span, TextEdit, Token, TokenType, TokenValue},
+};
+
+fn types(source: &str) -> Vec<TokenType> {
+    Scanner::scan(source)
+        .into_iter()
+        .map(|token| token.token_type)
+        .filter(|token_type| *token_type != TokenType::EOF)
+        .collect()
+}
+
+#[test]
+fn every_keyword_is_scanned_from_its_spelling() {
+    let keywords = [
+        Keyword::Fn, Keyword::Struct, Keyword::Enum, Keyword::While, Keyword::Let, Keyword::Return,
+        Keyword::SelfType, Keyword::If, Keyword::Impl, Keyword::Else, Keyword::True, Keyword::False,
+        Keyword::Trait, Keyword::And, Keyword::Or, Keyword::Mut, Keyword::For, Keyword::Continue,
+        Keyword::Break, Keyword::Panic, Keyword::Match, Keyword::Loop, Keyword::Const, Keyword::Static,
+        Keyword::Use, Keyword::Mod, Keyword::Pub, Keyword::As, Keyword::In, Keyword::Type, Keyword::Where,
+    ];
+    for keyword in keywords {
+        let spelling = keyword.to_string();
+        assert_eq!(types(&spelling), vec![keyword.token_type()], "{spelling:?}");
+        let token = &Scanner::scan(&spelling)[0];
+        assert_eq!(token.keyword(), Some(keyword));
+    }
+}
+
+#[test]
+fn keyword_prefixes_and_extensions_are_identifiers() {
+    for word in ["f", "fo", "fns", "t", "tr", "tru", "truer", "trai", "Trait", "self", "iff", "i", "e", "el", "matches", "typed", "u", "asm", "r", "_if"] {
+        assert_eq!(types(word), vec![TokenType::Ident], "{word:?}");
+    }
+    assert_eq!(
+        types("f(t)tr;"),
+        vec![TokenType::Ident, TokenType::LParen, TokenType::Ident, TokenType::RParen, TokenType::Ident, TokenType::SemiColon]
+    );
+}
+
+#[test]
+fn reserved_words_cannot_be_names() {
+    let tokens = Scanner::scan("let match = 1;");
+    let mut stream = TokenStream::new(&tokens);
+    stream.next();
+    let err = stream.consume(TokenType::Ident).unwrap_err();
+    assert_eq!(err.got.token_type, TokenType::Match);
+    assert_eq!(err.note.as_deref(), Some("`match` is a keyword and can't be used as a name"));
+
+    let tokens = Scanner::scan("struct type {}");
+    assert!(!Program::parse(&mut TokenStream::new(&tokens)).1.is_empty());
+}
+
+#[test]
+fn contextual_keywords_are_identifiers_with_a_meaning() {
+    let tokens = Scanner::scan("module modules");
+    assert_eq!(tokens[0].token_type, TokenType::Ident);
+    assert!(tokens[0].is_contextual(ContextualKeyword::Module));
+    assert!(!tokens[1].is_contextual(ContextualKeyword::Module));
+    let mut stream = TokenStream::new(&tokens);
+    assert!(stream.peek_consume_contextual(ContextualKeyword::Module).is_ok());
+    assert!(stream.peek_consume_contextual(ContextualKeyword::Module).is_err());
+}
+
+#[test]
+fn compound_assignments_are_one_token() {
+    assert_eq!(
+        types("+= -= *= /= %= &= |= <<= << <= >>="),
+        vec![
+            TokenType::PlusEqual, TokenType::MinusEqual, TokenType::StarEqual, TokenType::SlashEqual,
+            TokenType::PercentEqual, TokenType::AmpersandEqual, TokenType::PipeEqual, TokenType::SHLEqual,
+            TokenType::SHL, TokenType::LessOrEqual, TokenType::RArrow, TokenType::RArrow, TokenType::Equal,
+        ]
+    );
+    // a comment still wins over `/=`
+    assert_eq!(types("x //= 1"), vec![TokenType::Ident]);
+}
+
+const SOURCE: &str = r#"/// docs
+struct Point{x: Int, y: Int}
+fn length(p: Point) -> Int {
+    let s = "a string // not a comment";
+    /* a /* nested */ comment */ let n = 0x1F + 2.5e3; // trailing
+    if p.x >= n { return p.x << 2; }
+    p.y
+}
+"#;
+
+fn summary(tokens: &[Token]) -> Vec<(TokenType, usize, usize, usize, String)> {
+    tokens
+        .iter()
+        .map(|token| (token.token_type, token.span.byte_start, token.span.byte_end, token.line, token.lexeme.clone()))
+        .collect()
+}
+
+/// Edits at every offset of the kinds that tend to join, split or swallow tokens.
+fn edits() -> Vec<TextEdit> {
+    let mut edits = vec![];
+    for start in 0..=SOURCE.len() {
+        for replacement in ["", "x", " ", "\n", "\"", "/*", "*/", "//", "1", ".", "-", "=", "fn"] {
+            edits.push(TextEdit::new(start..start, replacement));
+            if start < SOURCE.len() {
+                edits.push(TextEdit::new(start..(start + 3).min(SOURCE.len()), replacement));
+            }
+        }
+    }
+    edits
+}
+
+#[test]
+fn relexing_an_edit_matches_scanning_from_scratch() {
+    let old = Scanner::scan(SOURCE);
+    let old_lossless = Scanner::scan_lossless(SOURCE, 0);
+    for edit in edits() {
+        let source = edit.apply(SOURCE);
+        assert_eq!(
+            summary(&Scanner::new(&source, 0).relex(old.clone(), &edit)),
+            summary(&Scanner::scan(&source)),
+            "after {edit:?}"
+        );
+        assert_eq!(
+            summary(&Scanner::new(&source, 0).lossless().relex(old_lossless.clone(), &edit)),
+            summary(&Scanner::scan_lossless(&source, 0)),
+            "after {edit:?} keeping trivia"
+        );
+    }
+}
+
+#[test]
+fn scanning_is_lazy() {
+    let mut scanner = Scanner::new("let x = 1; ~", 0);
+    assert_eq!(scanner.next().map(|token| token.token_type), Some(TokenType::Let));
+    assert_eq!(scanner.next().map(|token| token.lexeme), Some("x".to_string()));
+    assert!(scanner.errors().is_empty());
+    let rest = scanner.by_ref().map(|token| token.token_type).collect::<Vec<_>>();
+    assert_eq!(rest, vec![TokenType::Equal, TokenType::Int, TokenType::SemiColon, TokenType::EOF]);
+    assert_eq!(scanner.errors().len(), 1);
+}
+
+fn lex_errors(source: &str) -> Vec<LexError> {
+    Scanner::scan_with_errors(source, 0).1
+}
+
+#[test]
+fn each_kind_of_lex_error_is_reported_with_its_span() {
+    let span = |start, end| Span::new(0, start, end);
+    assert_eq!(lex_errors("x ~ y"), vec![LexError::UnexpectedCharacter { found: '~', span: span(2, 3) }]);
+    assert_eq!(
+        lex_errors("let s = \"abc"),
+        vec![LexError::UnterminatedString { closing: "\"".to_string(), span: span(8, 12) }]
+    );
+    assert_eq!(
+        lex_errors("r#\"abc\""),
+        vec![LexError::UnterminatedString { closing: "\"#".to_string(), span: span(0, 7) }]
+    );
+    assert_eq!(lex_errors("x /* y"), vec![LexError::UnterminatedComment { span: span(2, 6) }]);
+    assert_eq!(
+        lex_errors("\"a\\qb\""),
+        vec![LexError::InvalidEscape { escape: "\\q".to_string(), span: span(2, 4) }]
+    );
+    assert_eq!(
+        lex_errors("'ab'"),
+        vec![LexError::MalformedChar { text: "'ab'".to_string(), span: span(0, 4) }]
+    );
+    assert_eq!(
+        lex_errors("0b102 1.5u8 0b 300u8"),
+        vec![
+            LexError::MalformedNumber { problem: NumberProblem::InvalidDigit { digit: '2', radix: 2 }, span: span(0, 5) },
+            LexError::MalformedNumber { problem: NumberProblem::IntSuffixOnFloat(IntSuffix::U8), span: span(6, 11) },
+            LexError::MalformedNumber { problem: NumberProblem::MissingDigits { radix: 2 }, span: span(12, 14) },
+            LexError::MalformedNumber { problem: NumberProblem::OutOfRange("u8".to_string()), span: span(15, 20) },
+        ]
+    );
+    let (tokens, errors) = Scanner::scan_bytes(b"let \xFF\xFEx = 1;", 0);
+    assert_eq!(errors, vec![LexError::InvalidUtf8 { span: span(4, 6) }]);
+    assert_eq!(tokens[1].lexeme, "x");
+    assert_eq!(tokens[1].span, span(6, 7));
+}
+
+#[test]
+fn bad_literals_still_become_the_token_they_meant_to_be() {
+    let tokens = Scanner::scan("f(\"a\\q\", 1.5u8, 'ab', 0x, \"open");
+    let types = tokens.iter().map(|token| token.token_type).collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            TokenType::Ident, TokenType::LParen, TokenType::String, TokenType::Comma, TokenType::Float,
+            TokenType::Comma, TokenType::Char, TokenType::Comma, TokenType::Int, TokenType::Comma,
+            TokenType::String, TokenType::EOF,
+        ]
+    );
+    assert_eq!(tokens[2].value, Some(TokenValue::Str("a\\q".to_string())));
+    assert_eq!(tokens[10].value, Some(TokenValue::Str("open".to_string())));
+    let tokens = Scanner::scan("let x = 1 ` + 2;");
+    assert!(Program::parse(&mut TokenStream::new(&Scanner::scan("fn f() { let x = 1 ` + 2; }"))).1.is_empty());
+    assert!(tokens.iter().all(|token| token.token_type != TokenType::Error));
+}
+
+#[test]
+fn suggestions_fix_the_source() {
+    let fixed = |source: &str| {
+        let errors = lex_errors(source);
+        let suggestion = errors[0].suggestion().unwrap_or_else(|| panic!("no suggestion for {errors:?}"));
+        suggestion.edit.apply(source)
+    };
+    assert_eq!(fixed("x \u{2212} 1"), "x - 1");
+    assert_eq!(fixed("\u{201C}hi\u{201D}"), "\"hi\u{201D}");
+    assert_eq!(fixed("x ~ y"), "x  y");
+    assert_eq!(fixed("\"abc"), "\"abc\"");
+    assert_eq!(fixed("/* abc"), "/* abc*/");
+    assert_eq!(fixed("\"a\\qb\""), "\"a\\\\qb\"");
+    assert_eq!(fixed("'ab'"), "\"ab\"");
+    assert_eq!(fixed("1.5u8"), "1.5f64");
+    for source in ["x ~ y", "\"abc", "/* abc", "\"a\\qb\"", "'ab'", "1.5u8"] {
+        assert!(lex_errors(&fixed(source)).is_empty(), "{source:?} is still broken after its fix");
+    }
+}
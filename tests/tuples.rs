@@ -0,0 +1,141 @@
+//! Tuples: the types, the values, `.0` to get at an element, and the patterns that take
+//! them apart.
+
+mod common;
+
+use calcify::{
+    ast::{
+        decl::Type as ASTType,
+        expresions::Expresion,
+        statments::{Pattern, Statment},
+    },
+    ir::{
+        ir_chunk::{Calc, IRCode, ValueAccess},
+        ir_lowering::lower_function,
+        ir_match_check::check_function,
+        ir_type_data::{CompileError, IR},
+    },
+};
+
+use common::{function, parse};
+
+/// The type of the only parameter of `fn go(x: <type_>) {}`.
+fn type_(type_: &str) -> ASTType {
+    let program = parse(&format!("fn go(x: {type_}) {{}}"));
+    function(&program).sig.parameters[0].1.clone()
+}
+
+/// The value of the `let` that `fn go() { <body> }` starts with.
+fn let_value(body: &str) -> Expresion {
+    let Statment::VarCreation(var_creation) = first(body) else { panic!("{body:?}") };
+    let Statment::Expresion(value) = *var_creation.value else { panic!("{body:?}") };
+    value
+}
+
+/// The first statment of `fn go() { <body> }`.
+fn first(body: &str) -> Statment {
+    let program = parse(&format!("fn go() {{ {body} }}"));
+    function(&program).body.statments[0].clone()
+}
+
+fn empty() -> IR {
+    IR::try_from(&parse("")).expect("there is nothing to go wrong")
+}
+
+#[test]
+fn tuple_types_parse() {
+    assert!(matches!(type_("(Int, [Float])"), ASTType::Tuple(types, _) if types.len() == 2));
+    assert!(matches!(type_("(Int,)"), ASTType::Tuple(types, _) if types.len() == 1));
+    assert!(matches!(type_("((Int, Int), Bool,)"), ASTType::Tuple(types, _) if matches!(&types[0], ASTType::Tuple(..))));
+    // without a comma it is still a dyn type
+    assert!(matches!(type_("(Area)"), ASTType::DynamicType(..)));
+    assert!(matches!(type_("(Area + Debug)"), ASTType::DynamicType(..)));
+    assert!(matches!(type_("()"), ASTType::Unit(_)));
+}
+
+#[test]
+fn tuple_values_and_their_elements() {
+    let Expresion::Tuple(tuple) = let_value("let t = (1, (2, 3.5), \"s\",);") else { panic!() };
+    assert_eq!(tuple.elements.len(), 3);
+    assert!(matches!(&tuple.elements[1], Expresion::Tuple(inner) if inner.elements.len() == 2));
+    assert!(matches!(let_value("let one = (1,);"), Expresion::Tuple(tuple) if tuple.elements.len() == 1));
+    assert!(matches!(let_value("let grouped = (1);"), Expresion::Parens(_)));
+    // `t.0.1` scans as `t` `.` `0.1`, and is still two accesses
+    let Statment::Expresion(Expresion::FieldAcess(outer)) = first("t.0.1;") else { panic!() };
+    assert_eq!(outer.field.lexeme, "1");
+    assert!(matches!(&*outer.expr, Expresion::FieldAcess(inner) if inner.field.lexeme == "0"));
+}
+
+#[test]
+fn tuple_patterns_parse() {
+    let Statment::VarCreation(var_creation) = first("let (a, (b,), _) = t;") else { panic!() };
+    let Pattern::Tuple(tuple) = &var_creation.pattern else { panic!() };
+    assert_eq!(tuple.elements.len(), 3);
+    assert!(matches!(&tuple.elements[1], Pattern::Tuple(one) if one.elements.len() == 1));
+    let Statment::VarCreation(var_creation) = first("let (a) = t;") else { panic!() };
+    assert!(matches!(var_creation.pattern, Pattern::Binding(_)));
+}
+
+#[test]
+fn tuples_in_a_match() {
+    let check = |body: &str| {
+        let program = parse(&format!("fn go(a: Bool, b: Bool) {{ {body} }}"));
+        check_function(&empty(), function(&program))
+    };
+    for body in [
+        "match (a, b) { (true, _) => 1, (false, true) => 2, (false, false) => 3 }",
+        "let (x, (y, _)) = (a, (b, 1));",
+        "if let (true, x) (a, b) {}",
+    ] {
+        assert!(check(body).is_ok(), "{body:?}: {:?}", check(body));
+    }
+    assert!(matches!(check("match (a, b) { (true, _) => 1, (_, true) => 2 }"), Err(CompileError::NonExhaustiveMatch(..))));
+    assert!(matches!(check("let (true, x) = (a, b);"), Err(CompileError::RefutablePatternInLet(_))));
+    assert!(matches!(
+        check("match (a, b) { (x, y) => 1, (x, y, z) => 2 }"),
+        Err(CompileError::MismatchedPatternType(expected, found, _)) if expected == "(_, _)" && found == "(_, _, _)"
+    ), "{:?}", check("match (a, b) { (x, y) => 1, (x, y, z) => 2 }"));
+}
+
+#[test]
+fn tuples_are_built_and_read_by_index() {
+    let program = parse("fn go(x: Int) { let t = (x, 2); let (a, b) = t; t.1; }");
+    let chunk = lower_function(&empty(), function(&program)).unwrap();
+    let t = match &chunk.code[..4] {
+        [IRCode::VarCreate(t, Calc::Alloc(2)), IRCode::FieldAssign(first, 0, Calc::Clone(ValueAccess::VarAccess(0))), IRCode::FieldAssign(second, 1, Calc::Clone(ValueAccess::IntConst(2, None))), IRCode::VarCreate(_, Calc::Clone(ValueAccess::VarAccess(from)))]
+            if first == t && second == t && from == t => *t,
+        other => panic!("{other:#?}"),
+    };
+    assert!(
+        chunk.code.iter().filter(|code| matches!(code, IRCode::VarCreate(_, Calc::FieldAccess(..)))).count() == 3,
+        "{:#?}",
+        chunk.code
+    );
+    assert!(matches!(chunk.code.last(), Some(IRCode::VarCreate(_, Calc::FieldAccess(ValueAccess::VarAccess(_), 1)))), "{t}: {:#?}", chunk.code);
+}
+
+#[test]
+fn a_name_is_bound_once_a_pattern() {
+    let check = |body: &str| {
+        let program = parse(&format!("struct P{{x: Int, y: Int}} fn go(p: P) {{ {body} }}"));
+        let ir = IR::try_from(&parse("struct P{x: Int, y: Int}")).unwrap();
+        check_function(&ir, function(&program))?;
+        lower_function(&ir, function(&program))
+    };
+    for body in ["let (a, b) = (1, 2);", "let (a, P{x, y: b}) = (1, p);", "match (1, 2) { (a, 1) | (1, a) => a, _ => 0 }"] {
+        assert!(check(body).is_ok(), "{body:?}: {:?}", check(body));
+    }
+    for (body, name) in [
+        ("let (a, a) = (1, 2);", "a"),
+        ("let (x, P{x, y}) = (1, p);", "x"),
+        ("let p @ (q, p) = (1, 2);", "p"),
+        ("match (1, 2) { (b, (c, b)) => 1 }", "b"),
+        ("if let (a, a) (1, 2) {}", "a"),
+    ] {
+        assert!(
+            matches!(check(body), Err(CompileError::DuplicateBinding(bound, _)) if bound == name),
+            "{body:?}: {:?}",
+            check(body)
+        );
+    }
+}
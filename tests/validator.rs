@@ -0,0 +1,92 @@
+//! `IR::try_from` checks that every type and trait the declarations name is there, in scope
+//! and given the right number of types, and gives back everything it found wrong at once.
+
+mod common;
+
+use calcify::ir::ir_type_data::{CompileError, Type, IR};
+
+use common::parse;
+
+fn ir(source: &str) -> Result<IR, CompileError> {
+    IR::try_from(&parse(source))
+}
+
+fn errors(source: &str) -> Vec<CompileError> {
+    match ir(source) {
+        Err(CompileError::Invalid(errors)) => errors,
+        other => panic!("{source:?} should not validate: {other:?}"),
+    }
+}
+
+#[test]
+fn well_formed_declarations() {
+    let source = "trait Add<T>{ fn add(self: Self, other: T) -> Self }
+trait Show{ fn show(self: Self) -> String }
+struct Point<T: Add<U>, U>{x: T, y: U, seen: [Bool], tag: (u8, f32, Char)}
+enum Shape<T>{Circle{center: Point<T, T>, radius: Float}, Empty}
+impl<T: Show> Show for Shape<T> { fn show(self: Self) -> String { \"shape\" } }
+impl Show for Point<Int, Int> { fn show(self: Self) -> String { \"point\" } }
+fn apply<T>(f: |T|: T, x: T, shown: (Show + Add<Int>)) -> T { f(x) }";
+    assert!(ir(source).is_ok(), "{:?}", ir(source));
+}
+
+#[test]
+fn unknown_types_anywhere() {
+    for source in [
+        "struct A{x: Nope}",
+        "enum A{B{x: [Nope]}}",
+        "trait A{ fn f(x: (Int, Nope)) }",
+        "impl Nope {}",
+        "fn f(x: |Nope|: Int) {}",
+        "fn f() -> Self {}",
+        "struct A<T>{x: T} fn f<U>(x: A<T>) {}",
+    ] {
+        assert!(
+            matches!(&errors(source)[..], [CompileError::NoTypeWithThatNameFound(_)]),
+            "{source:?}: {:?}",
+            errors(source)
+        );
+    }
+}
+
+#[test]
+fn constraints_and_impls_name_real_traits() {
+    for source in ["struct A<T: Nope>{}", "fn f(x: (Nope)) {}", "struct A{} impl Nope for A {}"] {
+        assert!(
+            matches!(&errors(source)[..], [CompileError::NoTraitWithThatNameFound(name, _)] if name == "Nope"),
+            "{source:?}: {:?}",
+            errors(source)
+        );
+    }
+}
+
+#[test]
+fn generics_are_given_the_right_number_of_types() {
+    for (source, expected, given) in [
+        ("struct A<T>{} struct B{a: A}", 1, 0),
+        ("struct A{} fn f(a: A<Int>) {}", 0, 1),
+        ("trait T<U>{} struct A<V: T>{}", 1, 0),
+        ("fn f(x: Int<Int>) {}", 0, 1),
+    ] {
+        assert!(
+            matches!(&errors(source)[..], [CompileError::NumberOfGenericsAndTypesGivenDoNotMatchInItem(_, e, g, _)] if (*e, *g) == (expected, given)),
+            "{source:?}: {:?}",
+            errors(source)
+        );
+    }
+    assert!(matches!(&errors("struct A<T>{x: T<Int>}")[..], [CompileError::GenericsCantHaveTypeParameters(_)]));
+}
+
+#[test]
+fn every_error_is_given_back() {
+    let found = errors("struct A{x: Nope, y: B<Int>} enum B{C{z: Gone}} fn f(a: A<Int>) -> Missing {}");
+    let names = found
+        .iter()
+        .map(|error| match error {
+            CompileError::NoTypeWithThatNameFound(Type::Actual(actual)) => actual.name.clone(),
+            CompileError::NumberOfGenericsAndTypesGivenDoNotMatchInItem(name, ..) => format!("{name} arity"),
+            other => panic!("{other:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Nope", "B arity", "Gone", "A arity", "Missing"]);
+}
@@ -0,0 +1,162 @@
+//! `pub` and `pub(module)`: how they parse, and who gets to use an item, field or method
+//! declared with or without them.
+
+mod common;
+
+use calcify::{
+    ast::decl::{Declaration, Program, Visibility},
+    ir::{
+        ir_type_data::{CompileError, IR},
+        ir_visibility::check_program,
+    },
+};
+
+use common::{load_clean, parse, parse_errors};
+
+/// Links the crate and checks the fields and methods its functions use.
+fn check(files: &[(&str, &str)]) -> Result<(), CompileError> {
+    let crate_ = load_clean(files);
+    let program = crate_.link()?;
+    // only the types are needed, the bodies are checked as they are written
+    let types = Program {
+        docs: vec![],
+        code: program
+            .code
+            .iter()
+            .filter(|declaration| matches!(declaration, Declaration::StructDecl(_) | Declaration::EnumDecl(_)))
+            .cloned()
+            .collect(),
+    };
+    let ir = IR::try_from(&types)?;
+    let modules = crate_.modules.iter().map(|module| module.path.clone()).collect::<Vec<_>>();
+    check_program(&ir, &program, &modules)
+}
+
+const GEO: &str = "
+pub struct Point{pub x: Int, pub(module) y: Int, secret: Int}
+pub fn origin() -> Point { Point{x: 0, y: 0, secret: 0} }
+impl Point {
+    pub fn new() -> Self { Point{x: 0, y: 0, secret: 0} }
+    fn hidden(self: Self) -> Int { self.secret }
+    const LIMIT: Int = 10;
+}
+";
+
+#[test]
+fn modifiers_on_items_fields_and_methods() {
+    let source = "/// a point\npub struct Point{pub x: Int, pub(module) y: Int, z: Int}\npub(module) fn f() {}\nenum E{}\nimpl Point { pub fn g() {} fn h() {} }";
+    let program = parse(source);
+    let Declaration::StructDecl(point) = &program.code[0] else { panic!() };
+    assert_eq!(point.visibility, Visibility::Public);
+    assert_eq!(point.docs.len(), 1);
+    let fields = point.fields.iter().map(|field| field.visibility).collect::<Vec<_>>();
+    assert_eq!(fields, [Visibility::Public, Visibility::Module, Visibility::Private]);
+    let Declaration::FunctionDecl(f) = &program.code[1] else { panic!() };
+    assert_eq!(f.visibility, Visibility::Module);
+    assert_eq!(&source[f.span.byte_start..f.span.byte_end], "pub(module) fn f() {}");
+    let Declaration::EnumDecl(e) = &program.code[2] else { panic!() };
+    assert_eq!(e.visibility, Visibility::Private);
+    let Declaration::ImplDecl(impl_) = &program.code[3] else { panic!() };
+    let methods = impl_.funcs.iter().map(|function| function.visibility).collect::<Vec<_>>();
+    assert_eq!(methods, [Visibility::Public, Visibility::Private]);
+}
+
+#[test]
+fn misplaced_modifiers() {
+    for (source, note) in [
+        ("pub impl Point {}", "`impl` can't be `pub`"),
+        ("pub use a::B;", "`use` can't be `pub`"),
+        ("pub(crate) struct A{}", "expected `module` here"),
+    ] {
+        let errors = parse_errors(source);
+        assert_eq!(errors[0].note.as_deref(), Some(note), "{source:?}: {errors:?}");
+    }
+}
+
+#[test]
+fn items_outside_their_module() {
+    let private = |files: &[(&str, &str)]| match load_clean(files).link() {
+        Err(CompileError::PrivateItem(name, used, declared)) => (name, used.file_id, declared.file_id),
+        other => panic!("{files:?} gave {other:?}"),
+    };
+    assert_eq!(
+        private(&[("main.cal", "mod geo; fn f(p: geo::Point) {}"), ("geo.cal", "struct Point{}")]),
+        ("geo::Point".to_string(), 0, 1)
+    );
+    assert_eq!(
+        private(&[("main.cal", "mod geo; use geo::origin;"), ("geo.cal", "fn origin() {}")]).0,
+        "geo::origin"
+    );
+    assert_eq!(
+        private(&[("main.cal", "mod geo; fn f() { geo::origin(); }"), ("geo.cal", "fn origin() {}")]).0,
+        "geo::origin"
+    );
+    // `pub(module)` reaches down into the modules inside, but not back up
+    assert_eq!(
+        private(&[
+            ("main.cal", "mod geo; fn f(p: geo::Point) {}"),
+            ("geo.cal", "mod inner; pub(module) struct Point{}"),
+            ("geo/inner.cal", "fn f(p: crate::geo::Point) {}"),
+        ])
+        .1,
+        0
+    );
+    // and a private item stays in its own module only
+    assert_eq!(
+        private(&[("main.cal", "mod geo; struct Point{}"), ("geo.cal", "use crate::Point;")]).0,
+        "Point"
+    );
+}
+
+#[test]
+fn fields_and_methods_outside_their_module() {
+    let private_field = |body: &str| match check(&[("main.cal", &format!("mod geo; use geo::Point; {body}")), ("geo.cal", GEO)]) {
+        Err(CompileError::PrivateField(type_, field, used, declared)) => {
+            assert_eq!((used.file_id, declared.file_id), (0, 1));
+            format!("{type_}.{field}")
+        }
+        other => panic!("{body:?} gave {other:?}"),
+    };
+    assert_eq!(private_field("fn f(p: Point) -> Int { p.secret }"), "geo::Point.secret");
+    assert_eq!(private_field("fn f(p: Point) -> Int { p.y }"), "geo::Point.y");
+    assert_eq!(private_field("fn f() { let p = Point{x: 1, secret: 3}; }"), "geo::Point.secret");
+    assert_eq!(private_field("fn f() { let p = geo::origin(); let s = (p).secret; }"), "geo::Point.secret");
+    assert_eq!(private_field("fn f() -> Int { Point::new().secret }"), "geo::Point.secret");
+    assert_eq!(private_field("fn f(p: Point) { let Point{secret, ..} = p; }"), "geo::Point.secret");
+    for body in [
+        "fn f(p: Point) -> Int { let q = match 1 { _ => p }; q.secret }",
+        "fn f(p: Point) -> Int { let t = (p, 1); t.0.secret }",
+        "fn f(p: Point) -> Int { let (q, _) = (1, p); let (_, r) = (q, p); r.secret }",
+        "fn f(ps: [Point]) -> Int { ps[0].secret }",
+        "fn f(p: Point) -> Int { let ps = [p]; ps[0].secret }",
+        "fn f(p: Point) -> Int { let g = |q: Int| p; g(1).secret }",
+        "static S: Int = geo::origin().secret;",
+        "const S: Int = Point::new().secret;",
+        "struct Q{} impl Q { const S: Int = geo::origin().secret; }",
+    ] {
+        assert_eq!(private_field(body), "geo::Point.secret", "{body:?}");
+    }
+    assert!(matches!(
+        check(&[("main.cal", "mod geo; fn f(p: geo::Point) -> Int { p.hidden() }"), ("geo.cal", GEO)]),
+        Err(CompileError::PrivateItem(name, _, _)) if name == "geo::Point::hidden"
+    ));
+    assert!(matches!(
+        check(&[("main.cal", "mod geo; fn f() -> Int { geo::Point::LIMIT }"), ("geo.cal", GEO)]),
+        Err(CompileError::PrivateItem(name, _, _)) if name == "geo::Point::LIMIT"
+    ));
+}
+
+#[test]
+fn fields_and_methods_that_can_be_seen() {
+    for (root, inner) in [
+        ("mod geo; fn f(p: geo::Point) -> Int { p.x + geo::Point::new().x }", ""),
+        // the type isn't known, so there's nothing to check
+        ("mod geo; fn f(p: Int) -> Int { p.secret }", ""),
+        ("mod geo;", "fn f(p: crate::geo::Point) -> Int { p.y }"),
+    ] {
+        let geo = format!("mod inner; {GEO}");
+        let files = [("main.cal", root), ("geo.cal", &geo), ("geo/inner.cal", inner)];
+        assert!(check(&files).is_ok(), "{files:?}: {:?}", check(&files));
+    }
+    assert!(check(&[("main.cal", "mod geo;"), ("geo.cal", GEO)]).is_ok());
+}
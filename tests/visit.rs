@@ -0,0 +1,118 @@
+//! The shared walk over function bodies: it reaches every node, opens scopes where the
+//! language does, and a `VisitMut` pass can change what it walks.
+
+mod common;
+
+use std::convert::Infallible;
+
+use calcify::{
+    ast::{
+        decl::Declaration,
+        expresions::Expresion,
+        visit::{walk_expresion, walk_expresion_mut, walk_function, walk_function_mut, Visit, VisitMut},
+    },
+    scanner::Token,
+};
+
+use common::{function, parse};
+
+/// Every name used, with whether it was bound in a scope around it at the time.
+#[derive(Default)]
+struct Uses {
+    scopes: Vec<Vec<String>>,
+    uses: Vec<(String, bool)>,
+}
+
+impl Visit for Uses {
+    type Error = Infallible;
+    fn visit_expresion(&mut self, expresion: &Expresion) -> Result<(), Infallible> {
+        if let Expresion::VarAccess(name) = expresion {
+            let bound = self.scopes.iter().flatten().any(|bound| *bound == name.lexeme);
+            self.uses.push((name.lexeme.clone(), bound));
+        }
+        walk_expresion(self, expresion)
+    }
+    fn scoped(&mut self, inside: impl FnOnce(&mut Self) -> Result<(), Infallible>) -> Result<(), Infallible> {
+        self.scopes.push(vec![]);
+        let result = inside(self);
+        self.scopes.pop();
+        result
+    }
+    fn bind(&mut self, name: &Token) {
+        self.scopes.last_mut().expect("bound in a scope").push(name.lexeme.clone());
+    }
+}
+
+fn uses(source: &str) -> Vec<(String, bool)> {
+    let program = parse(source);
+    let mut visitor = Uses::default();
+    let Ok(()) = walk_function(&mut visitor, function(&program));
+    visitor.uses
+}
+
+#[test]
+fn every_use_is_reached_with_what_is_bound_around_it() {
+    let found = uses(
+        "fn go(a: Int) {
+    let b = a;
+    { let c = b; }
+    c;
+    if let Some.X{d} e { d } else { f }
+    for i in g { i; }
+    i;
+    match h { (j, 1) if j > k => j, _ => l };
+    let m = |n: Int| n + o;
+    fn nested(p: Int) -> Int { p + q }
+    nested(b)
+}",
+    );
+    let expected = [
+        ("a", true),
+        ("b", true),
+        ("c", false),
+        ("e", false),
+        ("d", true),
+        ("f", false),
+        ("g", false),
+        ("i", true),
+        ("i", false),
+        ("h", false),
+        ("j", true),
+        ("k", false),
+        ("j", true),
+        ("l", false),
+        ("n", true),
+        ("o", false),
+        ("p", true),
+        ("q", false),
+        ("nested", true),
+        ("b", true),
+    ];
+    let expected = expected.map(|(name, bound)| (name.to_string(), bound));
+    assert_eq!(found, expected);
+}
+
+/// Renames every use of `from` to `to`.
+struct Rename(&'static str, &'static str);
+
+impl VisitMut for Rename {
+    type Error = Infallible;
+    fn visit_expresion(&mut self, expresion: &mut Expresion) -> Result<(), Infallible> {
+        match expresion {
+            Expresion::VarAccess(name) if name.lexeme == self.0 => name.lexeme = self.1.to_string(),
+            _ => {}
+        }
+        walk_expresion_mut(self, expresion)
+    }
+}
+
+#[test]
+fn a_mutable_pass_changes_what_it_walks() {
+    let mut program = parse("fn go(x: Int) -> Int { let f = |y: Int| x + y; f(x) }");
+    let Declaration::FunctionDecl(go) = &mut program.code[0] else { panic!() };
+    let Ok(()) = walk_function_mut(&mut Rename("x", "z"), go);
+    let mut after = Uses::default();
+    let Ok(()) = walk_function(&mut after, go);
+    let names = after.uses.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+    assert_eq!(names, ["z", "y", "f", "z"]);
+}